@@ -3,15 +3,13 @@ use crate::{
     avs::NonSignerStakesAndSignature,
     avs::{
         writer::IncredibleSquaringWriter,
-        IncredibleSquaringContractManager, SetupConfig, SignedTaskResponse, {Task, TaskResponse},
+        IncredibleSquaringContractManager, SetupConfig, {Task, TaskResponse},
     },
-    get_task_response_digest,
     operator::OperatorError,
 };
 use alloy_primitives::U256;
-use alloy_sol_types::SolType;
 use eigen_utils::{
-    node_api::tokiort::TokioIo,
+    aggregator::{auth::OperatorIdResolver, limits::ServerLimits, server::AggregatorServer},
     services::{
         avs_registry::AvsRegistryServiceChainCaller,
         bls_aggregation::{
@@ -19,22 +17,16 @@ use eigen_utils::{
         },
         operator_info::OperatorInfoServiceTrait,
     },
+    tls::TlsConfig,
     types::{quorum_ids_to_bitmap, QuorumNum, QuorumThresholdPercentage, TaskIndex},
     Config,
 };
-use http_body_util::{BodyExt, Full};
-use hyper::{
-    body::{self, Bytes},
-    server::conn::http1,
-    service::service_fn,
-    Method, Request, Response, StatusCode,
-};
-use std::{collections::HashMap, convert::Infallible, sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use tokio::{
-    net::TcpListener,
     sync::{broadcast, RwLock},
     time::interval,
 };
+use tokio_util::sync::CancellationToken;
 
 // Constants
 pub const TASK_CHALLENGE_WINDOW_BLOCK: u64 = 100;
@@ -43,6 +35,8 @@ pub const QUORUM_THRESHOLD_NUMERATOR: u8 = 100;
 pub const QUORUM_THRESHOLD_DENOMINATOR: u8 = 100;
 pub const QUERY_FILTER_FROM_BLOCK: u64 = 1;
 pub const QUORUM_NUMBERS: &[QuorumNum] = &[QuorumNum(0)];
+pub const TASK_TIME_TO_EXPIRY: Duration =
+    Duration::from_secs(TASK_CHALLENGE_WINDOW_BLOCK * BLOCK_TIME_SECONDS);
 
 #[derive(Clone)]
 pub struct Aggregator<T, I>
@@ -50,11 +44,11 @@ where
     T: Config,
     I: OperatorInfoServiceTrait,
 {
-    server_ip_port_addr: String,
     incredible_squaring_contract_manager: IncredibleSquaringContractManager<T>,
-    bls_aggregation_service: BlsAggregatorService<T, I>,
+    bls_aggregation_service: BlsAggregatorService<AvsRegistryServiceChainCaller<T, I>>,
+    aggregator_server:
+        AggregatorServer<TaskResponse, BlsAggregatorService<AvsRegistryServiceChainCaller<T, I>>>,
     tasks: Arc<RwLock<HashMap<u32, Task>>>,
-    task_responses: Arc<RwLock<HashMap<u32, HashMap<U256, TaskResponse>>>>,
 }
 
 impl<T, I> Aggregator<T, I>
@@ -74,7 +68,8 @@ where
             config.eth_client_ws.clone(),
             config.signer.clone(),
         )
-        .await?;
+        .await?
+        .with_log_confirmations(config.log_confirmations);
         let avs_chain_caller = AvsRegistryServiceChainCaller::build(
             incredible_squaring_contract_manager.service_manager_addr,
             config.registry_coordinator_addr,
@@ -89,17 +84,43 @@ where
         .await?;
         let (tx, _) = broadcast::channel(100);
         let bls_aggregation_service = BlsAggregatorService::new(tx, avs_chain_caller);
+        let aggregator_server =
+            AggregatorServer::new(&server_ip_port_addr, bls_aggregation_service.clone());
 
         Ok(Self {
-            server_ip_port_addr,
             incredible_squaring_contract_manager,
             bls_aggregation_service,
+            aggregator_server,
             tasks: Arc::new(RwLock::new(HashMap::new())),
-            task_responses: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
-    pub async fn start(self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Only accepts signed task responses carrying the ECDSA signature of the operator they
+    /// claim to come from.
+    pub fn with_operator_authentication(mut self, resolver: impl OperatorIdResolver) -> Self {
+        self.aggregator_server = self
+            .aggregator_server
+            .with_operator_authentication(resolver);
+        self
+    }
+
+    pub fn with_server_limits(mut self, limits: ServerLimits) -> Self {
+        self.aggregator_server = self.aggregator_server.with_limits(limits);
+        self
+    }
+
+    /// Serves operators over TLS, requiring their client certificates if the config pins them.
+    pub fn with_tls(mut self, tls_config: TlsConfig) -> Self {
+        self.aggregator_server = self.aggregator_server.with_tls(tls_config);
+        self
+    }
+
+    /// Sends a new task every 10 seconds and submits aggregated responses on chain until
+    /// `shutdown` is cancelled. Responses already aggregated by then are still submitted.
+    pub async fn start(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         // Initialize task number
         let mut task_num = 0;
 
@@ -121,6 +142,7 @@ where
         // Continuously send tasks and process responses
         loop {
             tokio::select! {
+                _ = shutdown.cancelled() => break,
                 _ = ticker.tick() => {
                     self.send_new_task(U256::from(task_num)).await?;
                     task_num += 1;
@@ -133,6 +155,13 @@ where
                 }
             }
         }
+
+        while let Ok(bls_agg_service_resp) = receiver.try_recv() {
+            self.send_aggregated_response_to_contract(bls_agg_service_resp)
+                .await;
+        }
+        log::info!("Aggregator stopped");
+        Ok(())
     }
 
     async fn send_new_task(&self, num_to_square: U256) -> Result<(), Box<dyn std::error::Error>> {
@@ -157,8 +186,6 @@ where
 
         let quorum_threshold_percentages =
             vec![QuorumThresholdPercentage(QUORUM_THRESHOLD_NUMERATOR); QUORUM_NUMBERS.len()];
-        let task_time_to_expiry =
-            Duration::from_secs(TASK_CHALLENGE_WINDOW_BLOCK * BLOCK_TIME_SECONDS);
 
         self.bls_aggregation_service
             .initialize_new_task(
@@ -166,7 +193,7 @@ where
                 new_task.taskCreatedBlock,
                 quorum_ids_to_bitmap(QUORUM_NUMBERS),
                 quorum_threshold_percentages,
-                task_time_to_expiry,
+                TASK_TIME_TO_EXPIRY,
             )
             .await?;
 
@@ -179,6 +206,9 @@ where
     ) {
         if let Some(err) = bls_agg_service_resp.err {
             log::error!("BlsAggregationServiceResponse contains an error: {}", err);
+            self.aggregator_server
+                .remove_task_responses(bls_agg_service_resp.task_index)
+                .await;
             return;
         }
 
@@ -224,15 +254,13 @@ where
             tasks.get(&bls_agg_service_resp.task_index).cloned()
         };
 
-        let task_response = {
-            let task_responses = self.task_responses.read().await;
-            task_responses
-                .get(&bls_agg_service_resp.task_index)
-                .and_then(|responses| {
-                    responses.get(&bls_agg_service_resp.task_response_digest.into())
-                })
-                .cloned()
-        };
+        let task_response = self
+            .aggregator_server
+            .task_response(
+                bls_agg_service_resp.task_index,
+                bls_agg_service_resp.task_response_digest,
+            )
+            .await;
 
         if let (Some(task), Some(task_response)) = (task, task_response) {
             if let Err(err) = self
@@ -243,100 +271,16 @@ where
                 log::error!("Aggregator failed to respond to task: {}", &err.to_string());
             }
         }
+        self.aggregator_server
+            .remove_task_responses(bls_agg_service_resp.task_index)
+            .await;
     }
 
-    async fn process_signed_task_response(
-        &self,
-        req: Request<body::Incoming>,
-    ) -> Result<Response<Full<Bytes>>, Box<dyn std::error::Error + Send + Sync>> {
-        let body_bytes = req.collect().await?.to_bytes();
-        let signed_task_response: SignedTaskResponse = serde_json::from_slice(&body_bytes)?;
-
-        log::info!("Received signed task response: {:?}", signed_task_response);
-
-        let task_response = TaskResponse::abi_decode(&signed_task_response.task_response, true)?;
-        let task_index = task_response.referenceTaskIndex;
-        let task_response_digest = get_task_response_digest(&task_response);
-        let task_response_digest_u256 = U256::from_le_bytes(task_response_digest.0);
-
-        log::info!(
-            "Aggregator received task response: \n\tTask Index: {:?}\n\tResult: {:?}",
-            task_response.referenceTaskIndex,
-            task_response.numberSquared
-        );
-
-        {
-            let mut task_responses = self.task_responses.write().await;
-            task_responses
-                .entry(task_index)
-                .or_insert_with(HashMap::new);
-            let task_response_map = task_responses.get_mut(&task_index).unwrap();
-            task_response_map
-                .entry(task_response_digest_u256)
-                .or_insert(task_response);
-        }
-
-        self.bls_aggregation_service
-            .process_new_signature(
-                task_index,
-                task_response_digest.to_vec(),
-                signed_task_response.bls_signature,
-                signed_task_response.operator_id,
-            )
-            .await?;
-
-        Ok(Response::new(Full::new(Bytes::from(
-            "Task response processed successfully",
-        ))))
-    }
-
-    pub async fn start_server(self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let listener = TcpListener::bind(&self.server_ip_port_addr).await?;
-        loop {
-            log::info!(
-                "Aggregator server listening on {}",
-                self.server_ip_port_addr
-            );
-            let (stream, _) = listener.accept().await.unwrap();
-            let io = TokioIo::new(stream);
-            let this = Arc::new(self.clone());
-            log::info!("Aggregator serving connection...");
-            tokio::task::spawn(async move {
-                if let Err(err) = http1::Builder::new()
-                    .serve_connection(
-                        io,
-                        service_fn(move |req| {
-                            let this = Arc::clone(&this);
-                            async move { this.router(req).await }
-                        }),
-                    )
-                    .await
-                {
-                    log::info!("Error serving connection: {:?}", err);
-                }
-            });
-        }
-    }
-
-    async fn router(
-        &self,
-        req: Request<body::Incoming>,
-    ) -> Result<Response<Full<Bytes>>, Infallible> {
-        log::info!("Routing Request: {:?}", req);
-        match (req.method(), req.uri().path()) {
-            (&Method::POST, "/Aggregator.ProcessSignedTaskResponse") => {
-                let _ = self.process_signed_task_response(req).await;
-                Ok(Response::builder()
-                    .status(StatusCode::OK)
-                    .body(Full::new(Bytes::from(
-                        "Task response processed successfully",
-                    )))
-                    .unwrap())
-            }
-            _ => Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::from("Not Found")))
-                .unwrap()),
-        }
+    /// Serves operator submissions until `shutdown` is cancelled.
+    pub async fn start_server(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.aggregator_server.start(shutdown).await
     }
 }
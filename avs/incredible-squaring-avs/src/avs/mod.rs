@@ -5,11 +5,9 @@ pub mod writer;
 use alloy_primitives::{Address, Bytes, U256};
 use alloy_rpc_types::{Log, TransactionReceipt};
 use eigen_contracts::RegistryCoordinator;
-use eigen_utils::{
-    crypto::bls::{G1Point, Signature},
-    types::{AvsError, OperatorId},
-    Config,
-};
+pub use eigen_utils::aggregator::SignedTaskResponse;
+use eigen_utils::subscriptions::confirmed::clamp_confirmations;
+use eigen_utils::{crypto::bls::G1Point, types::AvsError, Config};
 pub use erc_20_mock::Erc20Mock;
 pub use incredible_squaring_service_manager::IncredibleSquaringServiceManager;
 pub use incredible_squaring_task_manager::IBLSSignatureChecker::NonSignerStakesAndSignature;
@@ -19,7 +17,7 @@ pub use incredible_squaring_task_manager::IIncredibleSquaringTaskManager::{
 };
 pub use incredible_squaring_task_manager::IncredibleSquaringTaskManager;
 pub use incredible_squaring_task_manager::BN254 as Bn254;
-use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 mod incredible_squaring_task_manager {
     alloy_sol_types::sol!(
@@ -58,13 +56,6 @@ pub struct TaskResponseData {
     pub non_signing_operator_keys: Vec<Bn254::G1Point>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignedTaskResponse {
-    pub task_response: Vec<u8>,
-    pub bls_signature: Signature,
-    pub operator_id: OperatorId,
-}
-
 #[derive(Debug, Clone)]
 pub struct SetupConfig<T: Config> {
     pub registry_coordinator_addr: Address,
@@ -74,6 +65,8 @@ pub struct SetupConfig<T: Config> {
     pub eth_client_http: T::PH,
     pub eth_client_ws: T::PW,
     pub signer: T::S,
+    /// Subscribed logs are delivered once this many blocks were built on top of them
+    pub log_confirmations: u64,
 }
 
 #[derive(Clone)]
@@ -83,6 +76,11 @@ pub struct IncredibleSquaringContractManager<T: Config> {
     pub eth_client_http: T::PH,
     pub eth_client_ws: T::PW,
     pub signer: T::S,
+    /// Subscribed logs are delivered once this many blocks were built on top of them
+    pub log_confirmations: u64,
+    /// When set, subscribed logs are polled over the HTTP provider at this interval instead
+    /// of subscribed to over the websocket provider
+    pub log_polling_interval: Option<Duration>,
 }
 
 impl<T: Config> IncredibleSquaringContractManager<T> {
@@ -112,9 +110,27 @@ impl<T: Config> IncredibleSquaringContractManager<T> {
             eth_client_http,
             eth_client_ws,
             signer,
+            log_confirmations: 0,
+            log_polling_interval: None,
         })
     }
 
+    /// Delivers subscribed logs once `log_confirmations` blocks were built on top of them,
+    /// clamped to [`MAX_CONFIRMATIONS`] so that removals of confirmed logs are still reported.
+    ///
+    /// [`MAX_CONFIRMATIONS`]: eigen_utils::subscriptions::confirmed::MAX_CONFIRMATIONS
+    pub fn with_log_confirmations(mut self, log_confirmations: u64) -> Self {
+        self.log_confirmations = clamp_confirmations(log_confirmations);
+        self
+    }
+
+    /// Polls for subscribed logs over the HTTP provider every `log_polling_interval` instead
+    /// of subscribing over the websocket provider, for endpoints that only expose HTTP.
+    pub fn with_log_polling(mut self, log_polling_interval: Duration) -> Self {
+        self.log_polling_interval = Some(log_polling_interval);
+        self
+    }
+
     pub async fn create_new_task(
         &self,
         num_to_square: U256,
@@ -1,40 +1,66 @@
-use alloy_provider::Provider;
-use alloy_pubsub::Subscription;
-use alloy_rpc_types::{Filter, Log};
+use alloy_rpc_types::Filter;
 use alloy_sol_types::SolEvent;
 use async_trait::async_trait;
+use eigen_utils::subscriptions::confirmed::ConfirmedLogSubscriber;
+use eigen_utils::subscriptions::polling::PollingLogSubscriber;
+use eigen_utils::subscriptions::resilient::ResilientLogSubscriber;
+use eigen_utils::subscriptions::LogSubscription;
 use eigen_utils::{types::AvsError, Config};
 
 use super::{IncredibleSquaringContractManager, IncredibleSquaringTaskManager};
 
 #[async_trait]
 pub trait IncredibleSquaringSubscriber: Send + Sync {
-    async fn subscribe_to_new_tasks(&self) -> Result<Subscription<Log>, AvsError>;
+    async fn subscribe_to_new_tasks(&self) -> Result<LogSubscription, AvsError>;
 
-    async fn subscribe_to_task_responses(&self) -> Result<Subscription<Log>, AvsError>;
+    async fn subscribe_to_task_responses(&self) -> Result<LogSubscription, AvsError>;
 }
 
 #[async_trait]
 impl<T: Config> IncredibleSquaringSubscriber for IncredibleSquaringContractManager<T> {
-    async fn subscribe_to_new_tasks(&self) -> Result<Subscription<Log>, AvsError> {
-        let subscription = self
-            .eth_client_ws
-            .subscribe_logs(
-                &Filter::new().event(IncredibleSquaringTaskManager::NewTaskCreated::SIGNATURE),
-            )
-            .await?;
-
-        Ok(subscription)
+    async fn subscribe_to_new_tasks(&self) -> Result<LogSubscription, AvsError> {
+        self.subscribe_logs(
+            Filter::new().event(IncredibleSquaringTaskManager::NewTaskCreated::SIGNATURE),
+        )
+        .await
     }
 
-    async fn subscribe_to_task_responses(&self) -> Result<Subscription<Log>, AvsError> {
-        let subscription = self
-            .eth_client_ws
-            .subscribe_logs(
-                &Filter::new().event(IncredibleSquaringTaskManager::TaskResponded::SIGNATURE),
-            )
-            .await?;
+    async fn subscribe_to_task_responses(&self) -> Result<LogSubscription, AvsError> {
+        self.subscribe_logs(
+            Filter::new().event(IncredibleSquaringTaskManager::TaskResponded::SIGNATURE),
+        )
+        .await
+    }
+}
 
+impl<T: Config> IncredibleSquaringContractManager<T> {
+    async fn subscribe_logs(&self, filter: Filter) -> Result<LogSubscription, AvsError> {
+        let subscription = match self.log_polling_interval {
+            Some(log_polling_interval) => {
+                let subscription =
+                    PollingLogSubscriber::<_, T::TH>::new(self.eth_client_http.clone(), filter)
+                        .with_poll_interval(log_polling_interval)
+                        .subscribe()
+                        .await?;
+                ConfirmedLogSubscriber::<_, T::TH>::new(
+                    self.eth_client_http.clone(),
+                    self.log_confirmations,
+                )
+                .with_head_poll_interval(log_polling_interval)
+                .confirm(subscription)
+            }
+            None => {
+                let subscription =
+                    ResilientLogSubscriber::<_, T::TW>::new(self.eth_client_ws.clone(), filter)
+                        .subscribe()
+                        .await?;
+                ConfirmedLogSubscriber::<_, T::TW>::new(
+                    self.eth_client_ws.clone(),
+                    self.log_confirmations,
+                )
+                .confirm(subscription)
+            }
+        };
         Ok(subscription)
     }
 }
@@ -2,11 +2,14 @@ use alloy_primitives::U256;
 use alloy_provider::Provider;
 use alloy_rpc_types::Log;
 use alloy_sol_types::SolCall;
+use eigen_utils::subscriptions::LogEvent;
 use eigen_utils::types::{AvsError, TaskIndex};
 use eigen_utils::Config;
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
 
 use crate::avs::subscriber::IncredibleSquaringSubscriber;
 use crate::avs::writer::IncredibleSquaringWriter;
@@ -32,7 +35,8 @@ impl<T: Config> Challenger<T> {
             c.eth_client_ws,
             c.signer,
         )
-        .await?;
+        .await?
+        .with_log_confirmations(c.log_confirmations);
 
         Ok(Challenger {
             incredible_squaring_contract_manager,
@@ -41,7 +45,32 @@ impl<T: Config> Challenger<T> {
         })
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error>> {
+    /// Only acts on task and response logs once `log_confirmations` blocks were built on top
+    /// of them, so that responses reorganised out of the chain are not challenged. Overrides
+    /// the `log_confirmations` of the setup config.
+    pub fn with_log_confirmations(mut self, log_confirmations: u64) -> Self {
+        self.incredible_squaring_contract_manager = self
+            .incredible_squaring_contract_manager
+            .with_log_confirmations(log_confirmations);
+        self
+    }
+
+    /// Polls for task and response logs over the HTTP provider every `log_polling_interval`
+    /// instead of subscribing over the websocket provider, for endpoints that only expose
+    /// HTTP.
+    pub fn with_log_polling(mut self, log_polling_interval: Duration) -> Self {
+        self.incredible_squaring_contract_manager = self
+            .incredible_squaring_contract_manager
+            .with_log_polling(log_polling_interval);
+        self
+    }
+
+    /// Watches new tasks and their responses, challenging wrong responses, until `shutdown`
+    /// is cancelled.
+    pub async fn start(
+        &self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         log::info!("Starting Challenger.");
 
         let mut new_task_sub = self
@@ -58,7 +87,20 @@ impl<T: Config> Challenger<T> {
 
         loop {
             tokio::select! {
-                Ok(new_task) = new_task_sub.recv() => {
+                _ = shutdown.cancelled() => {
+                    log::info!("Challenger stopped");
+                    return Ok(());
+                }
+                Some(new_task) = new_task_sub.recv() => {
+                    let new_task = match new_task {
+                        LogEvent::Added(new_task) => new_task,
+                        LogEvent::Removed(new_task) => {
+                            let new_task: Log<IncredibleSquaringTaskManager::NewTaskCreated> = new_task.log_decode().unwrap();
+                            log::warn!("New task log removed by a reorg: {:?}", new_task);
+                            self.tasks.lock().await.remove(&new_task.inner.taskIndex);
+                            continue;
+                        }
+                    };
                     let new_task: Log<IncredibleSquaringTaskManager::NewTaskCreated> = new_task.log_decode().unwrap();
                     log::info!("New task created log received: {:?}", new_task);
                     let task_index = self.process_new_task_created_log(&new_task).await;
@@ -71,7 +113,19 @@ impl<T: Config> Challenger<T> {
                         continue;
                     }
                 }
-                Ok(task_response) = task_response_sub.recv() => {
+                Some(task_response) = task_response_sub.recv() => {
+                    let task_response = match task_response {
+                        LogEvent::Added(task_response) => task_response,
+                        LogEvent::Removed(task_response) => {
+                            let task_response: Log<IncredibleSquaringTaskManager::TaskResponded> = task_response.log_decode().unwrap();
+                            log::warn!("Task response log removed by a reorg: {:?}", task_response);
+                            self.task_responses
+                                .lock()
+                                .await
+                                .remove(&task_response.inner.taskResponse.referenceTaskIndex);
+                            continue;
+                        }
+                    };
                     let task_response: Log<IncredibleSquaringTaskManager::TaskResponded> = task_response.log_decode().unwrap();
                     log::info!("Task response log received: {:?}", task_response);
                     let task_index = self.process_task_response_log(&task_response).await;
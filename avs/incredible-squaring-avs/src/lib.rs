@@ -1,6 +1,7 @@
 use alloy_primitives::{keccak256, B256};
-use alloy_sol_types::SolValue;
+use alloy_sol_types::{SolType, SolValue};
 use avs::TaskResponse;
+use eigen_utils::{aggregator::AggregatorTaskResponse, types::TaskIndex};
 
 pub mod aggregator;
 pub mod avs;
@@ -12,3 +13,21 @@ pub fn get_task_response_digest(task_response: &TaskResponse) -> B256 {
     let encoded = task_response.abi_encode_packed();
     keccak256(encoded)
 }
+
+impl AggregatorTaskResponse for TaskResponse {
+    fn abi_decode_response(data: &[u8]) -> Result<Self, String> {
+        <TaskResponse as SolType>::abi_decode(data, true).map_err(|e| e.to_string())
+    }
+
+    fn abi_encode_response(&self) -> Vec<u8> {
+        SolValue::abi_encode(self)
+    }
+
+    fn task_index(&self) -> TaskIndex {
+        self.referenceTaskIndex
+    }
+
+    fn digest_preimage(&self) -> Vec<u8> {
+        self.abi_encode_packed()
+    }
+}
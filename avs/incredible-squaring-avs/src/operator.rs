@@ -1,16 +1,15 @@
 #![allow(dead_code)]
-use crate::aggregator::Aggregator;
+use crate::aggregator::{Aggregator, TASK_TIME_TO_EXPIRY};
 use crate::avs::subscriber::IncredibleSquaringSubscriber;
 use crate::avs::{
     IncredibleSquaringContractManager, IncredibleSquaringTaskManager, SetupConfig,
     SignedTaskResponse, TaskResponse,
 };
 use crate::get_task_response_digest;
-use crate::rpc_client::AggregatorRpcClient;
+use crate::rpc_client::{task_response_deadline, AggregatorRpcClient};
 use alloy_contract::private::Ethereum;
 use alloy_primitives::{Address, Bytes, ChainId, FixedBytes, Signature, B256, U256};
 use alloy_provider::{Provider, RootProvider};
-use alloy_pubsub::Subscription;
 use alloy_rpc_types::Log;
 use alloy_signer_local::PrivateKeySigner;
 use alloy_sol_types::SolValue;
@@ -23,8 +22,11 @@ use eigen_utils::crypto::bls::KeyPair;
 use eigen_utils::crypto::ecdsa::ToAddress;
 use eigen_utils::el_contracts::writer::ElWriter;
 use eigen_utils::el_contracts::ElChainContractManager;
-use eigen_utils::node_api::NodeApi;
+use eigen_utils::metrics::{EigenMetrics, MetricsServer, SdkCallsCollector};
+use eigen_utils::node_api::health::{BlsKeyHealthCheck, ProviderHealthCheck, TcpHealthCheck};
+use eigen_utils::node_api::{NodeApi, NodeService, ServiceStatus};
 use eigen_utils::services::operator_info::OperatorInfoServiceTrait;
+use eigen_utils::subscriptions::{LogEvent, LogSubscription};
 use eigen_utils::types::{AvsError, OperatorId, OperatorInfo};
 use eigen_utils::Config;
 use k256::ecdsa::{SigningKey, VerifyingKey};
@@ -33,7 +35,9 @@ use prometheus::Registry;
 use std::future::Future;
 use std::pin::Pin;
 use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use thiserror::Error;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 
 pub const AVS_NAME: &str = "incredible-squaring";
 pub const SEM_VER: &str = "0.0.1";
@@ -99,6 +103,8 @@ pub struct Operator<T: Config, I: OperatorInfoServiceTrait> {
     pub aggregator_server_ip_port_addr: String,
     pub aggregator_server: Aggregator<T, I>,
     pub aggregator_rpc_client: AggregatorRpcClient,
+    pub metrics_registry: Registry,
+    pub eigen_metrics: EigenMetrics,
 }
 
 #[derive(Clone, Debug)]
@@ -160,7 +166,17 @@ pub struct NodeConfig {
     pub node_api_ip_port_address: String,
     pub enable_node_api: bool,
     pub eth_rpc_url: String,
-    pub eth_ws_url: String,
+    /// RPC URLs to fail over to when `eth_rpc_url` is unavailable, in order of preference
+    pub eth_rpc_fallback_urls: Vec<String>,
+    /// Websocket URL new tasks are subscribed to over, required unless
+    /// `eth_log_polling_interval` is set
+    pub eth_ws_url: Option<String>,
+    /// When set, new tasks are polled for over `eth_rpc_url` at this interval instead of
+    /// subscribed to over `eth_ws_url`, for endpoints that only expose HTTP
+    pub eth_log_polling_interval: Option<Duration>,
+    /// Blocks built on top of new task and operator registry logs before they are acted on,
+    /// so that logs reorganised out of the chain are ignored
+    pub log_confirmations: u64,
     pub bls_private_key_store_path: String,
     pub ecdsa_private_key_store_path: String,
     pub incredible_squaring_service_manager_addr: String,
@@ -223,11 +239,36 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
         operator_info_service: I,
         signer: T::S,
     ) -> Result<Self, OperatorError> {
-        let _metrics_reg = Registry::new();
+        if config.eth_ws_url.is_none() && config.eth_log_polling_interval.is_none() {
+            return Err(OperatorError::WsEthClientError(
+                "eth_ws_url is required unless eth_log_polling_interval is set".to_string(),
+            ));
+        }
+        let metrics_registry = Registry::new();
+        let eigen_metrics = EigenMetrics::new(AVS_NAME, &metrics_registry)
+            .map_err(|e| OperatorError::MetricsServerError(e.to_string()))?;
         let operator_address = Address::from_str(&config.operator_address)
             .map_err(|e| OperatorError::AddressError(e.to_string()))?;
 
         let node_api = NodeApi::new(AVS_NAME, SEM_VER, &config.node_api_ip_port_address);
+        node_api.register_health_check(
+            NodeService::new(
+                "eth-http",
+                "Ethereum HTTP provider",
+                "Reachability of the Ethereum HTTP RPC endpoint",
+                ServiceStatus::Initializing,
+            ),
+            ProviderHealthCheck::<_, T::TH>::new(eth_client_http.clone()),
+        );
+        node_api.register_health_check(
+            NodeService::new(
+                "aggregator",
+                "Aggregator",
+                "Reachability of the aggregator server",
+                ServiceStatus::Initializing,
+            ),
+            TcpHealthCheck::new(&config.server_ip_port_address),
+        );
 
         log::info!("Reading BLS key");
         let bls_key_password =
@@ -237,6 +278,15 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             &bls_key_password,
         )
         .map_err(OperatorError::from)?;
+        node_api.register_health_check(
+            NodeService::new(
+                "bls-key",
+                "BLS key",
+                "BLS key pair used to sign task responses",
+                ServiceStatus::Initializing,
+            ),
+            BlsKeyHealthCheck::new(bls_keypair.clone()),
+        );
 
         log::info!("Reading ECDSA key");
         let ecdsa_key_password =
@@ -266,9 +316,10 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             eth_client_http: eth_client_http.clone(),
             eth_client_ws: eth_client_ws.clone(),
             signer: signer.clone(),
+            log_confirmations: config.log_confirmations,
         };
 
-        let incredible_squaring_contract_manager = IncredibleSquaringContractManager::build(
+        let mut incredible_squaring_contract_manager = IncredibleSquaringContractManager::build(
             setup_config.registry_coordinator_addr,
             setup_config.operator_state_retriever_addr,
             eth_client_http.clone(),
@@ -276,10 +327,15 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             signer.clone(),
         )
         .await
-        .map_err(|e| OperatorError::ContractManagerError(e.to_string()))?;
+        .map_err(|e| OperatorError::ContractManagerError(e.to_string()))?
+        .with_log_confirmations(config.log_confirmations);
+        if let Some(log_polling_interval) = config.eth_log_polling_interval {
+            incredible_squaring_contract_manager =
+                incredible_squaring_contract_manager.with_log_polling(log_polling_interval);
+        }
 
         log::info!("Building AVS Registry Contract Manager");
-        let avs_registry_contract_manager = AvsRegistryContractManager::build(
+        let mut avs_registry_contract_manager = AvsRegistryContractManager::build(
             Address::from_str(&config.incredible_squaring_service_manager_addr)
                 .map_err(|e| OperatorError::AddressError(e.to_string()))?,
             setup_config.registry_coordinator_addr,
@@ -291,7 +347,18 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             signer.clone(),
         )
         .await
-        .map_err(|e| OperatorError::ContractManagerError(e.to_string()))?;
+        .map_err(|e| OperatorError::ContractManagerError(e.to_string()))?
+        .with_log_confirmations(config.log_confirmations);
+        if let Some(log_polling_interval) = config.eth_log_polling_interval {
+            avs_registry_contract_manager =
+                avs_registry_contract_manager.with_log_polling(log_polling_interval);
+        }
+        if config.enable_metrics {
+            let sdk_calls_collector = SdkCallsCollector::new(AVS_NAME, &metrics_registry)
+                .map_err(|e| OperatorError::MetricsServerError(e.to_string()))?;
+            avs_registry_contract_manager =
+                avs_registry_contract_manager.with_call_metrics(sdk_calls_collector);
+        }
 
         log::info!("Building Aggregator Service...");
         let aggregator_service = Aggregator::build(
@@ -300,10 +367,12 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             config.server_ip_port_address.clone(),
         )
         .await
-        .map_err(|e| OperatorError::AggregatorRpcClientError(e.to_string()))?;
+        .map_err(|e| OperatorError::AggregatorRpcClientError(e.to_string()))?
+        .with_operator_authentication(avs_registry_contract_manager.clone());
 
         log::info!("Building Aggregator RPC Client...");
-        let aggregator_rpc_client = AggregatorRpcClient::new(config.server_ip_port_address.clone());
+        let aggregator_rpc_client = AggregatorRpcClient::new(config.server_ip_port_address.clone())
+            .with_ecdsa_signing_key(ecdsa_signing_key.clone());
 
         log::info!("Building Eigenlayer Contract Manager...");
         let eigenlayer_contract_manager = ElChainContractManager::build(
@@ -376,6 +445,8 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             aggregator_server_ip_port_addr: config.server_ip_port_address.clone(),
             aggregator_server: aggregator_service,
             aggregator_rpc_client,
+            metrics_registry,
+            eigen_metrics,
         };
 
         Ok(operator)
@@ -390,7 +461,10 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
         Ok(operator_is_registered)
     }
 
-    pub async fn start(self) -> Result<(), OperatorError> {
+    /// Runs the operator until `shutdown` is cancelled: serves the node API and aggregator,
+    /// and answers new tasks. On shutdown it stops taking new tasks and waits for pending
+    /// submissions to the aggregator and for both servers to drain.
+    pub async fn start(self, shutdown: CancellationToken) -> Result<(), OperatorError> {
         log::info!("Starting operator.");
         let operator_is_registered = self
             .avs_registry_contract_manager
@@ -398,31 +472,92 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
             .await?;
         log::info!("Operator registration status: {:?}", operator_is_registered);
 
+        // The servers are stopped only once pending submissions, which may target the
+        // aggregator server, have drained
+        let servers = TaskTracker::new();
+        let servers_shutdown = CancellationToken::new();
+        // Also stops the servers if the operator returns early with an error
+        let _servers_guard = servers_shutdown.clone().drop_guard();
         if self.config.enable_node_api {
-            if let Err(e) = self.node_api.start().await {
-                return Err(OperatorError::NodeApiError(e.to_string()));
-            }
+            let node_api = self.node_api.clone();
+            let node_api_shutdown = servers_shutdown.clone();
+            servers.spawn(async move {
+                if let Err(e) = node_api.start(node_api_shutdown).await {
+                    log::error!("Node API failed: {}", e);
+                }
+            });
         }
         let mut sub = self.subscribe_to_new_tasks().await?;
+        // Down while the subscription reconnects, and for good if it stops
+        let subscription_liveness = sub.liveness();
+        self.node_api.register_health_check(
+            NodeService::new(
+                "new-task-subscription",
+                "New task subscription",
+                "Connection of the subscription to new tasks",
+                ServiceStatus::Up,
+            ),
+            subscription_liveness.clone(),
+        );
+
+        if self.config.enable_metrics {
+            let metrics_server = MetricsServer::new(
+                &self.config.eigen_metrics_ip_port_address,
+                self.metrics_registry.clone(),
+            );
+            let metrics_server_shutdown = servers_shutdown.clone();
+            servers.spawn(async move {
+                if let Err(e) = metrics_server.start(metrics_server_shutdown).await {
+                    log::error!("Metrics server failed: {}", e);
+                }
+            });
+        }
 
         let server = self.aggregator_server.clone();
-        let aggregator_server = async move {
-            server.start_server().await.unwrap();
-        };
-        tokio::spawn(aggregator_server);
+        let aggregator_server_shutdown = servers_shutdown.clone();
+        servers.spawn(async move {
+            server
+                .start_server(aggregator_server_shutdown)
+                .await
+                .unwrap();
+        });
 
         log::info!("Subscribed to new tasks: {:?}", sub);
 
-        let value = sub
-            .recv()
-            .await
-            .map_err(|e| OperatorError::TaskError(e.to_string()))?;
-        log::info!("Received new task: {:?}", value);
+        tokio::select! {
+            value = sub.recv() => {
+                let value = value.ok_or_else(|| {
+                    OperatorError::WebsocketSubscriptionError(
+                        "New task subscription closed".to_string(),
+                    )
+                })?;
+                log::info!("Received new task: {:?}", value);
+            }
+            _ = shutdown.cancelled() => {}
+        }
+
+        let submissions = TaskTracker::new();
 
-        loop {
+        while !shutdown.is_cancelled() {
             log::info!("Waiting for new task submissions");
             tokio::select! {
-                Ok(new_task_created_log) = sub.recv() => {
+                _ = shutdown.cancelled() => {}
+                new_task_created_log = sub.recv() => {
+                    let new_task_created_log = match new_task_created_log {
+                        Some(LogEvent::Added(new_task_created_log)) => new_task_created_log,
+                        Some(LogEvent::Removed(new_task_created_log)) => {
+                            log::warn!("New task removed by a reorg: {:?}", new_task_created_log);
+                            continue;
+                        }
+                        None => {
+                            // No more tasks can be received, but the node API and the
+                            // aggregator server keep serving until shutdown
+                            log::error!("New task subscription closed");
+                            subscription_liveness.set_alive(false);
+                            shutdown.cancelled().await;
+                            continue;
+                        }
+                    };
                     log::info!("Received new task: {:?}", new_task_created_log);
                     // self.metrics.inc_num_tasks_received();
                     let log: Log<IncredibleSquaringTaskManager::NewTaskCreated> = new_task_created_log.log_decode().map_err(|e| OperatorError::TaskError(e.to_string()))?;
@@ -431,13 +566,23 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
                     if let Ok(signed_task_response) = self.sign_task_response(&task_response) {
                         log::info!("Sending signed task response to aggregator: {:?}", signed_task_response);
                         let agg_rpc_client = self.aggregator_rpc_client.clone();
-                        tokio::spawn(async move {
-                            agg_rpc_client.send_signed_task_response_to_aggregator(signed_task_response).await;
+                        let task_created_at = self.task_created_at(&log).await;
+                        let deadline = task_response_deadline(task_created_at, TASK_TIME_TO_EXPIRY);
+                        submissions.spawn(async move {
+                            let _ = agg_rpc_client.send_signed_task_response_to_aggregator(signed_task_response, deadline).await;
                         });
                     }
                 },
             }
         }
+
+        log::info!("Shutting down operator");
+        submissions.close();
+        submissions.wait().await;
+        servers_shutdown.cancel();
+        servers.close();
+        servers.wait().await;
+        Ok(())
     }
 
     pub fn config(&self) -> NodeConfig {
@@ -467,6 +612,32 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
         }
     }
 
+    /// Time the task was created at, taken from the timestamp of the block that created it.
+    /// Falls back to now if the block cannot be fetched.
+    pub async fn task_created_at(
+        &self,
+        new_task_created_log: &Log<IncredibleSquaringTaskManager::NewTaskCreated>,
+    ) -> SystemTime {
+        let block_timestamp = match new_task_created_log.block_timestamp {
+            Some(block_timestamp) => Some(block_timestamp),
+            None => match new_task_created_log.block_number {
+                Some(block_number) => self
+                    .incredible_squaring_contract_manager
+                    .eth_client_http
+                    .get_block_by_number(block_number.into(), false)
+                    .await
+                    .map_err(|e| log::warn!("Failed to fetch task creation block: {}", e))
+                    .ok()
+                    .flatten()
+                    .map(|block| block.header.timestamp),
+                None => None,
+            },
+        };
+        block_timestamp
+            .map(|block_timestamp| UNIX_EPOCH + Duration::from_secs(block_timestamp))
+            .unwrap_or_else(SystemTime::now)
+    }
+
     pub fn sign_task_response(
         &self,
         task_response: &TaskResponse,
@@ -482,16 +653,19 @@ impl<T: Config, I: OperatorInfoServiceTrait> Operator<T, I> {
         Ok(signed_task_response)
     }
 
-    pub async fn subscribe_to_new_tasks(&self) -> Result<Subscription<Log>, AvsError> {
+    pub async fn subscribe_to_new_tasks(&self) -> Result<LogSubscription, AvsError> {
         self.incredible_squaring_contract_manager
             .subscribe_to_new_tasks()
             .await
     }
 
-    pub async fn start_aggregator_server(&self) -> Result<(), AvsError> {
+    pub async fn start_aggregator_server(
+        &self,
+        shutdown: CancellationToken,
+    ) -> Result<(), AvsError> {
         self.aggregator_server
             .clone()
-            .start_server()
+            .start_server(shutdown)
             .await
             .map_err(|e| AvsError::OperatorError(e.to_string()))
     }
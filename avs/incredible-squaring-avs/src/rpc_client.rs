@@ -1,16 +1,103 @@
-use eigen_utils::types::AvsError;
-use reqwest::{Client, Response, Url};
-use serde_json;
-use std::time::Duration;
-use tokio::time::sleep;
+use eigen_utils::aggregator::auth::AuthenticatedSignedTaskResponse;
+use eigen_utils::aggregator::jsonrpc::{
+    JsonRpcError, JsonRpcRequest, JsonRpcResponse, INTERNAL_ERROR,
+    PROCESS_SIGNED_TASK_RESPONSE_METHOD, RATE_LIMITED, TASK_NOT_FOUND,
+};
+use eigen_utils::aggregator::server::{JSON_RPC_PATH, PROCESS_SIGNED_TASK_RESPONSE_PATH};
+use k256::ecdsa::SigningKey;
+use rand::Rng;
+use reqwest::{Certificate, Client, Identity, StatusCode, Url};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use thiserror::Error;
+use tokio::time::{sleep, Instant};
 
 use crate::avs::SignedTaskResponse;
 
+/// How signed task responses are sent to the aggregator.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AggregatorTransport {
+    /// JSON-RPC 2.0 calls to `Aggregator.ProcessSignedTaskResponse`.
+    #[default]
+    JsonRpc,
+    /// The response POSTed as a raw JSON body, for aggregators without JSON-RPC support.
+    RawPost,
+}
+
+/// Backoff between attempts to deliver a signed task response.
+///
+/// The delay starts at `initial_backoff` and doubles after every failed attempt up to
+/// `max_backoff`; each sleep is drawn uniformly from the upper half of the current delay.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Upper bound on a single request, further capped by the time left until the deadline.
+    pub request_timeout: Duration,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// TLS settings for an aggregator served over HTTPS.
+#[derive(Debug, Clone)]
+pub struct AggregatorTlsConfig {
+    /// PEM file with the CA certificates trusted to issue the aggregator's certificate.
+    pub ca_cert_path: PathBuf,
+    /// PEM certificate and PKCS#8, PKCS#1 or SEC1 key presented to aggregators requiring
+    /// mutual TLS.
+    pub client_identity: Option<(PathBuf, PathBuf)>,
+}
+
+/// Acknowledgement of a signed task response accepted by the aggregator.
+#[derive(Debug, Clone)]
+pub struct AggregatorAck {
+    pub reply: String,
+    pub attempts: u32,
+}
+
+#[derive(Debug, Error)]
+pub enum AggregatorRpcError {
+    /// The aggregator refused the response; resending it will not help.
+    #[error("Aggregator rejected signed task response with status {status}: {reply}")]
+    Rejected { status: StatusCode, reply: String },
+    /// The aggregator refused the response with a JSON-RPC error.
+    #[error("Aggregator rejected signed task response with error {}: {}", .0.code, .0.message)]
+    RpcRejected(JsonRpcError),
+    /// Every attempt failed with a retryable error before the deadline.
+    #[error("Could not deliver signed task response after {attempts} attempts: {last_error}")]
+    DeadlineExceeded { attempts: u32, last_error: String },
+    #[error("Invalid aggregator request: {0}")]
+    InvalidRequest(String),
+    #[error("Invalid aggregator TLS configuration: {0}")]
+    TlsConfig(String),
+}
+
+/// Outcome of a single delivery attempt.
+enum AttemptError {
+    Retryable(String),
+    Permanent(AggregatorRpcError),
+}
+
 #[derive(Clone)]
 pub struct AggregatorRpcClient {
     client: Client,
     // metrics: Metrics,
     aggregator_ip_port_addr: String,
+    scheme: &'static str,
+    retry_config: RetryConfig,
+    transport: AggregatorTransport,
+    ecdsa_signing_key: Option<SigningKey>,
+    next_request_id: Arc<AtomicU64>,
 }
 
 impl AggregatorRpcClient {
@@ -22,73 +109,441 @@ impl AggregatorRpcClient {
             client: Client::new(),
             // metrics,
             aggregator_ip_port_addr,
+            scheme: "http",
+            retry_config: RetryConfig::default(),
+            transport: AggregatorTransport::default(),
+            ecdsa_signing_key: None,
+            next_request_id: Arc::new(AtomicU64::new(1)),
         }
     }
 
-    async fn dial_aggregator_rpc_client(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let response = self
-            .client
-            .get(format!("http://{}/", self.aggregator_ip_port_addr))
-            .send()
-            .await?;
-        log::info!("Dialing aggregator RPC client. Response: {:?}", response);
-        Ok(())
+    pub fn with_transport(mut self, transport: AggregatorTransport) -> Self {
+        self.transport = transport;
+        self
     }
 
+    /// Signs submissions with the operator's ECDSA key, for aggregators that authenticate
+    /// operators.
+    pub fn with_ecdsa_signing_key(mut self, ecdsa_signing_key: SigningKey) -> Self {
+        self.ecdsa_signing_key = Some(ecdsa_signing_key);
+        self
+    }
+
+    /// Connects to the aggregator over HTTPS, presenting a client certificate if one is
+    /// configured.
+    pub fn with_tls(
+        mut self,
+        tls_config: &AggregatorTlsConfig,
+    ) -> Result<Self, AggregatorRpcError> {
+        let read = |path: &PathBuf| {
+            std::fs::read(path).map_err(|e| {
+                AggregatorRpcError::TlsConfig(format!("Failed to read {}: {}", path.display(), e))
+            })
+        };
+        let ca_cert = Certificate::from_pem(&read(&tls_config.ca_cert_path)?)
+            .map_err(|e| AggregatorRpcError::TlsConfig(e.to_string()))?;
+        // rustls, like the aggregator server, rather than the platform TLS library
+        let mut builder = Client::builder()
+            .use_rustls_tls()
+            .add_root_certificate(ca_cert);
+        if let Some((cert_path, key_path)) = &tls_config.client_identity {
+            let mut identity_pem = read(cert_path)?;
+            identity_pem.push(b'\n');
+            identity_pem.extend(read(key_path)?);
+            let identity = Identity::from_pem(&identity_pem)
+                .map_err(|e| AggregatorRpcError::TlsConfig(e.to_string()))?;
+            builder = builder.identity(identity);
+        }
+        self.client = builder
+            .build()
+            .map_err(|e| AggregatorRpcError::TlsConfig(e.to_string()))?;
+        self.scheme = "https";
+        Ok(self)
+    }
+
+    pub fn with_retry_config(mut self, retry_config: RetryConfig) -> Self {
+        self.retry_config = retry_config;
+        self
+    }
+
+    /// Sends a signed task response to the aggregator, retrying transient failures with
+    /// exponential backoff until it is accepted, rejected, or `deadline` passes.
+    ///
+    /// Connection errors, timeouts, `404`, `408`, `429` and `5xx` responses are retried, as
+    /// are the JSON-RPC task-not-found, rate-limited and internal errors; task not found can
+    /// mean the aggregator has not initialized the task yet. Any other rejection is
+    /// permanent, including `410 Gone` and the JSON-RPC task-expired error for tasks that
+    /// expired, were cancelled or already completed. The deadline should be the task's
+    /// expiry, see [`task_response_deadline`], after which the aggregator would discard the
+    /// response anyway.
     pub async fn send_signed_task_response_to_aggregator(
         &self,
         signed_task_response: SignedTaskResponse,
-    ) {
-        log::info!("RPC client is nil. Dialing aggregator RPC client");
-        if let Err(err) = self.dial_aggregator_rpc_client().await {
-            log::error!("Could not dial aggregator RPC client. Not sending signed task response header to aggregator. Is aggregator running? Error: {:?}", err);
-            return;
+        deadline: Instant,
+    ) -> Result<AggregatorAck, AggregatorRpcError> {
+        let path = match self.transport {
+            AggregatorTransport::JsonRpc => JSON_RPC_PATH,
+            AggregatorTransport::RawPost => PROCESS_SIGNED_TASK_RESPONSE_PATH,
+        };
+        let url = format!("{}://{}{}", self.scheme, self.aggregator_ip_port_addr, path)
+            .parse::<Url>()
+            .map_err(|e| AggregatorRpcError::InvalidRequest(e.to_string()))?;
+        let submission = match &self.ecdsa_signing_key {
+            Some(signing_key) => {
+                AuthenticatedSignedTaskResponse::sign(signed_task_response, signing_key)
+                    .map_err(|e| AggregatorRpcError::InvalidRequest(format!("{:?}", e)))?
+            }
+            None => AuthenticatedSignedTaskResponse::unauthenticated(signed_task_response),
+        };
+        let body = match self.transport {
+            AggregatorTransport::JsonRpc => serde_json::to_string(&JsonRpcRequest::new(
+                PROCESS_SIGNED_TASK_RESPONSE_METHOD,
+                &submission,
+                self.next_request_id.fetch_add(1, Ordering::Relaxed),
+            )),
+            AggregatorTransport::RawPost => serde_json::to_string(&submission),
         }
+        .map_err(|e| AggregatorRpcError::InvalidRequest(e.to_string()))?;
 
-        for _ in 0..5 {
-            let response = self
-                .call_aggregator_rpc(
-                    "Aggregator.ProcessSignedTaskResponse",
-                    &signed_task_response,
-                )
-                .await;
-
-            match response {
-                Ok(res) => {
+        let mut backoff = self.retry_config.initial_backoff;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let last_error = match self.call_aggregator_rpc(&url, &body, deadline).await {
+                Ok(reply) => {
                     log::info!(
-                        "Signed task response header accepted by aggregator. Reply: {:?}",
-                        res
+                        "Signed task response accepted by aggregator. Reply: {:?}",
+                        reply
                     );
                     // self.metrics.inc_num_tasks_accepted_by_aggregator();
-                    return;
+                    return Ok(AggregatorAck { reply, attempts });
                 }
-                Err(err) => {
-                    log::error!("Received error from aggregator: {:?}", err);
+                Err(AttemptError::Permanent(err)) => {
+                    log::error!("Aggregator rejected signed task response: {}", err);
+                    return Err(err);
                 }
+                Err(AttemptError::Retryable(err)) => err,
+            };
+
+            let delay = jittered(backoff);
+            if Instant::now() + delay >= deadline {
+                log::error!(
+                    "Giving up on signed task response after {} attempts: {}",
+                    attempts,
+                    last_error
+                );
+                return Err(AggregatorRpcError::DeadlineExceeded {
+                    attempts,
+                    last_error,
+                });
             }
-            log::info!("Retrying in 2 seconds");
-            sleep(Duration::from_secs(2)).await;
+            log::warn!(
+                "Sending signed task response failed ({}), retrying in {:?}",
+                last_error,
+                delay
+            );
+            sleep(delay).await;
+            backoff = (backoff * 2).min(self.retry_config.max_backoff);
         }
-        log::error!("Could not send signed task response to aggregator. Tried 5 times.");
     }
 
     async fn call_aggregator_rpc(
         &self,
-        method: &str,
-        params: &SignedTaskResponse,
-    ) -> Result<Response, AvsError> {
-        let url: Url = format!("http://{}/{}", self.aggregator_ip_port_addr, method)
-            .parse()
-            .unwrap();
+        url: &Url,
+        body: &str,
+        deadline: Instant,
+    ) -> Result<String, AttemptError> {
+        let timeout = deadline
+            .saturating_duration_since(Instant::now())
+            .min(self.retry_config.request_timeout);
 
-        let json = serde_json::to_string(params).map_err(AvsError::from)?;
-
-        reqwest::Client::new()
-            .post(url)
+        let response = self
+            .client
+            .post(url.clone())
             .header("Content-Type", "application/json")
-            .body(json)
+            .body(body.to_string())
+            .timeout(timeout)
             .send()
             .await
-            .map_err(AvsError::from)
+            .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+
+        let status = response.status();
+        let reply = response
+            .text()
+            .await
+            .map_err(|e| AttemptError::Retryable(e.to_string()))?;
+
+        if status.is_success() && self.transport == AggregatorTransport::JsonRpc {
+            parse_json_rpc_reply(&reply)
+        } else if status.is_success() {
+            Ok(reply)
+        } else if status.is_server_error()
+            || matches!(
+                status,
+                StatusCode::NOT_FOUND | StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS
+            )
+        {
+            Err(AttemptError::Retryable(format!("{}: {}", status, reply)))
+        } else {
+            Err(AttemptError::Permanent(AggregatorRpcError::Rejected {
+                status,
+                reply,
+            }))
+        }
+    }
+}
+
+fn parse_json_rpc_reply(reply: &str) -> Result<String, AttemptError> {
+    let response: JsonRpcResponse = serde_json::from_str(reply).map_err(|e| {
+        AttemptError::Permanent(AggregatorRpcError::InvalidRequest(format!(
+            "Invalid JSON-RPC response {:?}: {}",
+            reply, e
+        )))
+    })?;
+
+    match response.error {
+        None => Ok(response.result.unwrap_or_default().to_string()),
+        Some(error) if matches!(error.code, TASK_NOT_FOUND | RATE_LIMITED | INTERNAL_ERROR) => Err(
+            AttemptError::Retryable(format!("JSON-RPC error {}: {}", error.code, error.message)),
+        ),
+        Some(error) => Err(AttemptError::Permanent(AggregatorRpcError::RpcRejected(
+            error,
+        ))),
+    }
+}
+
+/// Deadline for delivering a response to a task created at `task_created_at`, which the
+/// aggregator expires `time_to_expiry` later. Time the task spent before reaching the operator
+/// is deducted, so retries do not outlive the task on the aggregator.
+pub fn task_response_deadline(task_created_at: SystemTime, time_to_expiry: Duration) -> Instant {
+    let task_age = SystemTime::now()
+        .duration_since(task_created_at)
+        .unwrap_or_default();
+    Instant::now() + time_to_expiry.saturating_sub(task_age)
+}
+
+/// Picks a delay uniformly from `[backoff / 2, backoff]`, so operators that failed together
+/// do not retry in lockstep.
+fn jittered(backoff: Duration) -> Duration {
+    let half = backoff / 2;
+    half + half.mul_f64(rand::thread_rng().gen::<f64>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eigen_utils::aggregator::jsonrpc::{INCORRECT_SIGNATURE, TASK_EXPIRED};
+    use eigen_utils::crypto::bls::Signature;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers the n-th request with the n-th status, repeating the last one, and returns
+    /// the server address along with the number of requests served.
+    async fn serve_statuses(statuses: Vec<u16>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = Arc::clone(&requests);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Reads the headers and the Content-Length body
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let request = String::from_utf8_lossy(&request);
+                    if let Some(header_end) = request.find("\r\n\r\n") {
+                        let content_length = request[..header_end]
+                            .lines()
+                            .find_map(|line| {
+                                line.to_ascii_lowercase()
+                                    .strip_prefix("content-length:")
+                                    .map(|len| len.trim().parse::<usize>().unwrap())
+                            })
+                            .unwrap_or(0);
+                        if request.len() >= header_end + 4 + content_length {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+                let n = served.fetch_add(1, Ordering::SeqCst);
+                let status = statuses[n.min(statuses.len() - 1)];
+                let response = format!(
+                    "HTTP/1.1 {} Status\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+                    status
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (addr, requests)
+    }
+
+    fn client(addr: String) -> AggregatorRpcClient {
+        AggregatorRpcClient::new(addr)
+            .with_transport(AggregatorTransport::RawPost)
+            .with_retry_config(RetryConfig {
+                initial_backoff: Duration::from_millis(10),
+                max_backoff: Duration::from_millis(20),
+                request_timeout: Duration::from_secs(1),
+            })
+    }
+
+    fn signed_task_response() -> SignedTaskResponse {
+        SignedTaskResponse {
+            task_response: vec![1],
+            bls_signature: Signature::new_zero(),
+            operator_id: Default::default(),
+        }
+    }
+
+    fn json_rpc_error_reply(code: i64) -> String {
+        serde_json::to_string(&JsonRpcResponse::error(
+            1.into(),
+            JsonRpcError::new(code, "error"),
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_json_rpc_reply_classification() {
+        let reply = serde_json::to_string(&JsonRpcResponse::result(1.into(), true.into())).unwrap();
+        assert_eq!(parse_json_rpc_reply(&reply).ok(), Some("true".to_string()));
+
+        for code in [TASK_NOT_FOUND, RATE_LIMITED, INTERNAL_ERROR] {
+            assert!(matches!(
+                parse_json_rpc_reply(&json_rpc_error_reply(code)),
+                Err(AttemptError::Retryable(_))
+            ));
+        }
+        for code in [TASK_EXPIRED, INCORRECT_SIGNATURE] {
+            assert!(matches!(
+                parse_json_rpc_reply(&json_rpc_error_reply(code)),
+                Err(AttemptError::Permanent(AggregatorRpcError::RpcRejected(error)))
+                    if error.code == code
+            ));
+        }
+        assert!(matches!(
+            parse_json_rpc_reply("not json"),
+            Err(AttemptError::Permanent(AggregatorRpcError::InvalidRequest(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_tls_config() {
+        let testdata = |name: &str| {
+            PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/../../utils/testdata/tls"
+            ))
+            .join(name)
+        };
+        let tls_config = |client_identity| AggregatorTlsConfig {
+            ca_cert_path: testdata("ca.crt"),
+            client_identity,
+        };
+        let tls_client =
+            |tls_config| AggregatorRpcClient::new("127.0.0.1:0".to_string()).with_tls(&tls_config);
+
+        assert_eq!(tls_client(tls_config(None)).unwrap().scheme, "https");
+        let identity = Some((testdata("client.crt"), testdata("client.key")));
+        assert_eq!(tls_client(tls_config(identity)).unwrap().scheme, "https");
+
+        for client_identity in [
+            Some((testdata("client.crt"), testdata("missing.key"))),
+            Some((testdata("client.crt"), testdata("client.crt"))),
+        ] {
+            assert!(matches!(
+                tls_client(tls_config(client_identity)),
+                Err(AggregatorRpcError::TlsConfig(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_jittered_backoff() {
+        let backoff = Duration::from_millis(100);
+        for _ in 0..100 {
+            let delay = jittered(backoff);
+            assert!(delay >= backoff / 2 && delay <= backoff);
+        }
+    }
+
+    #[test]
+    fn test_task_response_deadline() {
+        let time_to_expiry = Duration::from_secs(30);
+        let remaining = |task_age: Duration| {
+            task_response_deadline(SystemTime::now() - task_age, time_to_expiry)
+                .saturating_duration_since(Instant::now())
+        };
+
+        assert!(remaining(Duration::ZERO) > Duration::from_secs(29));
+        let remaining_after_10s = remaining(Duration::from_secs(10));
+        assert!(
+            remaining_after_10s > Duration::from_secs(19)
+                && remaining_after_10s <= Duration::from_secs(20)
+        );
+        assert_eq!(remaining(Duration::from_secs(60)), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_retryable_statuses_are_retried() {
+        let (addr, requests) = serve_statuses(vec![503, 404, 408, 429, 200]).await;
+        let ack = client(addr)
+            .send_signed_task_response_to_aggregator(
+                signed_task_response(),
+                Instant::now() + Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        assert_eq!(ack.attempts, 5);
+        assert_eq!(ack.reply, "ok");
+        assert_eq!(requests.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_rejections_are_not_retried() {
+        for status in [400, 401, 409, 410] {
+            let (addr, requests) = serve_statuses(vec![status]).await;
+            let err = client(addr)
+                .send_signed_task_response_to_aggregator(
+                    signed_task_response(),
+                    Instant::now() + Duration::from_secs(5),
+                )
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                err,
+                AggregatorRpcError::Rejected { status: rejected, .. }
+                    if rejected.as_u16() == status
+            ));
+            assert_eq!(requests.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retries_stop_at_the_deadline() {
+        let (addr, requests) = serve_statuses(vec![503]).await;
+        let started_at = Instant::now();
+        let err = client(addr)
+            .send_signed_task_response_to_aggregator(
+                signed_task_response(),
+                started_at + Duration::from_millis(200),
+            )
+            .await
+            .unwrap_err();
+        assert!(started_at.elapsed() < Duration::from_millis(300));
+        let AggregatorRpcError::DeadlineExceeded { attempts, .. } = err else {
+            panic!("expected the deadline to be exceeded, got {}", err);
+        };
+        assert!(attempts > 1);
+        assert_eq!(requests.load(Ordering::SeqCst), attempts as usize);
     }
 }
@@ -7,6 +7,7 @@ use alloy_signer::Signer;
 use alloy_signer_local::PrivateKeySigner;
 use alloy_transport_ws::WsConnect;
 use eigen_utils::crypto::bls::KeyPair;
+use eigen_utils::providers::fallback::FallbackProvider;
 use eigen_utils::types::{operator_id_from_key_pair, OperatorInfo, OperatorPubkeys};
 use incredible_squaring_avs::operator::*;
 use k256::ecdsa::SigningKey;
@@ -43,7 +44,11 @@ async fn operator_setup(
     let node_config = NodeConfig {
         node_api_ip_port_address: "127.0.0.1:9808".to_string(),
         eth_rpc_url: http_endpoint.to_string(),
-        eth_ws_url: ws_endpoint.to_string(),
+        eth_rpc_fallback_urls: vec![],
+        eth_ws_url: Some(ws_endpoint.to_string()),
+        eth_log_polling_interval: None,
+        // Anvil does not reorganise blocks
+        log_confirmations: 0,
         bls_private_key_store_path: "./keystore/bls".to_string(),
         ecdsa_private_key_store_path: "./keystore/ecdsa".to_string(),
         incredible_squaring_service_manager_addr: contract_addresses.service_manager.to_string(),
@@ -63,28 +68,32 @@ async fn operator_setup(
 
     log::info!("Creating HTTP Provider...");
 
-    let http_provider = ProviderBuilder::new()
-        .with_recommended_fillers()
-        .on_http(
-            http_endpoint
-                .parse::<url::Url>()
-                .map_err(|e| OperatorError::HttpEthClientError(e.to_string()))?,
-        )
-        .root()
-        .clone()
-        .boxed();
-
-    log::info!("Creating WS Provider...");
-
-    let ws_provider = ProviderBuilder::new()
-        .with_recommended_fillers()
-        .on_ws(WsConnect::new(ws_endpoint))
+    let http_urls: Vec<String> = std::iter::once(node_config.eth_rpc_url.clone())
+        .chain(node_config.eth_rpc_fallback_urls.iter().cloned())
+        .collect();
+    let http_provider = FallbackProvider::connect(&http_urls)
         .await
-        .map_err(|e| OperatorError::WsEthClientError(e.to_string()))?
+        .map_err(|e| OperatorError::HttpEthClientError(e.to_string()))?
         .root()
         .clone()
         .boxed();
 
+    // Polled logs are fetched over HTTP, so no websocket connection is needed for them
+    let ws_provider = match &node_config.eth_ws_url {
+        Some(ws_url) if node_config.eth_log_polling_interval.is_none() => {
+            log::info!("Creating WS Provider...");
+            ProviderBuilder::new()
+                .with_recommended_fillers()
+                .on_ws(WsConnect::new(ws_url))
+                .await
+                .map_err(|e| OperatorError::WsEthClientError(e.to_string()))?
+                .root()
+                .clone()
+                .boxed()
+        }
+        _ => http_provider.clone(),
+    };
+
     log::info!("Now setting up Operator!");
 
     let chain_id = http_provider
@@ -145,8 +154,12 @@ async fn operator_setup(
 mod tests {
     use super::*;
     use alloy_rpc_types_eth::Log;
+    use eigen_utils::subscriptions::LogEvent;
+    use incredible_squaring_avs::aggregator::TASK_TIME_TO_EXPIRY;
     use incredible_squaring_avs::avs::IncredibleSquaringTaskManager;
+    use incredible_squaring_avs::rpc_client::task_response_deadline;
     use std::env;
+    use tokio_util::sync::CancellationToken;
 
     #[tokio::test]
     async fn test_incredible_squaring_deployment() {
@@ -172,11 +185,13 @@ mod tests {
 
         let server = operator.aggregator_server.clone();
         let aggregator_server = async move {
-            server.start_server().await.unwrap();
+            server.start_server(CancellationToken::new()).await.unwrap();
         };
         tokio::spawn(aggregator_server);
 
-        let new_task_created_log = sub.recv().await.unwrap();
+        let Some(LogEvent::Added(new_task_created_log)) = sub.recv().await else {
+            panic!("New task subscription closed or reported a removed task");
+        };
         log::info!("Received new task: {:?}", new_task_created_log);
 
         let log: Log<IncredibleSquaringTaskManager::NewTaskCreated> =
@@ -189,9 +204,11 @@ mod tests {
                 signed_task_response
             );
             let agg_rpc_client = operator.aggregator_rpc_client.clone();
+            let deadline =
+                task_response_deadline(operator.task_created_at(&log).await, TASK_TIME_TO_EXPIRY);
             tokio::spawn(async move {
-                agg_rpc_client
-                    .send_signed_task_response_to_aggregator(signed_task_response)
+                let _ = agg_rpc_client
+                    .send_signed_task_response_to_aggregator(signed_task_response, deadline)
                     .await;
             });
         }
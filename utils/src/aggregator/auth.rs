@@ -0,0 +1,415 @@
+//! ECDSA authentication of operator submissions.
+//!
+//! An operator signs [`authentication_digest`] of its [`SignedTaskResponse`] with the ECDSA
+//! key it registered with, and the aggregator only accepts the submission if the recovered
+//! signer address is registered under the claimed operator id. This is checked before the
+//! far more expensive BLS signature verification.
+//!
+//! Submissions are bound to their task through the signed task response, and a submission
+//! already accepted for a task is rejected as a replay without being verified again.
+use alloy_primitives::{keccak256, Address, Bytes, B256};
+use async_trait::async_trait;
+use k256::ecdsa::{RecoveryId, Signature as EcdsaSignature, SigningKey, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::avs_registry::{reader::AvsRegistryChainReaderTrait, AvsRegistryContractManager};
+use crate::crypto::ecdsa::ToAddress;
+use crate::services::bls_aggregation::BlsAggregationError;
+use crate::types::{AvsError, OperatorId, TaskIndex};
+use crate::Config;
+
+use super::{AggregatorServerError, SignedTaskResponse};
+
+/// Domain separator, so the signature cannot be replayed as any other ECDSA message.
+const AUTHENTICATION_DOMAIN: &[u8] = b"eigen.aggregator.SignedTaskResponse";
+
+/// How long a signer found not to be a registered operator is refused without looking it up
+/// again.
+pub const DEFAULT_UNREGISTERED_SIGNER_TTL: Duration = Duration::from_secs(60);
+
+/// Number of cached unregistered signers above which expired entries are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// A [`SignedTaskResponse`] optionally carrying the operator's ECDSA signature.
+///
+/// The signed task response is flattened, so submissions from operators that do not
+/// authenticate deserialize with `ecdsa_signature` unset.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthenticatedSignedTaskResponse {
+    #[serde(flatten)]
+    pub signed_task_response: SignedTaskResponse,
+    /// 65-byte `r || s || v` signature over [`authentication_digest`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ecdsa_signature: Option<Bytes>,
+}
+
+impl AuthenticatedSignedTaskResponse {
+    /// Wraps a signed task response without authenticating it.
+    pub fn unauthenticated(signed_task_response: SignedTaskResponse) -> Self {
+        Self {
+            signed_task_response,
+            ecdsa_signature: None,
+        }
+    }
+
+    /// Signs the response with the operator's ECDSA key.
+    pub fn sign(
+        signed_task_response: SignedTaskResponse,
+        signing_key: &SigningKey,
+    ) -> Result<Self, AvsError> {
+        let digest = authentication_digest(&signed_task_response);
+        let (signature, recovery_id) = signing_key
+            .sign_prehash_recoverable(digest.as_slice())
+            .map_err(|e| AvsError::KeyError(e.to_string()))?;
+        let mut ecdsa_signature = signature.to_bytes().to_vec();
+        ecdsa_signature.push(recovery_id.to_byte() + 27);
+        Ok(Self {
+            signed_task_response,
+            ecdsa_signature: Some(Bytes::from(ecdsa_signature)),
+        })
+    }
+
+    /// Recovers the address of the key that signed the response, if it is signed.
+    pub fn recover_signer(&self) -> Result<Option<Address>, AggregatorServerError> {
+        let Some(ecdsa_signature) = &self.ecdsa_signature else {
+            return Ok(None);
+        };
+        let invalid = |e: String| {
+            AggregatorServerError::Unauthenticated(format!("Invalid ECDSA signature: {}", e))
+        };
+        if ecdsa_signature.len() != 65 {
+            return Err(invalid(format!(
+                "expected 65 bytes, got {}",
+                ecdsa_signature.len()
+            )));
+        }
+        let signature = EcdsaSignature::from_slice(&ecdsa_signature[..64])
+            .map_err(|e| invalid(e.to_string()))?;
+        let v = ecdsa_signature[64];
+        let recovery_id = RecoveryId::from_byte(if v >= 27 { v - 27 } else { v })
+            .ok_or_else(|| invalid(format!("recovery id {}", v)))?;
+        let digest = authentication_digest(&self.signed_task_response);
+        let verifying_key =
+            VerifyingKey::recover_from_prehash(digest.as_slice(), &signature, recovery_id)
+                .map_err(|e| invalid(e.to_string()))?;
+        Ok(Some(verifying_key.to_address()))
+    }
+}
+
+/// Digest an operator signs with its ECDSA key to authenticate a [`SignedTaskResponse`].
+///
+/// Commits to the task response, the BLS signature and the claimed operator id.
+pub fn authentication_digest(signed_task_response: &SignedTaskResponse) -> B256 {
+    let g1_point = &signed_task_response.bls_signature.g1_point;
+    let mut preimage = Vec::with_capacity(AUTHENTICATION_DOMAIN.len() + 4 * 32);
+    preimage.extend_from_slice(AUTHENTICATION_DOMAIN);
+    preimage.extend_from_slice(keccak256(&signed_task_response.task_response).as_slice());
+    preimage.extend_from_slice(&g1_point.x.to_be_bytes::<32>());
+    preimage.extend_from_slice(&g1_point.y.to_be_bytes::<32>());
+    preimage.extend_from_slice(signed_task_response.operator_id.as_slice());
+    keccak256(preimage)
+}
+
+/// Looks up the operator id registered for an operator's ECDSA address.
+#[async_trait]
+pub trait OperatorIdResolver: Send + Sync + 'static {
+    async fn resolve_operator_id(&self, operator_address: Address) -> Result<OperatorId, AvsError>;
+}
+
+#[async_trait]
+impl<T: Config> OperatorIdResolver for AvsRegistryContractManager<T> {
+    async fn resolve_operator_id(&self, operator_address: Address) -> Result<OperatorId, AvsError> {
+        self.get_operator_id(operator_address).await
+    }
+}
+
+/// Checks that submissions are signed by the operator they claim to come from.
+///
+/// Operator ids are derived from the operator's BLS key at registration and do not change,
+/// so resolved ids are cached by address. Signers that are not registered are cached for
+/// the unregistered signer TTL only, as they may still register.
+pub struct OperatorAuthenticator {
+    resolver: Box<dyn OperatorIdResolver>,
+    operator_ids: RwLock<HashMap<Address, OperatorId>>,
+    unregistered_signer_ttl: Duration,
+    /// When each unregistered signer was last looked up.
+    unregistered_signers: RwLock<HashMap<Address, Instant>>,
+    /// Authentication digests of the submissions accepted for each task.
+    accepted_submissions: Mutex<HashMap<TaskIndex, HashSet<B256>>>,
+}
+
+impl OperatorAuthenticator {
+    pub fn new(resolver: impl OperatorIdResolver) -> Self {
+        Self {
+            resolver: Box::new(resolver),
+            operator_ids: RwLock::new(HashMap::new()),
+            unregistered_signer_ttl: DEFAULT_UNREGISTERED_SIGNER_TTL,
+            unregistered_signers: RwLock::new(HashMap::new()),
+            accepted_submissions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_unregistered_signer_ttl(mut self, unregistered_signer_ttl: Duration) -> Self {
+        self.unregistered_signer_ttl = unregistered_signer_ttl;
+        self
+    }
+
+    /// Rejects a submission that was already accepted for `task_index`.
+    pub(crate) fn check_not_replayed(
+        &self,
+        task_index: TaskIndex,
+        signed_task_response: &SignedTaskResponse,
+    ) -> Result<(), AggregatorServerError> {
+        let digest = authentication_digest(signed_task_response);
+        let accepted_submissions = self.accepted_submissions.lock().unwrap();
+        if accepted_submissions
+            .get(&task_index)
+            .is_some_and(|digests| digests.contains(&digest))
+        {
+            return Err(AggregatorServerError::Aggregation(
+                BlsAggregationError::DuplicateSignatureError(
+                    signed_task_response.operator_id,
+                    task_index,
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Records a submission the BLS aggregation service accepted for `task_index`, returning
+    /// whether it is the first one recorded for the task.
+    pub(crate) fn record_accepted(
+        &self,
+        task_index: TaskIndex,
+        signed_task_response: &SignedTaskResponse,
+    ) -> bool {
+        let mut accepted_submissions = self.accepted_submissions.lock().unwrap();
+        let first = !accepted_submissions.contains_key(&task_index);
+        accepted_submissions
+            .entry(task_index)
+            .or_default()
+            .insert(authentication_digest(signed_task_response));
+        first
+    }
+
+    /// Drops the submissions recorded for a task that no longer accepts signatures.
+    pub(crate) fn forget_task(&self, task_index: TaskIndex) {
+        self.accepted_submissions
+            .lock()
+            .unwrap()
+            .remove(&task_index);
+    }
+
+    /// Returns the signed task response if its ECDSA signer is registered under the claimed
+    /// operator id.
+    pub async fn authenticate(
+        &self,
+        submission: AuthenticatedSignedTaskResponse,
+    ) -> Result<SignedTaskResponse, AggregatorServerError> {
+        let signer = submission.recover_signer()?.ok_or_else(|| {
+            AggregatorServerError::Unauthenticated("Missing ECDSA signature".to_string())
+        })?;
+
+        let claimed_operator_id = submission.signed_task_response.operator_id;
+        let operator_id = self.operator_id(signer).await?;
+        if operator_id != claimed_operator_id {
+            return Err(AggregatorServerError::Unauthenticated(format!(
+                "Signer {} is not operator {}",
+                signer, claimed_operator_id
+            )));
+        }
+        Ok(submission.signed_task_response)
+    }
+
+    async fn operator_id(&self, signer: Address) -> Result<OperatorId, AggregatorServerError> {
+        if let Some(operator_id) = self.operator_ids.read().await.get(&signer) {
+            return Ok(*operator_id);
+        }
+        let not_registered = || {
+            AggregatorServerError::Unauthenticated(format!(
+                "Signer {} is not a registered operator",
+                signer
+            ))
+        };
+        if let Some(looked_up_at) = self.unregistered_signers.read().await.get(&signer) {
+            if looked_up_at.elapsed() < self.unregistered_signer_ttl {
+                return Err(not_registered());
+            }
+        }
+
+        let operator_id = self
+            .resolver
+            .resolve_operator_id(signer)
+            .await
+            .map_err(|e| AggregatorServerError::OperatorLookup(format!("{:?}", e)))?;
+        // Unregistered addresses resolve to the zero id, which may still get registered
+        if operator_id.is_zero() {
+            let now = Instant::now();
+            let mut unregistered_signers = self.unregistered_signers.write().await;
+            if unregistered_signers.len() >= PRUNE_THRESHOLD {
+                unregistered_signers.retain(|_, looked_up_at| {
+                    now.duration_since(*looked_up_at) < self.unregistered_signer_ttl
+                });
+            }
+            unregistered_signers.insert(signer, now);
+            return Err(not_registered());
+        }
+        self.unregistered_signers.write().await.remove(&signer);
+        self.operator_ids.write().await.insert(signer, operator_id);
+        Ok(operator_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::bls::Signature;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Resolves the registered addresses to their operator ids, and others to the zero id.
+    #[derive(Clone, Default)]
+    struct MockResolver {
+        operator_ids: Arc<Mutex<HashMap<Address, OperatorId>>>,
+        lookups: Arc<AtomicUsize>,
+    }
+
+    impl MockResolver {
+        fn register(&self, signing_key: &SigningKey, operator_id: OperatorId) {
+            self.operator_ids
+                .lock()
+                .unwrap()
+                .insert(VerifyingKey::from(signing_key).to_address(), operator_id);
+        }
+    }
+
+    #[async_trait]
+    impl OperatorIdResolver for MockResolver {
+        async fn resolve_operator_id(
+            &self,
+            operator_address: Address,
+        ) -> Result<OperatorId, AvsError> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(self
+                .operator_ids
+                .lock()
+                .unwrap()
+                .get(&operator_address)
+                .copied()
+                .unwrap_or_default())
+        }
+    }
+
+    fn signed_task_response(operator_id: OperatorId) -> SignedTaskResponse {
+        SignedTaskResponse {
+            task_response: vec![1, 2, 3],
+            bls_signature: Signature::new_zero(),
+            operator_id,
+        }
+    }
+
+    fn random_signing_key() -> SigningKey {
+        SigningKey::random(&mut rand::thread_rng())
+    }
+
+    #[tokio::test]
+    async fn test_authenticate() {
+        let resolver = MockResolver::default();
+        let authenticator = OperatorAuthenticator::new(resolver.clone());
+        let signing_key = random_signing_key();
+        let operator_id = B256::repeat_byte(1);
+        resolver.register(&signing_key, operator_id);
+
+        let submission =
+            AuthenticatedSignedTaskResponse::sign(signed_task_response(operator_id), &signing_key)
+                .unwrap();
+        assert_eq!(
+            authenticator
+                .authenticate(submission.clone())
+                .await
+                .unwrap()
+                .operator_id,
+            operator_id
+        );
+
+        // Claiming another operator's id, tampering with the response and not signing it
+        // are all rejected
+        let other_operator = AuthenticatedSignedTaskResponse::sign(
+            signed_task_response(B256::repeat_byte(2)),
+            &signing_key,
+        )
+        .unwrap();
+        let mut tampered = submission.clone();
+        tampered.signed_task_response.task_response = vec![4];
+        let unsigned =
+            AuthenticatedSignedTaskResponse::unauthenticated(signed_task_response(operator_id));
+        for submission in [other_operator, tampered, unsigned] {
+            assert!(matches!(
+                authenticator.authenticate(submission).await,
+                Err(AggregatorServerError::Unauthenticated(_))
+            ));
+        }
+
+        // Registered operator ids are cached
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 2);
+        authenticator.authenticate(submission).await.unwrap();
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unregistered_signers_are_cached_until_the_ttl() {
+        let resolver = MockResolver::default();
+        let authenticator = OperatorAuthenticator::new(resolver.clone())
+            .with_unregistered_signer_ttl(Duration::from_millis(100));
+        let signing_key = random_signing_key();
+        let operator_id = B256::repeat_byte(1);
+        let submission =
+            AuthenticatedSignedTaskResponse::sign(signed_task_response(operator_id), &signing_key)
+                .unwrap();
+
+        for _ in 0..3 {
+            assert!(matches!(
+                authenticator.authenticate(submission.clone()).await,
+                Err(AggregatorServerError::Unauthenticated(_))
+            ));
+        }
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 1);
+
+        // The signer registered in the meantime is looked up again once the TTL passed
+        resolver.register(&signing_key, operator_id);
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        authenticator.authenticate(submission).await.unwrap();
+        assert_eq!(resolver.lookups.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_accepted_submissions_are_not_replayed() {
+        let authenticator = OperatorAuthenticator::new(MockResolver::default());
+        let submission = signed_task_response(B256::repeat_byte(1));
+        let mut other_submission = submission.clone();
+        other_submission.task_response = vec![4];
+
+        authenticator.check_not_replayed(1, &submission).unwrap();
+        assert!(authenticator.record_accepted(1, &submission));
+        assert!(!authenticator.record_accepted(1, &other_submission));
+        authenticator.forget_task(1);
+        assert!(authenticator.record_accepted(1, &submission));
+        assert!(matches!(
+            authenticator.check_not_replayed(1, &submission),
+            Err(AggregatorServerError::Aggregation(
+                BlsAggregationError::DuplicateSignatureError(_, 1)
+            ))
+        ));
+        authenticator.check_not_replayed(2, &submission).unwrap();
+        authenticator
+            .check_not_replayed(1, &other_submission)
+            .unwrap();
+
+        authenticator.forget_task(1);
+        authenticator.check_not_replayed(1, &submission).unwrap();
+    }
+}
@@ -0,0 +1,139 @@
+//! JSON-RPC 2.0 envelope for operator-to-aggregator calls.
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use super::AggregatorServerError;
+use crate::services::bls_aggregation::BlsAggregationError;
+
+pub const JSON_RPC_VERSION: &str = "2.0";
+pub const PROCESS_SIGNED_TASK_RESPONSE_METHOD: &str = "Aggregator.ProcessSignedTaskResponse";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+/// The task is unknown to the aggregator, usually because it is not initialized yet.
+pub const TASK_NOT_FOUND: i64 = -32001;
+pub const INCORRECT_SIGNATURE: i64 = -32002;
+pub const OPERATOR_NOT_IN_QUORUM: i64 = -32003;
+/// The operator already signed the task, with the same or a conflicting response.
+pub const DUPLICATE_SIGNATURE: i64 = -32004;
+/// The submission is not signed by the operator it claims to come from.
+pub const UNAUTHENTICATED: i64 = -32005;
+/// The operator exceeded its submission rate limit; the call may be retried later.
+pub const RATE_LIMITED: i64 = -32006;
+/// The task expired, was cancelled or already completed, so the call must not be retried.
+pub const TASK_EXPIRED: i64 = -32007;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcRequest<P> {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: P,
+    /// Absent for notifications, which get no response. A `null` id is kept as
+    /// `Some(Value::Null)`, as such a request still expects a response.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_id",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Value>,
+}
+
+/// Deserializes a present id, including `null`, as `Some`; only a missing id is `None`.
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+impl<P> JsonRpcRequest<P> {
+    pub fn new(method: &str, params: P, id: u64) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            method: method.to_string(),
+            params,
+            id: Some(Value::from(id)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+    pub id: Value,
+}
+
+impl JsonRpcResponse {
+    pub fn result(id: Value, result: Value) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            result: Some(result),
+            error: None,
+            id,
+        }
+    }
+
+    pub fn error(id: Value, error: JsonRpcError) -> Self {
+        Self {
+            jsonrpc: JSON_RPC_VERSION.to_string(),
+            result: None,
+            error: Some(error),
+            id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl JsonRpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<&AggregatorServerError> for JsonRpcError {
+    fn from(err: &AggregatorServerError) -> Self {
+        let code = match err {
+            AggregatorServerError::InvalidRequest(_) => INVALID_PARAMS,
+            AggregatorServerError::Unauthenticated(_) => UNAUTHENTICATED,
+            AggregatorServerError::OperatorLookup(_) => INTERNAL_ERROR,
+            AggregatorServerError::RateLimited(_) => RATE_LIMITED,
+            AggregatorServerError::Aggregation(err) => match err {
+                BlsAggregationError::TaskInitializationError(..) => TASK_NOT_FOUND,
+                BlsAggregationError::TaskExpiredError(_)
+                | BlsAggregationError::TaskCancelledError(_)
+                | BlsAggregationError::TaskCompletedError(_) => TASK_EXPIRED,
+                BlsAggregationError::IncorrectSignatureError => INCORRECT_SIGNATURE,
+                BlsAggregationError::OperatorNotPartOfTaskQuorumError(..) => OPERATOR_NOT_IN_QUORUM,
+                BlsAggregationError::DuplicateSignatureError(..)
+                | BlsAggregationError::EquivocationError(..) => DUPLICATE_SIGNATURE,
+                _ => INTERNAL_ERROR,
+            },
+        };
+        JsonRpcError::new(code, err.to_string())
+    }
+}
+
+/// Decodes call params given either as an object or, as Go `net/rpc` clients send them, as
+/// a single-element array.
+pub fn decode_params<P: DeserializeOwned>(params: Value) -> Result<P, JsonRpcError> {
+    let params = match params {
+        Value::Array(mut params) if params.len() == 1 => params.remove(0),
+        params => params,
+    };
+    serde_json::from_value(params).map_err(|e| JsonRpcError::new(INVALID_PARAMS, e.to_string()))
+}
@@ -0,0 +1,226 @@
+//! Resource limits protecting the aggregator server from misbehaving or malicious clients.
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::types::OperatorId;
+
+/// Number of tracked clients above which buckets that have fully refilled are dropped.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Token bucket rate limit: bursts of up to `max_requests`, refilled at `max_requests` per
+/// `interval`. A zero `interval` disables the limit; otherwise zero `max_requests` refuses
+/// every request.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimit {
+    pub max_requests: u32,
+    pub interval: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max_requests: u32, interval: Duration) -> Self {
+        Self {
+            max_requests,
+            interval,
+        }
+    }
+}
+
+/// Limits enforced by the aggregator server.
+#[derive(Debug, Clone)]
+pub struct ServerLimits {
+    /// Largest accepted request body, in bytes.
+    pub max_body_size: usize,
+    /// Connections served at once; further connections wait to be accepted.
+    pub max_connections: usize,
+    /// Submissions whose ECDSA signature is recovered at once. The pairing checks of their
+    /// BLS signatures are bounded by the aggregation service, see
+    /// `BlsAggregatorService::with_max_concurrent_verifications`.
+    pub max_concurrent_verifications: usize,
+    /// Requests accepted per client IP address. Checked before the request body is read,
+    /// so rate limited clients never get an ECDSA signature recovered or a signer looked up.
+    pub per_ip_rate_limit: Option<RateLimit>,
+    /// Submissions accepted per operator id. Counted after authentication when it is
+    /// enabled, so an operator's quota cannot be used up by others claiming its id.
+    pub per_operator_rate_limit: Option<RateLimit>,
+}
+
+impl Default for ServerLimits {
+    fn default() -> Self {
+        Self {
+            max_body_size: 64 * 1024,
+            max_connections: 1024,
+            max_concurrent_verifications: 16,
+            per_ip_rate_limit: None,
+            per_operator_rate_limit: None,
+        }
+    }
+}
+
+impl ServerLimits {
+    /// Rejects limits under which the server could never serve a submission.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_connections == 0 {
+            return Err("max_connections must be at least 1".to_string());
+        }
+        if self.max_concurrent_verifications == 0 {
+            return Err("max_concurrent_verifications must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Shared state enforcing [`ServerLimits`].
+pub(crate) struct Limiter {
+    pub(crate) limits: ServerLimits,
+    pub(crate) connections: Arc<Semaphore>,
+    pub(crate) verifications: Semaphore,
+    per_ip: Option<RateLimiter<IpAddr>>,
+    per_operator: Option<RateLimiter<OperatorId>>,
+}
+
+impl Limiter {
+    pub(crate) fn new(limits: ServerLimits) -> Self {
+        Self {
+            connections: Arc::new(Semaphore::new(limits.max_connections)),
+            verifications: Semaphore::new(limits.max_concurrent_verifications),
+            per_ip: limits.per_ip_rate_limit.map(RateLimiter::new),
+            per_operator: limits.per_operator_rate_limit.map(RateLimiter::new),
+            limits,
+        }
+    }
+
+    pub(crate) fn check_ip(&self, ip: IpAddr) -> bool {
+        match &self.per_ip {
+            Some(limiter) => limiter.check(ip),
+            None => true,
+        }
+    }
+
+    pub(crate) fn check_operator(&self, operator_id: OperatorId) -> bool {
+        match &self.per_operator {
+            Some(limiter) => limiter.check(operator_id),
+            None => true,
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct RateLimiter<K> {
+    limit: RateLimit,
+    buckets: Mutex<HashMap<K, Bucket>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Takes a token from `key`'s bucket, returning false if it is empty.
+    fn check(&self, key: K) -> bool {
+        if self.limit.interval.is_zero() {
+            return true;
+        }
+        let now = Instant::now();
+        let capacity = f64::from(self.limit.max_requests);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| self.refill(bucket, now) < capacity);
+        }
+
+        let bucket = buckets.entry(key).or_insert(Bucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+        if self.refill(bucket, now) >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let capacity = f64::from(self.limit.max_requests);
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        let rate = capacity / self.limit.interval.as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated_at = now;
+        bucket.tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_bursts_then_refills() {
+        let limiter = RateLimiter::new(RateLimit::new(2, Duration::from_millis(100)));
+        assert!(limiter.check(1));
+        assert!(limiter.check(1));
+        assert!(!limiter.check(1));
+        // Keys have their own buckets
+        assert!(limiter.check(2));
+
+        // Half the interval refills one token
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(limiter.check(1));
+        assert!(!limiter.check(1));
+
+        // Idle buckets refill up to the burst size only
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(limiter.check(1));
+        assert!(limiter.check(1));
+        assert!(!limiter.check(1));
+    }
+
+    #[test]
+    fn test_rate_limiter_edge_cases() {
+        let unlimited = RateLimiter::new(RateLimit::new(1, Duration::ZERO));
+        assert!((0..100).all(|_| unlimited.check(1)));
+
+        let closed = RateLimiter::new(RateLimit::new(0, Duration::from_secs(1)));
+        assert!(!closed.check(1));
+        let disabled = RateLimiter::new(RateLimit::new(0, Duration::ZERO));
+        assert!(disabled.check(1));
+    }
+
+    #[test]
+    fn test_rate_limiter_prunes_full_buckets() {
+        let limiter = RateLimiter::new(RateLimit::new(1, Duration::from_millis(10)));
+        for key in 0..PRUNE_THRESHOLD {
+            limiter.check(key);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(limiter.check(PRUNE_THRESHOLD));
+        assert_eq!(limiter.buckets.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_server_limits_validation() {
+        assert!(ServerLimits::default().validate().is_ok());
+        assert!(ServerLimits {
+            max_connections: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+        assert!(ServerLimits {
+            max_concurrent_verifications: 0,
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
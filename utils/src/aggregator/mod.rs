@@ -0,0 +1,85 @@
+use alloy_primitives::keccak256;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::crypto::bls::Signature;
+use crate::services::bls_aggregation::BlsAggregationError;
+use crate::types::{OperatorId, TaskIndex, TaskResponseDigest};
+
+pub mod auth;
+pub mod jsonrpc;
+pub mod limits;
+pub mod server;
+
+/// A task response type that operators sign and submit to an aggregator.
+///
+/// Operators sign `keccak256(self.digest_preimage())`, which is also the digest the BLS
+/// aggregation service aggregates signatures over.
+pub trait AggregatorTaskResponse: Send + Sync + 'static {
+    /// Decodes a response from the ABI-encoded bytes carried by a [`SignedTaskResponse`].
+    fn abi_decode_response(data: &[u8]) -> Result<Self, String>
+    where
+        Self: Sized;
+
+    /// ABI-encodes the response for a [`SignedTaskResponse`].
+    fn abi_encode_response(&self) -> Vec<u8>;
+
+    /// Index of the task this response answers.
+    fn task_index(&self) -> TaskIndex;
+
+    /// Bytes whose keccak256 hash operators sign.
+    fn digest_preimage(&self) -> Vec<u8>;
+
+    fn digest(&self) -> TaskResponseDigest {
+        keccak256(self.digest_preimage())
+    }
+}
+
+/// A task response signed by an operator, as submitted to the aggregator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTaskResponse {
+    /// ABI-encoded task response.
+    pub task_response: Vec<u8>,
+    pub bls_signature: Signature,
+    pub operator_id: OperatorId,
+}
+
+#[derive(Debug, Error)]
+pub enum AggregatorServerError {
+    #[error("Invalid signed task response: {0}")]
+    InvalidRequest(String),
+    /// The submission is not signed by the ECDSA key of the operator it claims to come from.
+    #[error("Unauthenticated signed task response: {0}")]
+    Unauthenticated(String),
+    /// The operator registered for the submission's signer could not be looked up.
+    #[error("Operator lookup failed: {0}")]
+    OperatorLookup(String),
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+    #[error("{0}")]
+    Aggregation(#[from] BlsAggregationError),
+}
+
+impl AggregatorServerError {
+    /// HTTP status code reported to the submitting operator.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AggregatorServerError::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            AggregatorServerError::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+            AggregatorServerError::OperatorLookup(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AggregatorServerError::RateLimited(_) => StatusCode::TOO_MANY_REQUESTS,
+            AggregatorServerError::Aggregation(err) => match err {
+                BlsAggregationError::TaskInitializationError(..) => StatusCode::NOT_FOUND,
+                BlsAggregationError::TaskExpiredError(_)
+                | BlsAggregationError::TaskCancelledError(_)
+                | BlsAggregationError::TaskCompletedError(_) => StatusCode::GONE,
+                BlsAggregationError::IncorrectSignatureError => StatusCode::UNAUTHORIZED,
+                BlsAggregationError::OperatorNotPartOfTaskQuorumError(..) => StatusCode::FORBIDDEN,
+                BlsAggregationError::DuplicateSignatureError(..)
+                | BlsAggregationError::EquivocationError(..) => StatusCode::CONFLICT,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            },
+        }
+    }
+}
@@ -0,0 +1,968 @@
+use http_body_util::{BodyExt, Full, LengthLimitError, Limited};
+use hyper::{
+    body::{self, Bytes},
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    convert::Infallible,
+    marker::PhantomData,
+    net::IpAddr,
+    sync::Arc,
+};
+use tokio::{net::TcpListener, sync::RwLock};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::services::bls_aggregation::{BlsAggregationError, BlsAggregationService};
+use crate::tls::{serve_connection, TlsConfig};
+use crate::types::{TaskIndex, TaskResponseDigest};
+
+use super::auth::{AuthenticatedSignedTaskResponse, OperatorAuthenticator, OperatorIdResolver};
+use super::jsonrpc::{
+    decode_params, JsonRpcError, JsonRpcRequest, JsonRpcResponse, INVALID_REQUEST,
+    JSON_RPC_VERSION, METHOD_NOT_FOUND, PARSE_ERROR, PROCESS_SIGNED_TASK_RESPONSE_METHOD,
+};
+use super::limits::{Limiter, ServerLimits};
+use super::{AggregatorServerError, AggregatorTaskResponse, SignedTaskResponse};
+
+/// Path of the raw JSON POST endpoint, kept for compatibility with older operators.
+pub const PROCESS_SIGNED_TASK_RESPONSE_PATH: &str = "/Aggregator.ProcessSignedTaskResponse";
+/// Path of the JSON-RPC 2.0 endpoint.
+pub const JSON_RPC_PATH: &str = "/";
+
+type TaskResponses<R> =
+    Arc<RwLock<HashMap<TaskIndex, HashMap<TaskResponseDigest, StoredTaskResponse<R>>>>>;
+
+/// A decoded task response, kept while signatures over it are verified and once one of them
+/// is accepted.
+struct StoredTaskResponse<R> {
+    response: R,
+    /// Whether the BLS aggregation service accepted a signature over the response.
+    verified: bool,
+    /// Submissions of the response whose signature is still being verified.
+    pending_verifications: usize,
+}
+
+/// HTTP server receiving [`SignedTaskResponse`]s from operators and feeding them to a
+/// [`BlsAggregationService`].
+///
+/// Submissions are accepted as JSON-RPC 2.0 calls to
+/// [`PROCESS_SIGNED_TASK_RESPONSE_METHOD`] on [`JSON_RPC_PATH`], and as a raw JSON body
+/// POSTed to [`PROCESS_SIGNED_TASK_RESPONSE_PATH`].
+///
+/// Decoded responses are kept by task index and digest, so the AVS can look up the full
+/// response behind an aggregated digest with [`Self::task_response`]. A response is only
+/// kept once a signature over it was accepted, and is dropped with its task when the task
+/// expires, is cancelled or is removed with [`Self::remove_task_responses`].
+///
+/// With [`Self::with_operator_authentication`], submissions must also carry the operator's
+/// ECDSA signature, see [`super::auth`]. Accepted submissions are remembered to refuse their
+/// replays until their task has a result.
+///
+/// Request sizes, connections, per-client request rates and concurrent ECDSA signature
+/// recoveries are bounded by [`ServerLimits`], set with [`Self::with_limits`]. Concurrent BLS
+/// pairing checks are bounded by the aggregation service.
+///
+/// Connections speak HTTP/1.1 or HTTP/2, over TLS when set with [`Self::with_tls`]; pin
+/// operator client certificates there to require mutual TLS.
+pub struct AggregatorServer<R, S>
+where
+    R: AggregatorTaskResponse,
+    S: BlsAggregationService + Clone + Send + Sync + 'static,
+{
+    server_ip_port_addr: String,
+    bls_aggregation_service: S,
+    task_responses: TaskResponses<R>,
+    operator_authenticator: Option<Arc<OperatorAuthenticator>>,
+    limiter: Arc<Limiter>,
+    tls_config: Option<TlsConfig>,
+    _task_response: PhantomData<fn() -> R>,
+}
+
+impl<R, S> Clone for AggregatorServer<R, S>
+where
+    R: AggregatorTaskResponse,
+    S: BlsAggregationService + Clone + Send + Sync + 'static,
+{
+    fn clone(&self) -> Self {
+        Self {
+            server_ip_port_addr: self.server_ip_port_addr.clone(),
+            bls_aggregation_service: self.bls_aggregation_service.clone(),
+            task_responses: Arc::clone(&self.task_responses),
+            operator_authenticator: self.operator_authenticator.clone(),
+            limiter: Arc::clone(&self.limiter),
+            tls_config: self.tls_config.clone(),
+            _task_response: PhantomData,
+        }
+    }
+}
+
+impl<R, S> AggregatorServer<R, S>
+where
+    R: AggregatorTaskResponse,
+    S: BlsAggregationService + Clone + Send + Sync + 'static,
+{
+    pub fn new(server_ip_port_addr: &str, bls_aggregation_service: S) -> Self {
+        Self {
+            server_ip_port_addr: server_ip_port_addr.to_string(),
+            bls_aggregation_service,
+            task_responses: Arc::new(RwLock::new(HashMap::new())),
+            operator_authenticator: None,
+            limiter: Arc::new(Limiter::new(ServerLimits::default())),
+            tls_config: None,
+            _task_response: PhantomData,
+        }
+    }
+
+    /// Rejects submissions that are not signed by the ECDSA key registered for the claimed
+    /// operator id, before their BLS signature is verified.
+    pub fn with_operator_authentication(self, resolver: impl OperatorIdResolver) -> Self {
+        self.with_operator_authenticator(OperatorAuthenticator::new(resolver))
+    }
+
+    /// Like [`Self::with_operator_authentication`], with a configured authenticator.
+    pub fn with_operator_authenticator(mut self, authenticator: OperatorAuthenticator) -> Self {
+        self.operator_authenticator = Some(Arc::new(authenticator));
+        self
+    }
+
+    pub fn with_limits(mut self, limits: ServerLimits) -> Self {
+        self.limiter = Arc::new(Limiter::new(limits));
+        self
+    }
+
+    /// Serves over TLS instead of plaintext.
+    pub fn with_tls(mut self, tls_config: TlsConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Serves operators until `shutdown` is cancelled, then stops accepting connections and
+    /// returns once open connections have finished their in-flight submissions.
+    pub async fn start(
+        self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let listener = TcpListener::bind(&self.server_ip_port_addr).await?;
+        log::info!(
+            "Aggregator server listening on {}",
+            self.server_ip_port_addr
+        );
+        self.serve(listener, shutdown).await
+    }
+
+    /// Like [`Self::start`], accepting connections on an already bound `listener`.
+    pub async fn serve(
+        self,
+        listener: TcpListener,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.limiter.limits.validate()?;
+        let tls_acceptor = match &self.tls_config {
+            Some(tls_config) => Some(tls_config.build_acceptor()?),
+            None => None,
+        };
+        let this = Arc::new(self);
+        let connections = TaskTracker::new();
+        loop {
+            let connection_permit = tokio::select! {
+                permit = Arc::clone(&this.limiter.connections).acquire_owned() => permit?,
+                _ = shutdown.cancelled() => break,
+            };
+            let (stream, remote_addr) = tokio::select! {
+                accepted = listener.accept() => accepted?,
+                _ = shutdown.cancelled() => break,
+            };
+            let this = Arc::clone(&this);
+            let connection = serve_connection(
+                stream,
+                tls_acceptor.clone(),
+                service_fn(move |req| {
+                    let this = Arc::clone(&this);
+                    async move { this.router(req, remote_addr.ip()).await }
+                }),
+                shutdown.clone(),
+            );
+            connections.spawn(async move {
+                connection.await;
+                drop(connection_permit);
+            });
+        }
+        connections.close();
+        connections.wait().await;
+        Ok(())
+    }
+
+    /// Returns the decoded response an operator submitted for `task_response_digest`.
+    ///
+    /// Responses whose signature is still being verified are returned too, as the aggregated
+    /// response can be sent before their submission completes.
+    pub async fn task_response(
+        &self,
+        task_index: TaskIndex,
+        task_response_digest: TaskResponseDigest,
+    ) -> Option<R>
+    where
+        R: Clone,
+    {
+        self.task_responses
+            .read()
+            .await
+            .get(&task_index)
+            .and_then(|responses| responses.get(&task_response_digest))
+            .map(|stored| stored.response.clone())
+    }
+
+    /// Drops the responses kept for a task once the AVS no longer needs them.
+    pub async fn remove_task_responses(&self, task_index: TaskIndex) {
+        self.task_responses.write().await.remove(&task_index);
+        if let Some(authenticator) = &self.operator_authenticator {
+            authenticator.forget_task(task_index);
+        }
+    }
+
+    /// Authenticates a submission if operator authentication is enabled, then processes it
+    /// with [`Self::process_signed_task_response`].
+    pub async fn process_submission(
+        &self,
+        submission: AuthenticatedSignedTaskResponse,
+    ) -> Result<(), AggregatorServerError> {
+        let signed_task_response = match &self.operator_authenticator {
+            Some(authenticator) => {
+                // Bounds the ECDSA recoveries run at once. Released before the BLS signature
+                // is handed over, as the service may hold it for a whole batch window
+                let _verification_permit = self
+                    .limiter
+                    .verifications
+                    .acquire()
+                    .await
+                    .expect("verification semaphore is never closed");
+                authenticator.authenticate(submission).await?
+            }
+            None => submission.signed_task_response,
+        };
+        if !self
+            .limiter
+            .check_operator(signed_task_response.operator_id)
+        {
+            return Err(AggregatorServerError::RateLimited(format!(
+                "operator {}",
+                signed_task_response.operator_id
+            )));
+        }
+        let Some(authenticator) = &self.operator_authenticator else {
+            return self
+                .process_signed_task_response(signed_task_response)
+                .await;
+        };
+
+        // Replays of accepted submissions are refused before their signature is verified again
+        let task_index = R::abi_decode_response(&signed_task_response.task_response)
+            .map_err(AggregatorServerError::InvalidRequest)?
+            .task_index();
+        authenticator.check_not_replayed(task_index, &signed_task_response)?;
+        self.process_signed_task_response(signed_task_response.clone())
+            .await?;
+        if authenticator.record_accepted(task_index, &signed_task_response) {
+            // Accepted submissions are only kept until the task has a result, as the service
+            // refuses signatures for finished tasks itself
+            let bls_aggregation_service = self.bls_aggregation_service.clone();
+            let authenticator = Arc::clone(authenticator);
+            tokio::spawn(async move {
+                let _ = bls_aggregation_service.await_task_result(task_index).await;
+                authenticator.forget_task(task_index);
+            });
+        }
+        Ok(())
+    }
+
+    /// Decodes the task response and hands its signature to the BLS aggregation service,
+    /// returning the verification result. The submission is not authenticated.
+    pub async fn process_signed_task_response(
+        &self,
+        signed_task_response: SignedTaskResponse,
+    ) -> Result<(), AggregatorServerError> {
+        let task_response = R::abi_decode_response(&signed_task_response.task_response)
+            .map_err(AggregatorServerError::InvalidRequest)?;
+
+        let task_index = task_response.task_index();
+        let task_response_digest = task_response.digest();
+        let digest_preimage = task_response.digest_preimage();
+        log::info!(
+            "Aggregator received task response for task {} from operator {}",
+            task_index,
+            signed_task_response.operator_id
+        );
+
+        // Held during verification, as the aggregated response can be sent before
+        // `process_new_signature` returns
+        self.task_responses
+            .write()
+            .await
+            .entry(task_index)
+            .or_default()
+            .entry(task_response_digest)
+            .or_insert(StoredTaskResponse {
+                response: task_response,
+                verified: false,
+                pending_verifications: 0,
+            })
+            .pending_verifications += 1;
+
+        let result = self
+            .bls_aggregation_service
+            .process_new_signature(
+                task_index,
+                digest_preimage,
+                signed_task_response.bls_signature,
+                signed_task_response.operator_id,
+            )
+            .await;
+        self.settle_task_response(task_index, task_response_digest, &result)
+            .await;
+
+        Ok(result?)
+    }
+
+    /// Keeps a response once a signature over it is accepted, and drops it when its last
+    /// pending verification fails. Tasks that expired or were cancelled are dropped entirely.
+    async fn settle_task_response(
+        &self,
+        task_index: TaskIndex,
+        task_response_digest: TaskResponseDigest,
+        result: &Result<(), BlsAggregationError>,
+    ) {
+        let mut task_responses = self.task_responses.write().await;
+        if matches!(
+            result,
+            Err(BlsAggregationError::TaskExpiredError(_)
+                | BlsAggregationError::TaskCancelledError(_))
+        ) {
+            task_responses.remove(&task_index);
+            if let Some(authenticator) = &self.operator_authenticator {
+                authenticator.forget_task(task_index);
+            }
+            return;
+        }
+
+        // Missing if the task's responses were removed during verification
+        let Some(responses) = task_responses.get_mut(&task_index) else {
+            return;
+        };
+        if let Entry::Occupied(mut entry) = responses.entry(task_response_digest) {
+            let stored = entry.get_mut();
+            stored.pending_verifications -= 1;
+            stored.verified |= result.is_ok();
+            if !stored.verified && stored.pending_verifications == 0 {
+                entry.remove();
+            }
+        }
+        if responses.is_empty() {
+            task_responses.remove(&task_index);
+        }
+    }
+
+    async fn router(
+        &self,
+        req: Request<body::Incoming>,
+        remote_ip: IpAddr,
+    ) -> Result<Response<Full<Bytes>>, Infallible> {
+        log::debug!("Routing Request: {:?}", req);
+        if !self.limiter.check_ip(remote_ip) {
+            log::debug!("Rate limited request from {}", remote_ip);
+            return Ok(text_response(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too Many Requests".to_string(),
+            ));
+        }
+        match (req.method(), req.uri().path()) {
+            (&Method::POST, PROCESS_SIGNED_TASK_RESPONSE_PATH) => match self.read_body(req).await {
+                Ok(body) => Ok(self.raw_post_handler(&body).await),
+                Err(response) => Ok(response),
+            },
+            (&Method::POST, JSON_RPC_PATH) => match self.read_body(req).await {
+                Ok(body) => Ok(self.json_rpc_handler(&body).await),
+                Err(response) => Ok(response),
+            },
+            _ => Ok(text_response(
+                StatusCode::NOT_FOUND,
+                "Not Found".to_string(),
+            )),
+        }
+    }
+
+    /// Collects the request body, refusing bodies over the configured maximum size.
+    async fn read_body(
+        &self,
+        req: Request<body::Incoming>,
+    ) -> Result<Bytes, Response<Full<Bytes>>> {
+        let max_body_size = self.limiter.limits.max_body_size;
+        match Limited::new(req.into_body(), max_body_size).collect().await {
+            Ok(body) => Ok(body.to_bytes()),
+            Err(e) if e.is::<LengthLimitError>() => Err(text_response(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Request body exceeds {} bytes", max_body_size),
+            )),
+            Err(e) => Err(text_response(StatusCode::BAD_REQUEST, e.to_string())),
+        }
+    }
+
+    async fn raw_post_handler(&self, body: &[u8]) -> Response<Full<Bytes>> {
+        let result = match serde_json::from_slice(body) {
+            Ok(submission) => self.process_submission(submission).await,
+            Err(e) => Err(AggregatorServerError::InvalidRequest(e.to_string())),
+        };
+        match result {
+            Ok(()) => text_response(
+                StatusCode::OK,
+                "Task response processed successfully".to_string(),
+            ),
+            Err(e) => {
+                log::info!("Rejected signed task response: {}", e);
+                text_response(e.status_code(), e.to_string())
+            }
+        }
+    }
+
+    /// Handles a single JSON-RPC 2.0 call. Errors are reported in the response body with a
+    /// `200 OK` status, as JSON-RPC over HTTP expects; notifications get `204 No Content`.
+    async fn json_rpc_handler(&self, body: &[u8]) -> Response<Full<Bytes>> {
+        let request: JsonRpcRequest<serde_json::Value> = match serde_json::from_slice(body) {
+            Ok(request) => request,
+            Err(e) => {
+                let code = if serde_json::from_slice::<serde_json::Value>(body).is_ok() {
+                    INVALID_REQUEST
+                } else {
+                    PARSE_ERROR
+                };
+                return json_rpc_response(JsonRpcResponse::error(
+                    serde_json::Value::Null,
+                    JsonRpcError::new(code, e.to_string()),
+                ));
+            }
+        };
+
+        // Not a valid request, so answered even without an id
+        if request.jsonrpc != JSON_RPC_VERSION {
+            return json_rpc_response(JsonRpcResponse::error(
+                request.id.unwrap_or(serde_json::Value::Null),
+                JsonRpcError::new(
+                    INVALID_REQUEST,
+                    format!("Unsupported JSON-RPC version: {:?}", request.jsonrpc),
+                ),
+            ));
+        }
+
+        let result = if request.method != PROCESS_SIGNED_TASK_RESPONSE_METHOD {
+            Err(JsonRpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", request.method),
+            ))
+        } else {
+            match decode_params(request.params) {
+                Ok(submission) => self.process_submission(submission).await.map_err(|e| {
+                    log::info!("Rejected signed task response: {}", e);
+                    JsonRpcError::from(&e)
+                }),
+                Err(e) => Err(e),
+            }
+        };
+
+        let Some(id) = request.id else {
+            return Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Full::new(Bytes::new()))
+                .unwrap();
+        };
+        json_rpc_response(match result {
+            Ok(()) => JsonRpcResponse::result(id, serde_json::Value::Bool(true)),
+            Err(e) => JsonRpcResponse::error(id, e),
+        })
+    }
+}
+
+fn json_rpc_response(response: JsonRpcResponse) -> Response<Full<Bytes>> {
+    let body = serde_json::to_string(&response).unwrap();
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggregator::auth::OperatorIdResolver;
+    use crate::aggregator::jsonrpc::{TASK_EXPIRED, TASK_NOT_FOUND};
+    use crate::aggregator::limits::RateLimit;
+    use crate::crypto::bls::Signature;
+    use crate::services::bls_aggregation::{BlsAggregationServiceResponse, TaskStatus};
+    use crate::types::AvsError;
+    use crate::types::{OperatorId, QuorumThresholdPercentage, TaskResponse};
+    use alloy_primitives::Address;
+    use alloy_primitives::{Bytes as AlloyBytes, B256};
+    use async_trait::async_trait;
+    use k256::ecdsa::SigningKey;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use tokio::sync::Notify;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestTaskResponse {
+        task_index: TaskIndex,
+        value: u8,
+    }
+
+    impl AggregatorTaskResponse for TestTaskResponse {
+        fn abi_decode_response(data: &[u8]) -> Result<Self, String> {
+            match data {
+                [a, b, c, d, value] => Ok(Self {
+                    task_index: u32::from_be_bytes([*a, *b, *c, *d]),
+                    value: *value,
+                }),
+                _ => Err(format!("expected 5 bytes, got {}", data.len())),
+            }
+        }
+
+        fn abi_encode_response(&self) -> Vec<u8> {
+            let mut data = self.task_index.to_be_bytes().to_vec();
+            data.push(self.value);
+            data
+        }
+
+        fn task_index(&self) -> TaskIndex {
+            self.task_index
+        }
+
+        fn digest_preimage(&self) -> Vec<u8> {
+            vec![self.value]
+        }
+    }
+
+    /// Answers every signature with `result`, after `delay`. Tasks complete once
+    /// `task_completed` is notified.
+    #[derive(Clone)]
+    struct MockAggregationService {
+        result: Arc<Mutex<Result<(), BlsAggregationError>>>,
+        signatures: Arc<AtomicUsize>,
+        delay: Duration,
+        signature_received: Arc<Notify>,
+        task_completed: Arc<Notify>,
+    }
+
+    impl MockAggregationService {
+        fn new() -> Self {
+            Self {
+                result: Arc::new(Mutex::new(Ok(()))),
+                signatures: Arc::new(AtomicUsize::new(0)),
+                delay: Duration::ZERO,
+                signature_received: Arc::new(Notify::new()),
+                task_completed: Arc::new(Notify::new()),
+            }
+        }
+
+        fn set_result(&self, result: Result<(), BlsAggregationError>) {
+            *self.result.lock().unwrap() = result;
+        }
+    }
+
+    #[async_trait]
+    impl BlsAggregationService for MockAggregationService {
+        async fn initialize_new_task(
+            &self,
+            _task_index: TaskIndex,
+            _task_created_block: u32,
+            _quorum_numbers: AlloyBytes,
+            _quorum_threshold_percentages: Vec<QuorumThresholdPercentage>,
+            _time_to_expiry: Duration,
+        ) -> Result<(), BlsAggregationError> {
+            Ok(())
+        }
+
+        async fn process_new_signature(
+            &self,
+            _task_index: TaskIndex,
+            _task_response: TaskResponse,
+            _bls_signature: Signature,
+            _operator_id: OperatorId,
+        ) -> Result<(), BlsAggregationError> {
+            self.signatures.fetch_add(1, Ordering::SeqCst);
+            self.signature_received.notify_one();
+            tokio::time::sleep(self.delay).await;
+            self.result.lock().unwrap().clone()
+        }
+
+        async fn cancel_task(&self, _task_index: TaskIndex) -> Result<(), BlsAggregationError> {
+            Ok(())
+        }
+
+        async fn extend_task_expiry(
+            &self,
+            _task_index: TaskIndex,
+            _extension: Duration,
+        ) -> Result<(), BlsAggregationError> {
+            Ok(())
+        }
+
+        async fn get_task_status(
+            &self,
+            task_index: TaskIndex,
+        ) -> Result<TaskStatus, BlsAggregationError> {
+            Err(BlsAggregationError::TaskInitializationError(
+                "Task not initialized".to_string(),
+                task_index,
+            ))
+        }
+
+        async fn await_task_result(
+            &self,
+            task_index: TaskIndex,
+        ) -> Result<BlsAggregationServiceResponse, BlsAggregationError> {
+            self.task_completed.notified().await;
+            Ok(BlsAggregationServiceResponse {
+                task_index,
+                ..Default::default()
+            })
+        }
+    }
+
+    fn signed(task_response: &TestTaskResponse) -> SignedTaskResponse {
+        SignedTaskResponse {
+            task_response: task_response.abi_encode_response(),
+            bls_signature: Signature::new_zero(),
+            operator_id: B256::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_task_responses_are_kept_once_verified() {
+        let service = MockAggregationService::new();
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", service.clone());
+        let accepted = TestTaskResponse {
+            task_index: 1,
+            value: 7,
+        };
+        let forged = TestTaskResponse {
+            task_index: 1,
+            value: 8,
+        };
+
+        service.set_result(Err(BlsAggregationError::IncorrectSignatureError));
+        assert!(server
+            .process_signed_task_response(signed(&accepted))
+            .await
+            .is_err());
+        assert_eq!(server.task_response(1, accepted.digest()).await, None);
+        assert!(server.task_responses.read().await.is_empty());
+
+        service.set_result(Ok(()));
+        server
+            .process_signed_task_response(signed(&accepted))
+            .await
+            .unwrap();
+        assert_eq!(
+            server.task_response(1, accepted.digest()).await,
+            Some(accepted.clone())
+        );
+
+        // A rejected submission neither keeps its response nor drops the verified one
+        service.set_result(Err(BlsAggregationError::IncorrectSignatureError));
+        assert!(server
+            .process_signed_task_response(signed(&forged))
+            .await
+            .is_err());
+        assert!(server
+            .process_signed_task_response(signed(&accepted))
+            .await
+            .is_err());
+        assert_eq!(server.task_response(1, forged.digest()).await, None);
+        assert_eq!(
+            server.task_response(1, accepted.digest()).await,
+            Some(accepted.clone())
+        );
+
+        server.remove_task_responses(1).await;
+        assert!(server.task_responses.read().await.is_empty());
+    }
+
+    async fn json_rpc_call(
+        server: &AggregatorServer<TestTaskResponse, MockAggregationService>,
+        body: serde_json::Value,
+    ) -> (StatusCode, Option<JsonRpcResponse>) {
+        let response = server.json_rpc_handler(body.to_string().as_bytes()).await;
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn test_json_rpc_requests() {
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", MockAggregationService::new());
+        let params = serde_json::to_value([signed(&TestTaskResponse {
+            task_index: 1,
+            value: 7,
+        })])
+        .unwrap();
+        let request = |jsonrpc: &str| {
+            serde_json::json!({
+                "jsonrpc": jsonrpc,
+                "method": PROCESS_SIGNED_TASK_RESPONSE_METHOD,
+                "params": params,
+            })
+        };
+
+        let mut call = request("2.0");
+        call["id"] = 3.into();
+        let (status, response) = json_rpc_call(&server, call).await;
+        let response = response.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.id, 3);
+        assert_eq!(response.result, Some(true.into()));
+
+        // A null id is a call, not a notification
+        let mut call = request("2.0");
+        call["id"] = serde_json::Value::Null;
+        let (status, response) = json_rpc_call(&server, call).await;
+        let response = response.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.id, serde_json::Value::Null);
+        assert_eq!(response.result, Some(true.into()));
+
+        let (status, response) = json_rpc_call(&server, request("2.0")).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        assert!(response.is_none());
+
+        // Other versions are rejected, even as notifications
+        for jsonrpc in ["1.0", ""] {
+            let (status, response) = json_rpc_call(&server, request(jsonrpc)).await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(response.unwrap().error.unwrap().code, INVALID_REQUEST);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_tasks_are_rejected_permanently() {
+        let service = MockAggregationService::new();
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", service.clone());
+        let task_response = TestTaskResponse {
+            task_index: 1,
+            value: 7,
+        };
+        server
+            .process_signed_task_response(signed(&task_response))
+            .await
+            .unwrap();
+
+        // Responses of an expired task are dropped
+        service.set_result(Err(BlsAggregationError::TaskExpiredError(1)));
+        let err = server
+            .process_signed_task_response(signed(&task_response))
+            .await
+            .unwrap_err();
+        assert!(server.task_responses.read().await.is_empty());
+        assert_eq!(err.status_code(), StatusCode::GONE);
+        assert_eq!(JsonRpcError::from(&err).code, TASK_EXPIRED);
+
+        for err in [
+            BlsAggregationError::TaskCancelledError(1),
+            BlsAggregationError::TaskCompletedError(1),
+        ] {
+            let err = AggregatorServerError::Aggregation(err);
+            assert_eq!(err.status_code(), StatusCode::GONE);
+            assert_eq!(JsonRpcError::from(&err).code, TASK_EXPIRED);
+        }
+        let err = AggregatorServerError::Aggregation(BlsAggregationError::TaskInitializationError(
+            "Task not initialized".to_string(),
+            1,
+        ));
+        assert_eq!(err.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(JsonRpcError::from(&err).code, TASK_NOT_FOUND);
+    }
+
+    /// Resolves every signer to the same operator id, counting lookups.
+    #[derive(Clone, Default)]
+    struct CountingResolver {
+        lookups: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl OperatorIdResolver for CountingResolver {
+        async fn resolve_operator_id(
+            &self,
+            _operator_address: Address,
+        ) -> Result<OperatorId, AvsError> {
+            self.lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(B256::repeat_byte(1))
+        }
+    }
+
+    fn authenticated(task_response: &TestTaskResponse) -> AuthenticatedSignedTaskResponse {
+        let mut signed_task_response = signed(task_response);
+        signed_task_response.operator_id = B256::repeat_byte(1);
+        AuthenticatedSignedTaskResponse::sign(
+            signed_task_response,
+            &SigningKey::random(&mut rand::thread_rng()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_replayed_submissions_are_not_verified_again() {
+        let service = MockAggregationService::new();
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", service.clone())
+                .with_operator_authentication(CountingResolver::default());
+        let submission = authenticated(&TestTaskResponse {
+            task_index: 1,
+            value: 7,
+        });
+
+        // A submission that failed can be retried
+        service.set_result(Err(BlsAggregationError::TaskInitializationError(
+            "Task not initialized".to_string(),
+            1,
+        )));
+        assert!(server.process_submission(submission.clone()).await.is_err());
+        service.set_result(Ok(()));
+        server.process_submission(submission.clone()).await.unwrap();
+        assert_eq!(service.signatures.load(Ordering::SeqCst), 2);
+
+        // Once accepted, it is refused before reaching the aggregation service
+        assert!(matches!(
+            server.process_submission(submission.clone()).await,
+            Err(AggregatorServerError::Aggregation(
+                BlsAggregationError::DuplicateSignatureError(_, 1)
+            ))
+        ));
+        assert_eq!(service.signatures.load(Ordering::SeqCst), 2);
+
+        server.remove_task_responses(1).await;
+        server.process_submission(submission.clone()).await.unwrap();
+        assert_eq!(service.signatures.load(Ordering::SeqCst), 3);
+
+        // Submissions are also forgotten once the task completes
+        let authenticator = server.operator_authenticator.clone().unwrap();
+        let signed_task_response = submission.signed_task_response.clone();
+        for _ in 0..100 {
+            if authenticator
+                .check_not_replayed(1, &signed_task_response)
+                .is_ok()
+            {
+                break;
+            }
+            service.task_completed.notify_waiters();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        server.process_submission(submission).await.unwrap();
+        assert_eq!(service.signatures.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_submissions() {
+        let service = MockAggregationService {
+            delay: Duration::from_millis(300),
+            ..MockAggregationService::new()
+        };
+        let signature_received = Arc::clone(&service.signature_received);
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", service);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}{}",
+            listener.local_addr().unwrap(),
+            PROCESS_SIGNED_TASK_RESPONSE_PATH
+        );
+        let shutdown = CancellationToken::new();
+        let serving = tokio::spawn(server.serve(listener, shutdown.clone()));
+
+        let client = reqwest::Client::new();
+        let submission = serde_json::to_vec(&signed(&TestTaskResponse {
+            task_index: 1,
+            value: 7,
+        }))
+        .unwrap();
+        let in_flight = tokio::spawn(client.post(&url).body(submission.clone()).send());
+        signature_received.notified().await;
+        shutdown.cancel();
+
+        // The in-flight submission completes, then the server stops
+        assert_eq!(in_flight.await.unwrap().unwrap().status(), StatusCode::OK);
+        tokio::time::timeout(Duration::from_secs(5), serving)
+            .await
+            .expect("server did not stop after draining")
+            .unwrap()
+            .unwrap();
+        assert!(client.post(&url).body(submission).send().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unservable_limits_are_rejected() {
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", MockAggregationService::new()).with_limits(
+                ServerLimits {
+                    max_connections: 0,
+                    ..Default::default()
+                },
+            );
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        assert!(server
+            .serve(listener, CancellationToken::new())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_per_ip_rate_limit_applies_before_authentication() {
+        let resolver = CountingResolver::default();
+        let lookups = Arc::clone(&resolver.lookups);
+        let server: AggregatorServer<TestTaskResponse, _> =
+            AggregatorServer::new("127.0.0.1:0", MockAggregationService::new())
+                .with_operator_authentication(resolver)
+                .with_limits(ServerLimits {
+                    per_ip_rate_limit: Some(RateLimit::new(1, Duration::from_secs(3600))),
+                    ..Default::default()
+                });
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}{}",
+            listener.local_addr().unwrap(),
+            PROCESS_SIGNED_TASK_RESPONSE_PATH
+        );
+        let shutdown = CancellationToken::new();
+        let serving = tokio::spawn(server.serve(listener, shutdown.clone()));
+
+        let client = reqwest::Client::new();
+        let mut statuses = Vec::new();
+        for value in 0..3 {
+            let submission = authenticated(&TestTaskResponse {
+                task_index: 1,
+                value,
+            });
+            let response = client
+                .post(&url)
+                .body(serde_json::to_vec(&submission).unwrap())
+                .send()
+                .await
+                .unwrap();
+            statuses.push(response.status().as_u16());
+        }
+        assert_eq!(statuses, [200, 429, 429]);
+        // Each submission has its own signer, so only the first was looked up
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        shutdown.cancel();
+        serving.await.unwrap().unwrap();
+    }
+}
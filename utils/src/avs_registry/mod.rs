@@ -1,7 +1,10 @@
 use alloy_primitives::Address;
+use std::time::Duration;
 
 use eigen_contracts::RegistryCoordinator;
 
+use crate::metrics::{SdkCallTimer, SdkCallsCollector};
+use crate::subscriptions::confirmed::clamp_confirmations;
 use crate::{el_contracts::ElChainContractManager, types::AvsError, Config};
 
 pub mod reader;
@@ -21,6 +24,9 @@ pub struct AvsRegistryContractManager<T: Config> {
     eth_client_ws: T::PW,
     el_contract_manager: ElChainContractManager<T>,
     signer: T::S,
+    call_metrics: Option<SdkCallsCollector>,
+    log_confirmations: u64,
+    log_polling_interval: Option<Duration>,
 }
 
 impl<T: Config> AvsRegistryContractManager<T> {
@@ -68,6 +74,39 @@ impl<T: Config> AvsRegistryContractManager<T> {
             eth_client_ws,
             el_contract_manager,
             signer,
+            call_metrics: None,
+            log_confirmations: 0,
+            log_polling_interval: None,
         })
     }
+
+    /// Records the duration and count of every reader and writer call into `call_metrics`.
+    pub fn with_call_metrics(mut self, call_metrics: SdkCallsCollector) -> Self {
+        self.call_metrics = Some(call_metrics);
+        self
+    }
+
+    /// Delivers subscribed logs once `log_confirmations` blocks were built on top of them,
+    /// clamped to [`MAX_CONFIRMATIONS`] so that removals of confirmed logs are still reported.
+    ///
+    /// [`MAX_CONFIRMATIONS`]: crate::subscriptions::confirmed::MAX_CONFIRMATIONS
+    pub fn with_log_confirmations(mut self, log_confirmations: u64) -> Self {
+        self.log_confirmations = clamp_confirmations(log_confirmations);
+        self
+    }
+
+    /// Polls for subscribed logs over the HTTP provider every `log_polling_interval` instead
+    /// of subscribing over the websocket provider, for endpoints that only expose HTTP.
+    pub fn with_log_polling(mut self, log_polling_interval: Duration) -> Self {
+        self.log_polling_interval = Some(log_polling_interval);
+        self
+    }
+
+    /// Times a reader or writer call. Calls delegating to another call are only recorded as
+    /// that call, so that they are not counted twice.
+    pub(crate) fn call_timer(&self, call: &'static str) -> Option<SdkCallTimer> {
+        self.call_metrics
+            .as_ref()
+            .map(|call_metrics| call_metrics.start_timer(call))
+    }
 }
@@ -87,6 +87,7 @@ pub trait AvsRegistryChainReaderTrait {
 
 impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
     async fn get_quorum_count(&self) -> AvsRegistryContractResult<u8> {
+        let _timer = self.call_timer("get_quorum_count");
         let registry_coordinator =
             RegistryCoordinator::new(self.registry_coordinator_addr, self.eth_client_http.clone());
         registry_coordinator
@@ -111,6 +112,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         quorum_numbers: Bytes,
         block_number: u64,
     ) -> AvsRegistryContractResult<Vec<Vec<OperatorStateRetriever::Operator>>> {
+        let _timer = self.call_timer("get_operators_stake_in_quorums_at_block");
         let operator_state_retriever = OperatorStateRetriever::new(
             self.operator_state_retriever_addr,
             self.eth_client_http.clone(),
@@ -131,6 +133,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         &self,
         quorum_numbers: Bytes,
     ) -> AvsRegistryContractResult<Vec<Vec<Address>>> {
+        let _timer = self.call_timer("get_operator_addrs_in_quorums_at_current_block");
         let current_block = self.eth_client_http.get_block_number().await?;
         let operator_state_retriever = OperatorStateRetriever::new(
             self.operator_state_retriever_addr,
@@ -162,6 +165,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         operator_id: OperatorId,
         block_number: u64,
     ) -> AvsRegistryContractResult<(QuorumNums, Vec<Vec<OperatorStateRetriever::Operator>>)> {
+        let _timer = self.call_timer("get_operators_stake_in_quorums_of_operator_at_block");
         let operator_state_retriever = OperatorStateRetriever::new(
             self.operator_state_retriever_addr,
             self.eth_client_http.clone(),
@@ -192,6 +196,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         &self,
         operator_id: OperatorId,
     ) -> AvsRegistryContractResult<HashMap<QuorumNum, StakeAmount>> {
+        let _timer = self.call_timer("get_operator_stake_in_quorums_of_operator_at_current_block");
         let registry_coordinator =
             RegistryCoordinator::new(self.registry_coordinator_addr, self.eth_client_http.clone());
         let quorum_bitmap = registry_coordinator
@@ -228,6 +233,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         quorum_numbers: Bytes,
         non_signer_operator_ids: Vec<OperatorId>,
     ) -> AvsRegistryContractResult<OperatorStateRetriever::CheckSignaturesIndices> {
+        let _timer = self.call_timer("get_check_signatures_indices");
         let operator_state_retriever = OperatorStateRetriever::new(
             self.operator_state_retriever_addr,
             self.eth_client_http.clone(),
@@ -249,6 +255,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         &self,
         operator_address: Address,
     ) -> AvsRegistryContractResult<OperatorId> {
+        let _timer = self.call_timer("get_operator_id");
         let registry_coordinator =
             RegistryCoordinator::new(self.registry_coordinator_addr, self.eth_client_http.clone());
         registry_coordinator
@@ -263,6 +270,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         &self,
         operator_id: OperatorId,
     ) -> AvsRegistryContractResult<Address> {
+        let _timer = self.call_timer("get_operator_from_id");
         let registry_coordinator =
             RegistryCoordinator::new(self.registry_coordinator_addr, self.eth_client_http.clone());
         registry_coordinator
@@ -277,6 +285,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         &self,
         operator_address: Address,
     ) -> AvsRegistryContractResult<bool> {
+        let _timer = self.call_timer("is_operator_registered");
         let registry_coordinator =
             RegistryCoordinator::new(self.registry_coordinator_addr, self.eth_client_http.clone());
         let operator_status = registry_coordinator
@@ -294,6 +303,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         stop_block: u64,
         block_range: u64,
     ) -> AvsRegistryContractResult<(Vec<Address>, Vec<OperatorPubkeys>)> {
+        let _timer = self.call_timer("query_existing_registered_operator_pubkeys");
         let mut operator_addresses = Vec::new();
         let mut operator_pubkeys = Vec::new();
 
@@ -338,6 +348,7 @@ impl<T: Config> AvsRegistryChainReaderTrait for AvsRegistryContractManager<T> {
         stop_block: u64,
         block_range: u64,
     ) -> AvsRegistryContractResult<HashMap<OperatorId, Socket>> {
+        let _timer = self.call_timer("query_existing_registered_operator_sockets");
         let mut operator_id_to_socket_map = HashMap::new();
 
         let start = start_block;
@@ -1,9 +1,10 @@
 #![allow(async_fn_in_trait)]
-use alloy_provider::Provider;
-use alloy_pubsub::Subscription;
 use alloy_rpc_types::Filter;
-use alloy_rpc_types::Log;
 
+use crate::subscriptions::confirmed::ConfirmedLogSubscriber;
+use crate::subscriptions::polling::PollingLogSubscriber;
+use crate::subscriptions::resilient::ResilientLogSubscriber;
+use crate::subscriptions::LogSubscription;
 use crate::Config;
 
 use super::AvsRegistryContractManager;
@@ -12,31 +13,61 @@ use super::AvsRegistryContractResult;
 pub trait AvsRegistryChainSubscriberTrait {
     async fn subscribe_to_new_pubkey_registrations(
         &self,
-    ) -> AvsRegistryContractResult<Subscription<Log>>;
+    ) -> AvsRegistryContractResult<LogSubscription>;
 
     async fn subscribe_to_operator_socket_updates(
         &self,
-    ) -> AvsRegistryContractResult<Subscription<Log>>;
+    ) -> AvsRegistryContractResult<LogSubscription>;
 }
 
 impl<T: Config> AvsRegistryChainSubscriberTrait for AvsRegistryContractManager<T> {
     async fn subscribe_to_new_pubkey_registrations(
         &self,
-    ) -> AvsRegistryContractResult<Subscription<Log>> {
+    ) -> AvsRegistryContractResult<LogSubscription> {
         let filter = Filter::new()
             .address(self.bls_apk_registry_addr)
             .event("NewPubkeyRegistration");
-        let subscription = self.eth_client_ws.subscribe_logs(&filter).await?;
-        Ok(subscription)
+        self.subscribe_logs(filter).await
     }
 
     async fn subscribe_to_operator_socket_updates(
         &self,
-    ) -> AvsRegistryContractResult<Subscription<Log>> {
+    ) -> AvsRegistryContractResult<LogSubscription> {
         let filter = Filter::new()
             .address(self.registry_coordinator_addr)
             .event("OperatorSocketUpdate");
-        let subscription = self.eth_client_ws.subscribe_logs(&filter).await?;
+        self.subscribe_logs(filter).await
+    }
+}
+
+impl<T: Config> AvsRegistryContractManager<T> {
+    async fn subscribe_logs(&self, filter: Filter) -> AvsRegistryContractResult<LogSubscription> {
+        let subscription = match self.log_polling_interval {
+            Some(log_polling_interval) => {
+                let subscription =
+                    PollingLogSubscriber::<_, T::TH>::new(self.eth_client_http.clone(), filter)
+                        .with_poll_interval(log_polling_interval)
+                        .subscribe()
+                        .await?;
+                ConfirmedLogSubscriber::<_, T::TH>::new(
+                    self.eth_client_http.clone(),
+                    self.log_confirmations,
+                )
+                .with_head_poll_interval(log_polling_interval)
+                .confirm(subscription)
+            }
+            None => {
+                let subscription =
+                    ResilientLogSubscriber::<_, T::TW>::new(self.eth_client_ws.clone(), filter)
+                        .subscribe()
+                        .await?;
+                ConfirmedLogSubscriber::<_, T::TW>::new(
+                    self.eth_client_ws.clone(),
+                    self.log_confirmations,
+                )
+                .confirm(subscription)
+            }
+        };
         Ok(subscription)
     }
 }
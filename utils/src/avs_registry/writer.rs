@@ -57,6 +57,7 @@ impl<T: Config> AvsRegistryChainWriterTrait for AvsRegistryContractManager<T> {
         quorum_numbers: Bytes,
         socket: String,
     ) -> AvsRegistryContractResult<TransactionReceipt> {
+        let _timer = self.call_timer("register_operator");
         let operator_addr = operator_ecdsa_private_key.verifying_key().to_address();
         log::info!("Operator address: {:?}", operator_addr);
         let registry_coordinator =
@@ -168,6 +169,7 @@ impl<T: Config> AvsRegistryChainWriterTrait for AvsRegistryContractManager<T> {
         quorum_numbers: Bytes,
         socket: String,
     ) -> AvsRegistryContractResult<TransactionReceipt> {
+        let _timer = self.call_timer("register_operator_in_quorum_with_avs_registry_coordinator");
         let verifying_key = VerifyingKey::from(operator_ecdsa_private_key);
         let operator_addr = verifying_key.to_address();
         log::info!("Operator address: {:?}", operator_addr);
@@ -264,6 +266,7 @@ impl<T: Config> AvsRegistryChainWriterTrait for AvsRegistryContractManager<T> {
         operators_per_quorum: Vec<Vec<Address>>,
         quorum_numbers: Bytes,
     ) -> AvsRegistryContractResult<TransactionReceipt> {
+        let _timer = self.call_timer("update_stakes_of_entire_operator_set_for_quorums");
         log::info!("Updating stakes for entire operator set");
 
         let registry_coordinator =
@@ -284,6 +287,7 @@ impl<T: Config> AvsRegistryChainWriterTrait for AvsRegistryContractManager<T> {
         &self,
         operators: Vec<Address>,
     ) -> AvsRegistryContractResult<TransactionReceipt> {
+        let _timer = self.call_timer("update_stakes_of_operator_subset_for_all_quorums");
         log::info!("Updating stakes of operator subset for all quorums");
 
         let registry_coordinator =
@@ -304,6 +308,7 @@ impl<T: Config> AvsRegistryChainWriterTrait for AvsRegistryContractManager<T> {
         &self,
         quorum_numbers: Bytes,
     ) -> AvsRegistryContractResult<TransactionReceipt> {
+        let _timer = self.call_timer("deregister_operator");
         log::info!("Deregistering operator with the AVS's registry coordinator");
 
         let registry_coordinator =
@@ -2,10 +2,11 @@ use super::bn254::{
     get_g1_generator, get_g2_generator, map_to_curve, mul_by_generator_g1, point_to_u256,
     u256_to_point,
 };
+use super::pairing_products::cfg_multi_pairing;
 use crate::types::AvsError;
 use alloy_primitives::U256;
 use ark_bn254::Fq as F;
-use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine};
+use ark_bn254::{Bn254, Fr, G1Affine, G1Projective, G2Affine, G2Projective};
 use ark_ec::pairing::Pairing;
 use ark_ec::{AffineRepr, CurveGroup, Group};
 use ark_ff::{BigInteger256, PrimeField};
@@ -17,10 +18,11 @@ use base64::prelude::*;
 use chacha20poly1305::aead::Aead;
 use chacha20poly1305::{AeadCore, ChaCha20Poly1305, KeyInit, Nonce};
 use hex::FromHex;
-use rand::thread_rng;
+use rand::{thread_rng, Rng};
 use scrypt::password_hash::{PasswordHashString, SaltString};
 use scrypt::{password_hash, Params, Scrypt};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::fs;
 use std::ops::{Add, Neg, Sub};
@@ -255,7 +257,11 @@ impl G2Point {
 }
 
 /// Converts a [G1Point] to a [G1Affine]. Will panic if the provided point is not on the curve.
+/// `(0, 0)` is treated as the point at infinity, matching the EVM precompile encoding.
 pub fn g1_point_to_ark_point(pt: &G1Point) -> G1Affine {
+    if pt.x.is_zero() && pt.y.is_zero() {
+        return G1Affine::identity();
+    }
     G1Affine::new(u256_to_point(pt.x), u256_to_point(pt.y))
 }
 
@@ -280,7 +286,11 @@ pub fn ark_point_to_g1_point(pt: &G1Affine) -> G1Point {
 }
 
 /// Converts a [G2Point] to a [G2Affine]. Will panic if the provided point is not on the curve.
+/// `(0, 0)` is treated as the point at infinity, matching the EVM precompile encoding.
 pub fn g2_point_to_ark_point(pt: &G2Point) -> G2Affine {
+    if pt.x.iter().chain(pt.y.iter()).all(|c| c.is_zero()) {
+        return G2Affine::identity();
+    }
     G2Affine::new(
         QuadExtField {
             c0: u256_to_point(pt.x[1]),
@@ -377,6 +387,48 @@ impl Signature {
         let pairing_result = Bn254::multi_pairing(p_projective, q_projective);
         Ok(pairing_result.0.is_one())
     }
+
+    /// Verifies a batch of `(signature, pubkey, message)` triples with a single multi-pairing.
+    ///
+    /// Each verification equation is weighted by a random 128-bit scalar before the equations
+    /// are summed, so a batch containing an invalid signature only passes with negligible
+    /// probability. Pubkeys signing the same message share a single pairing. Returns `false`
+    /// if any signature in the batch is invalid, without identifying which one.
+    pub fn verify_batch(batch: &[(Signature, G2Point, [u8; 32])]) -> Result<bool, AvsError> {
+        if batch.is_empty() {
+            return Ok(true);
+        }
+
+        let mut rng = thread_rng();
+        let mut agg_sig = G1Projective::zero();
+        let mut pubkeys_by_message: HashMap<[u8; 32], G2Projective> = HashMap::new();
+
+        for (signature, pubkey, pre_hashed_message) in batch {
+            let sig = G1Affine::new_unchecked(
+                u256_to_point(signature.g1_point.x),
+                u256_to_point(signature.g1_point.y),
+            );
+            if !sig.is_on_curve() || !sig.is_in_correct_subgroup_assuming_on_curve() {
+                return Ok(false);
+            }
+
+            let r = Fr::from(rng.gen::<u128>());
+            agg_sig += sig * r;
+            *pubkeys_by_message
+                .entry(*pre_hashed_message)
+                .or_insert_with(G2Projective::zero) += pubkey.to_ark_g2() * r;
+        }
+
+        let mut left: Vec<G1Projective> = pubkeys_by_message.keys().map(map_to_curve).collect();
+        let mut right: Vec<G2Projective> = pubkeys_by_message.into_values().collect();
+        left.push(-agg_sig);
+        right.push(get_g2_generator().into_group());
+
+        let pairing_result = cfg_multi_pairing::<Bn254>(&left, &right).ok_or(
+            AvsError::KeyError("Final exponentiation failed during batch verification".to_string()),
+        )?;
+        Ok(pairing_result.0.is_one())
+    }
 }
 
 pub type PrivateKey = Fr;
@@ -557,7 +609,7 @@ impl KeyPair {
 
 #[cfg(test)]
 mod tests {
-    use crate::crypto::bls::{g1_point_to_g1_projective, G1Point, G2Point, KeyPair};
+    use crate::crypto::bls::{g1_point_to_g1_projective, G1Point, G2Point, KeyPair, Signature};
     use ark_bn254::Fq as F;
     use ark_bn254::{Fr, G1Affine, G1Projective, G2Affine, G2Projective};
     use ark_ec::CurveGroup;
@@ -642,6 +694,55 @@ mod tests {
         assert!(!signature.verify(&different_pub_key, &message).unwrap());
     }
 
+    #[tokio::test]
+    async fn test_zero_points_are_the_identity() {
+        let keypair = KeyPair::gen_random();
+
+        // Aggregates start from zero points, as in the `AggregatedOperators` defaults
+        let mut apk_g2 = G2Point::zero();
+        apk_g2.add(&keypair.get_pub_key_g2());
+        assert_eq!(apk_g2.to_ark_g2(), keypair.get_pub_key_g2().to_ark_g2());
+
+        let signature = keypair.sign_message(&[0u8; 32]);
+        let mut agg_sig_g1 = G1Point::zero();
+        agg_sig_g1.add(&signature.g1_point);
+        assert_eq!(agg_sig_g1, signature.g1_point);
+    }
+
+    #[tokio::test]
+    async fn test_signature_batch_verification() {
+        let mut shared_message = [0u8; 32];
+        thread_rng().fill(&mut shared_message);
+
+        let mut batch: Vec<(Signature, G2Point, [u8; 32])> = (0..6)
+            .map(|i| {
+                let keypair = KeyPair::gen_random();
+                // Mix signers of a shared message with signers of their own message
+                let message = if i % 2 == 0 {
+                    shared_message
+                } else {
+                    let mut message = [0u8; 32];
+                    thread_rng().fill(&mut message);
+                    message
+                };
+                (
+                    keypair.sign_message(&message),
+                    keypair.get_pub_key_g2(),
+                    message,
+                )
+            })
+            .collect();
+
+        assert!(Signature::verify_batch(&[]).unwrap());
+        assert!(Signature::verify_batch(&batch).unwrap());
+
+        // A single signature over the wrong message invalidates the whole batch
+        let mut wrong_message = [0u8; 32];
+        thread_rng().fill(&mut wrong_message);
+        batch[3].0 = KeyPair::gen_random().sign_message(&wrong_message);
+        assert!(!Signature::verify_batch(&batch).unwrap());
+    }
+
     #[tokio::test]
     async fn test_keypair_from_string() {
         let bigint = BigInt([
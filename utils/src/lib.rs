@@ -4,11 +4,16 @@ use alloy_provider::Provider;
 use alloy_signer::Signer;
 use alloy_transport::Transport;
 
+pub mod aggregator;
 pub mod avs_registry;
 pub mod crypto;
 pub mod el_contracts;
+pub mod metrics;
 pub mod node_api;
+pub mod providers;
 pub mod services;
+pub mod subscriptions;
+pub mod tls;
 pub mod types;
 pub mod utils;
 // pub mod curve_utils;
@@ -0,0 +1,393 @@
+//! Metrics defined by the EigenLayer AVS node metrics spec, and a server exposing them to
+//! Prometheus.
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use prometheus::{
+    CounterVec, Encoder, Gauge, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry,
+    TextEncoder, TEXT_FORMAT,
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::tls::{serve_connection, TlsConfig};
+
+const NAMESPACE: &str = "eigen";
+const METRICS_PATH: &str = "/metrics";
+
+fn avs_name_label(avs_name: &str) -> HashMap<String, String> {
+    HashMap::from([("avs_name".to_string(), avs_name.to_string())])
+}
+
+/// Economic and performance metrics every AVS node reports.
+///
+/// The collectors are reference counted, so clones record into the same series.
+#[derive(Debug, Clone)]
+pub struct EigenMetrics {
+    fees_earned_total: CounterVec,
+    performance_score: Gauge,
+}
+
+impl EigenMetrics {
+    /// Creates the metrics and registers them against `registry`. The performance score
+    /// starts at 100.
+    pub fn new(avs_name: &str, registry: &Registry) -> Result<Self, prometheus::Error> {
+        let metrics = Self {
+            fees_earned_total: CounterVec::new(
+                Opts::new("fees_earned_total", "The amount of fees earned in <token>")
+                    .namespace(NAMESPACE)
+                    .const_labels(avs_name_label(avs_name)),
+                &["token"],
+            )?,
+            performance_score: Gauge::with_opts(
+                Opts::new(
+                    "performance_score",
+                    "The performance metric is a score between 0 and 100 and each developer \
+                     can define their own way of calculating the score",
+                )
+                .namespace(NAMESPACE)
+                .const_labels(avs_name_label(avs_name)),
+            )?,
+        };
+        metrics.performance_score.set(100.0);
+
+        registry.register(Box::new(metrics.fees_earned_total.clone()))?;
+        registry.register(Box::new(metrics.performance_score.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub fn add_fees_earned_total(&self, amount: f64, token: &str) {
+        self.fees_earned_total
+            .with_label_values(&[token])
+            .inc_by(amount);
+    }
+
+    /// Sets the performance score, clamped to between 0 and 100.
+    pub fn set_performance_score(&self, score: f64) {
+        self.performance_score.set(score.clamp(0.0, 100.0));
+    }
+}
+
+/// Durations, counts and failures of the RPC requests an AVS node makes, by method and
+/// client version.
+#[derive(Debug, Clone)]
+pub struct RpcCallsCollector {
+    rpc_request_duration_seconds: HistogramVec,
+    rpc_request_total: IntCounterVec,
+    rpc_request_errors_total: IntCounterVec,
+}
+
+impl RpcCallsCollector {
+    /// Creates the metrics and registers them against `registry`.
+    pub fn new(avs_name: &str, registry: &Registry) -> Result<Self, prometheus::Error> {
+        let collector = Self {
+            rpc_request_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "rpc_request_duration_seconds",
+                    "Duration of json-rpc <method> in seconds",
+                )
+                .namespace(NAMESPACE)
+                .const_labels(avs_name_label(avs_name)),
+                &["method", "client_version"],
+            )?,
+            rpc_request_total: IntCounterVec::new(
+                Opts::new("rpc_request_total", "Total of json-rpc <method> requests")
+                    .namespace(NAMESPACE)
+                    .const_labels(avs_name_label(avs_name)),
+                &["method", "client_version"],
+            )?,
+            rpc_request_errors_total: IntCounterVec::new(
+                Opts::new(
+                    "rpc_request_errors_total",
+                    "Total of failed json-rpc <method> requests, by error kind",
+                )
+                .namespace(NAMESPACE)
+                .const_labels(avs_name_label(avs_name)),
+                &["method", "client_version", "kind"],
+            )?,
+        };
+
+        registry.register(Box::new(collector.rpc_request_duration_seconds.clone()))?;
+        registry.register(Box::new(collector.rpc_request_total.clone()))?;
+        registry.register(Box::new(collector.rpc_request_errors_total.clone()))?;
+
+        Ok(collector)
+    }
+
+    /// Records a request to `method` that took `duration`.
+    pub fn observe_rpc_request(&self, method: &str, client_version: &str, duration: Duration) {
+        self.rpc_request_duration_seconds
+            .with_label_values(&[method, client_version])
+            .observe(duration.as_secs_f64());
+        self.rpc_request_total
+            .with_label_values(&[method, client_version])
+            .inc();
+    }
+
+    /// Records a failed request to `method`, with `kind` naming the cause.
+    pub fn add_rpc_request_error(&self, method: &str, client_version: &str, kind: &str) {
+        self.rpc_request_errors_total
+            .with_label_values(&[method, client_version, kind])
+            .inc();
+    }
+
+    /// Labels the requests recorded through the returned handle with `client_version`.
+    pub fn for_client(&self, client_version: &str) -> ClientRpcMetrics {
+        ClientRpcMetrics {
+            collector: self.clone(),
+            client_version: client_version.to_string(),
+        }
+    }
+}
+
+/// [`RpcCallsCollector`] for the requests made to one RPC client.
+#[derive(Debug, Clone)]
+pub struct ClientRpcMetrics {
+    collector: RpcCallsCollector,
+    client_version: String,
+}
+
+impl ClientRpcMetrics {
+    pub fn observe_rpc_request(&self, method: &str, duration: Duration) {
+        self.collector
+            .observe_rpc_request(method, &self.client_version, duration);
+    }
+
+    pub fn add_rpc_request_error(&self, method: &str, kind: &str) {
+        self.collector
+            .add_rpc_request_error(method, &self.client_version, kind);
+    }
+}
+
+/// Durations and counts of the calls made through the SDK, such as the AVS registry reads
+/// and writes, by call.
+///
+/// Calls are kept apart from the `eigen_rpc_request_*` metrics, whose `method` label is the
+/// JSON-RPC method sent by
+/// [`InstrumentedProvider`](crate::providers::instrumented::InstrumentedProvider), since a
+/// call may send several requests.
+#[derive(Debug, Clone)]
+pub struct SdkCallsCollector {
+    sdk_call_duration_seconds: HistogramVec,
+    sdk_call_total: IntCounterVec,
+}
+
+impl SdkCallsCollector {
+    /// Creates the metrics and registers them against `registry`.
+    pub fn new(avs_name: &str, registry: &Registry) -> Result<Self, prometheus::Error> {
+        let collector = Self {
+            sdk_call_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "sdk_call_duration_seconds",
+                    "Duration of SDK <call> in seconds",
+                )
+                .namespace(NAMESPACE)
+                .const_labels(avs_name_label(avs_name)),
+                &["call"],
+            )?,
+            sdk_call_total: IntCounterVec::new(
+                Opts::new("sdk_call_total", "Total of SDK <call> calls")
+                    .namespace(NAMESPACE)
+                    .const_labels(avs_name_label(avs_name)),
+                &["call"],
+            )?,
+        };
+
+        registry.register(Box::new(collector.sdk_call_duration_seconds.clone()))?;
+        registry.register(Box::new(collector.sdk_call_total.clone()))?;
+
+        Ok(collector)
+    }
+
+    /// Records a call to `call` that took `duration`.
+    pub fn observe_call(&self, call: &str, duration: Duration) {
+        self.sdk_call_duration_seconds
+            .with_label_values(&[call])
+            .observe(duration.as_secs_f64());
+        self.sdk_call_total.with_label_values(&[call]).inc();
+    }
+
+    /// Starts timing a call to `call`, which is recorded when the timer is dropped.
+    pub fn start_timer(&self, call: &'static str) -> SdkCallTimer {
+        SdkCallTimer {
+            collector: self.clone(),
+            call,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+/// Records an SDK call when dropped.
+#[derive(Debug)]
+pub struct SdkCallTimer {
+    collector: SdkCallsCollector,
+    call: &'static str,
+    started_at: Instant,
+}
+
+impl Drop for SdkCallTimer {
+    fn drop(&mut self) {
+        self.collector
+            .observe_call(self.call, self.started_at.elapsed());
+    }
+}
+
+/// Serves the metrics in a registry to Prometheus at `/metrics`.
+#[derive(Clone)]
+pub struct MetricsServer {
+    ip_port_address: String,
+    registry: Registry,
+    tls_config: Option<TlsConfig>,
+}
+
+impl MetricsServer {
+    pub fn new(ip_port_address: &str, registry: Registry) -> Self {
+        Self {
+            ip_port_address: ip_port_address.to_string(),
+            registry,
+            tls_config: None,
+        }
+    }
+
+    /// Serves the metrics over TLS instead of plaintext.
+    pub fn with_tls(mut self, tls_config: TlsConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Serves the metrics until `shutdown` is cancelled, then stops accepting connections
+    /// and returns once open connections have finished their in-flight requests.
+    pub async fn start(
+        &self,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let listener = TcpListener::bind(&self.ip_port_address).await?;
+        self.serve(listener, shutdown).await
+    }
+
+    /// Like [`Self::start`], accepting connections on an already bound `listener`.
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let tls_acceptor = match &self.tls_config {
+            Some(tls_config) => Some(tls_config.build_acceptor()?),
+            None => None,
+        };
+        let connections = TaskTracker::new();
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => accepted?.0,
+                _ = shutdown.cancelled() => break,
+            };
+            let registry = self.registry.clone();
+            connections.spawn(serve_connection(
+                stream,
+                tls_acceptor.clone(),
+                service_fn(move |req| {
+                    let registry = registry.clone();
+                    async move { metrics_handler(req, &registry) }
+                }),
+                shutdown.clone(),
+            ));
+        }
+        connections.close();
+        connections.wait().await;
+        Ok(())
+    }
+}
+
+fn metrics_handler(
+    req: Request<Incoming>,
+    registry: &Registry,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    if req.uri().path() != METRICS_PATH {
+        return Ok(text_response(StatusCode::NOT_FOUND, "Not Found".into()));
+    }
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return Ok(text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            "Method Not Allowed".into(),
+        ));
+    }
+
+    let mut buffer = Vec::new();
+    match TextEncoder::new().encode(&registry.gather(), &mut buffer) {
+        Ok(()) => Ok(Response::builder()
+            .header(CONTENT_TYPE, TEXT_FORMAT)
+            .body(Full::new(Bytes::from(buffer)))
+            .unwrap()),
+        Err(e) => Ok(text_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            e.to_string(),
+        )),
+    }
+}
+
+fn text_response(status: StatusCode, body: String) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::from(body)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_server() {
+        let registry = Registry::new();
+        let eigen_metrics = EigenMetrics::new("test-avs", &registry).unwrap();
+        let rpc_metrics = RpcCallsCollector::new("test-avs", &registry)
+            .unwrap()
+            .for_client("geth/v1.14.0");
+        eigen_metrics.add_fees_earned_total(1.5, "ETH");
+        eigen_metrics.set_performance_score(150.0);
+        rpc_metrics.observe_rpc_request("eth_blockNumber", Duration::from_millis(5));
+        let sdk_calls = SdkCallsCollector::new("test-avs", &registry).unwrap();
+        drop(sdk_calls.start_timer("get_quorum_count"));
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let shutdown = CancellationToken::new();
+        let server = tokio::spawn({
+            let server = MetricsServer::new(&address.to_string(), registry);
+            let shutdown = shutdown.clone();
+            async move { server.serve(listener, shutdown).await.unwrap() }
+        });
+
+        let response = reqwest::get(format!("http://{}{}", address, METRICS_PATH))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+        let body = response.text().await.unwrap();
+        for line in [
+            r#"eigen_fees_earned_total{avs_name="test-avs",token="ETH"} 1.5"#,
+            r#"eigen_performance_score{avs_name="test-avs"} 100"#,
+            r#"eigen_rpc_request_total{avs_name="test-avs",client_version="geth/v1.14.0",method="eth_blockNumber"} 1"#,
+            r#"eigen_rpc_request_duration_seconds_count{avs_name="test-avs",client_version="geth/v1.14.0",method="eth_blockNumber"} 1"#,
+            r#"eigen_sdk_call_total{avs_name="test-avs",call="get_quorum_count"} 1"#,
+            r#"eigen_sdk_call_duration_seconds_count{avs_name="test-avs",call="get_quorum_count"} 1"#,
+        ] {
+            assert!(body.lines().any(|l| l == line), "missing {}", line);
+        }
+
+        let response = reqwest::get(format!("http://{}/other", address))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 404);
+
+        shutdown.cancel();
+        server.await.unwrap();
+    }
+}
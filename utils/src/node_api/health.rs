@@ -0,0 +1,162 @@
+//! Health checks from which [`NodeApi`](super::NodeApi) derives service and node health.
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_transport::Transport;
+use ark_ff::Zero;
+use async_trait::async_trait;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpStream;
+
+use super::{NodeHealth, NodeService, ServiceStatus};
+use crate::crypto::bls::KeyPair;
+
+/// Checks the health of a service registered with the node API.
+#[async_trait]
+pub trait HealthCheck: Send + Sync + 'static {
+    async fn check(&self) -> ServiceStatus;
+}
+
+#[async_trait]
+impl<F, Fut> HealthCheck for F
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = ServiceStatus> + Send,
+{
+    async fn check(&self) -> ServiceStatus {
+        self().await
+    }
+}
+
+/// Up while the provider answers `eth_blockNumber`.
+pub struct ProviderHealthCheck<P, T> {
+    provider: P,
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<P, T> ProviderHealthCheck<P, T> {
+    pub fn new(provider: P) -> Self {
+        Self {
+            provider,
+            _transport: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<P, T> HealthCheck for ProviderHealthCheck<P, T>
+where
+    P: Provider<T, Ethereum> + Send + Sync + 'static,
+    T: Transport + Clone,
+{
+    async fn check(&self) -> ServiceStatus {
+        match self.provider.get_block_number().await {
+            Ok(_) => ServiceStatus::Up,
+            Err(e) => {
+                log::debug!("Provider health check failed: {}", e);
+                ServiceStatus::Down
+            }
+        }
+    }
+}
+
+/// Up while a TCP connection can be opened to `address`.
+pub struct TcpHealthCheck {
+    address: String,
+}
+
+impl TcpHealthCheck {
+    pub fn new(address: &str) -> Self {
+        Self {
+            address: address.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for TcpHealthCheck {
+    async fn check(&self) -> ServiceStatus {
+        match TcpStream::connect(&self.address).await {
+            Ok(_) => ServiceStatus::Up,
+            Err(e) => {
+                log::debug!("Failed to connect to {}: {}", self.address, e);
+                ServiceStatus::Down
+            }
+        }
+    }
+}
+
+/// Up while the BLS key pair is loaded: its private key is set and its public key derives
+/// from it, so that the signatures it makes verify.
+pub struct BlsKeyHealthCheck {
+    key_pair: KeyPair,
+}
+
+impl BlsKeyHealthCheck {
+    pub fn new(key_pair: KeyPair) -> Self {
+        Self { key_pair }
+    }
+}
+
+#[async_trait]
+impl HealthCheck for BlsKeyHealthCheck {
+    async fn check(&self) -> ServiceStatus {
+        if self.key_pair.priv_key.is_zero() {
+            log::debug!("BLS private key is not set");
+            ServiceStatus::Down
+        } else if KeyPair::new(self.key_pair.priv_key).pub_key != self.key_pair.pub_key {
+            log::debug!("BLS public key does not match the private key");
+            ServiceStatus::Down
+        } else {
+            ServiceStatus::Up
+        }
+    }
+}
+
+/// Liveness flag set by a long-running task, such as a subscription, and reported as Up
+/// while it is alive.
+#[derive(Debug, Clone)]
+pub struct Liveness(Arc<AtomicBool>);
+
+impl Liveness {
+    pub fn new(alive: bool) -> Self {
+        Self(Arc::new(AtomicBool::new(alive)))
+    }
+
+    pub fn set_alive(&self, alive: bool) {
+        self.0.store(alive, Ordering::Relaxed);
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl HealthCheck for Liveness {
+    async fn check(&self) -> ServiceStatus {
+        if self.is_alive() {
+            ServiceStatus::Up
+        } else {
+            ServiceStatus::Down
+        }
+    }
+}
+
+/// The node is healthy if every service is up, unhealthy if none is, and partially healthy
+/// otherwise.
+pub(crate) fn derive_node_health(services: &[NodeService]) -> NodeHealth {
+    let up = services
+        .iter()
+        .filter(|service| service.status == ServiceStatus::Up)
+        .count();
+    if up == services.len() {
+        NodeHealth::Healthy
+    } else if up == 0 {
+        NodeHealth::Unhealthy
+    } else {
+        NodeHealth::PartiallyHealthy
+    }
+}
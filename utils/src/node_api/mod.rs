@@ -1,32 +1,48 @@
 use http_body_util::Full;
 use hyper::{
-    body::{self, Bytes, Incoming},
-    server::conn::http1,
+    body::{Body, Bytes, Incoming},
+    header::{HeaderValue, ALLOW, CONTENT_LENGTH},
     service::service_fn,
     Method, Request, Response, StatusCode,
 };
 
 use serde::Serialize;
 use serde_json::json;
-use std::{convert::Infallible, sync::Arc};
-use tokio::net::TcpListener;
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::{net::TcpListener, task::JoinSet, time::interval};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+
+use crate::tls::{serve_connection, TlsConfig};
 
-use self::tokiort::TokioIo;
+use self::health::{derive_node_health, HealthCheck};
 
+pub mod health;
 pub mod tokiort;
 
 const BASE_URL: &str = "/eigen";
 const SPEC_SEM_VER: &str = "v0.0.1";
+/// Header naming the Node API spec version. Every response names the version served, and
+/// requests naming a version incompatible with it are rejected with 406 Not Acceptable.
+pub const SPEC_VERSION_HEADER: &str = "x-eigen-spec-version";
+/// Methods accepted by every endpoint.
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+const DEFAULT_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
 
-#[derive(Debug, Clone, Copy)]
-enum NodeHealth {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeHealth {
     Healthy,
     PartiallyHealthy,
     Unhealthy,
 }
 
-#[derive(Debug, Serialize, Clone, Copy)]
-enum ServiceStatus {
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceStatus {
     Up,
     Down,
     Initializing,
@@ -40,13 +56,35 @@ pub struct NodeService {
     status: ServiceStatus,
 }
 
+impl NodeService {
+    pub fn new(id: &str, name: &str, description: &str, status: ServiceStatus) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: description.to_string(),
+            status,
+        }
+    }
+}
+
+/// Node state reported by the API, shared by every clone of a [`NodeApi`].
+struct NodeState {
+    health: NodeHealth,
+    node_services: Vec<NodeService>,
+    health_checks: HashMap<String, Arc<dyn HealthCheck>>,
+}
+
+/// Handle to the node API. Clones share the node's health and services, so updates made
+/// through any clone are served by a running [`NodeApi::start`].
 #[derive(Clone)]
 pub struct NodeApi {
     avs_node_name: String,
     avs_node_sem_ver: String,
-    health: NodeHealth,
-    node_services: Vec<NodeService>,
+    state: Arc<RwLock<NodeState>>,
     ip_port_address: String,
+    tls_config: Option<TlsConfig>,
+    health_check_interval: Duration,
+    health_check_timeout: Duration,
 }
 
 impl NodeApi {
@@ -54,26 +92,69 @@ impl NodeApi {
         Self {
             avs_node_name: avs_node_name.to_string(),
             avs_node_sem_ver: avs_node_sem_ver.to_string(),
-            health: NodeHealth::Healthy,
-            node_services: Vec::new(),
+            state: Arc::new(RwLock::new(NodeState {
+                health: NodeHealth::Healthy,
+                node_services: Vec::new(),
+                health_checks: HashMap::new(),
+            })),
             ip_port_address: ip_port_addr.to_string(),
+            tls_config: None,
+            health_check_interval: DEFAULT_HEALTH_CHECK_INTERVAL,
+            health_check_timeout: DEFAULT_HEALTH_CHECK_TIMEOUT,
         }
     }
 
-    fn update_health(&mut self, health: NodeHealth) {
-        self.health = health;
+    /// Serves the API over TLS instead of plaintext.
+    pub fn with_tls(mut self, tls_config: TlsConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Sets how often registered health checks run while the API is served. A zero interval
+    /// disables the periodic evaluation, leaving it to [`Self::evaluate_health_checks`].
+    pub fn with_health_check_interval(mut self, health_check_interval: Duration) -> Self {
+        self.health_check_interval = health_check_interval;
+        self
     }
 
-    fn register_new_service(&mut self, service: NodeService) {
-        self.node_services.push(service);
+    /// Sets how long a health check may take before its service is reported down.
+    pub fn with_health_check_timeout(mut self, health_check_timeout: Duration) -> Self {
+        self.health_check_timeout = health_check_timeout;
+        self
     }
 
-    fn update_service_status(
-        &mut self,
+    pub fn health(&self) -> NodeHealth {
+        self.state.read().unwrap().health
+    }
+
+    /// Sets the node health. Once health checks are registered, the node health is derived
+    /// from the service statuses instead and is overwritten at every evaluation.
+    pub fn update_health(&self, health: NodeHealth) {
+        self.state.write().unwrap().health = health;
+    }
+
+    pub fn register_new_service(&self, service: NodeService) {
+        self.state.write().unwrap().node_services.push(service);
+    }
+
+    /// Registers `service` and derives its status from `health_check`, which runs
+    /// periodically while the API is served. Replaces any service with the same id.
+    pub fn register_health_check(&self, service: NodeService, health_check: impl HealthCheck) {
+        let mut state = self.state.write().unwrap();
+        state.node_services.retain(|s| s.id != service.id);
+        state
+            .health_checks
+            .insert(service.id.clone(), Arc::new(health_check));
+        state.node_services.push(service);
+    }
+
+    pub fn update_service_status(
+        &self,
         service_id: &str,
         service_status: ServiceStatus,
     ) -> Result<(), String> {
-        for service in self.node_services.iter_mut() {
+        let mut state = self.state.write().unwrap();
+        for service in state.node_services.iter_mut() {
             if service.id == service_id {
                 service.status = service_status;
                 return Ok(());
@@ -82,62 +163,187 @@ impl NodeApi {
         Err(format!("Service with serviceId {} not found", service_id))
     }
 
-    fn deregister_service(&mut self, service_id: &str) -> Result<(), String> {
-        if let Some(index) = self
+    pub fn deregister_service(&self, service_id: &str) -> Result<(), String> {
+        let mut state = self.state.write().unwrap();
+        if let Some(index) = state
             .node_services
             .iter()
             .position(|service| service.id == service_id)
         {
-            self.node_services.remove(index);
+            state.node_services.remove(index);
+            state.health_checks.remove(service_id);
             return Ok(());
         }
         Err(format!("Service with serviceId {} not found", service_id))
     }
 
-    pub async fn start(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-        let listener = TcpListener::bind(&self.ip_port_address).await?;
-        loop {
-            let (stream, _) = listener.accept().await.unwrap();
-            let io = TokioIo::new(stream);
-            let this = Arc::new(self.clone());
-            tokio::task::spawn(async move {
-                if let Err(err) = http1::Builder::new()
-                    .serve_connection(
-                        io,
-                        service_fn(move |req| {
-                            let this = Arc::clone(&this);
-                            async move { this.router(req).await }
-                        }),
-                    )
+    /// Runs every registered health check concurrently, then updates the status of each
+    /// checked service and derives the node health from all services. Checks that time out
+    /// or panic report their service down.
+    pub async fn evaluate_health_checks(&self) {
+        let health_checks: Vec<_> = self
+            .state
+            .read()
+            .unwrap()
+            .health_checks
+            .iter()
+            .map(|(service_id, health_check)| (service_id.clone(), Arc::clone(health_check)))
+            .collect();
+        if health_checks.is_empty() {
+            return;
+        }
+
+        // Services whose check panics are reported down
+        let mut statuses: HashMap<_, _> = health_checks
+            .iter()
+            .map(|(service_id, _)| (service_id.clone(), ServiceStatus::Down))
+            .collect();
+        let mut checks = JoinSet::new();
+        for (service_id, health_check) in health_checks {
+            let timeout = self.health_check_timeout;
+            checks.spawn(async move {
+                let status = tokio::time::timeout(timeout, health_check.check())
                     .await
-                {
-                    println!("Error serving connection: {:?}", err);
-                }
+                    .unwrap_or(ServiceStatus::Down);
+                (service_id, status)
             });
         }
+        while let Some(result) = checks.join_next().await {
+            match result {
+                Ok((service_id, status)) => {
+                    statuses.insert(service_id, status);
+                }
+                Err(e) => log::error!("Health check failed: {}", e),
+            }
+        }
+
+        let mut state = self.state.write().unwrap();
+        for service in state.node_services.iter_mut() {
+            if let Some(status) = statuses.get(&service.id) {
+                service.status = *status;
+            }
+        }
+        state.health = derive_node_health(&state.node_services);
     }
 
-    async fn router(
+    /// Serves the API until `shutdown` is cancelled, then stops accepting connections and
+    /// returns once open connections have finished their in-flight requests. Registered
+    /// health checks are evaluated meanwhile.
+    pub async fn start(
         &self,
-        req: Request<body::Incoming>,
-    ) -> Result<Response<Full<Bytes>>, Infallible> {
-        match (req.method(), req.uri().path()) {
-            (&Method::GET, path) if path == format!("{}/node", BASE_URL) => {
-                self.node_handler().await
-            }
-            (&Method::GET, path) if path == format!("{}/node/health", BASE_URL) => {
-                self.health_handler().await
-            }
-            (&Method::GET, path) if path == format!("{}/node/services", BASE_URL) => {
-                self.services_handler().await
-            }
-            (&Method::GET, path) if path.starts_with(&format!("{}/node/services/", BASE_URL)) => {
-                self.service_health_handler(req).await
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let listener = TcpListener::bind(&self.ip_port_address).await?;
+        self.serve(listener, shutdown).await
+    }
+
+    /// Like [`Self::start`], accepting connections on an already bound `listener`.
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        shutdown: CancellationToken,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let tls_acceptor = match &self.tls_config {
+            Some(tls_config) => Some(tls_config.build_acceptor()?),
+            None => None,
+        };
+        let connections = TaskTracker::new();
+        if !self.health_check_interval.is_zero() {
+            connections.spawn({
+                let this = self.clone();
+                let shutdown = shutdown.clone();
+                async move {
+                    let mut ticker = interval(this.health_check_interval);
+                    loop {
+                        tokio::select! {
+                            _ = ticker.tick() => this.evaluate_health_checks().await,
+                            _ = shutdown.cancelled() => break,
+                        }
+                    }
+                }
+            });
+        }
+        let this = Arc::new(self.clone());
+        loop {
+            let stream = tokio::select! {
+                accepted = listener.accept() => accepted?.0,
+                _ = shutdown.cancelled() => break,
+            };
+            let this = Arc::clone(&this);
+            connections.spawn(serve_connection(
+                stream,
+                tls_acceptor.clone(),
+                service_fn(move |req| {
+                    let this = Arc::clone(&this);
+                    async move { this.router(req).await }
+                }),
+                shutdown.clone(),
+            ));
+        }
+        connections.close();
+        connections.wait().await;
+        Ok(())
+    }
+
+    async fn router(&self, req: Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+        let mut response = self.route(&req).await?;
+        response
+            .headers_mut()
+            .insert(SPEC_VERSION_HEADER, HeaderValue::from_static(SPEC_SEM_VER));
+        Ok(response)
+    }
+
+    async fn route(&self, req: &Request<Incoming>) -> Result<Response<Full<Bytes>>, Infallible> {
+        let Some(route) = Route::parse(req.uri().path()) else {
+            return Ok(error_response(StatusCode::NOT_FOUND, "Not Found"));
+        };
+
+        if let Some(requested) = req.headers().get(SPEC_VERSION_HEADER) {
+            match requested.to_str().ok().and_then(parse_sem_ver) {
+                None => {
+                    return Ok(error_response(
+                        StatusCode::BAD_REQUEST,
+                        format!("Invalid {} header", SPEC_VERSION_HEADER),
+                    ))
+                }
+                Some(requested) if !is_compatible_spec_version(requested) => {
+                    return Ok(error_response(
+                        StatusCode::NOT_ACCEPTABLE,
+                        format!(
+                            "Unsupported spec version, this node implements {}",
+                            SPEC_SEM_VER
+                        ),
+                    ))
+                }
+                Some(_) => {}
             }
-            _ => Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Full::new(Bytes::from("Not Found")))
+        }
+
+        match *req.method() {
+            Method::GET => self.handle(route).await,
+            Method::HEAD => Ok(without_body(self.handle(route).await?)),
+            Method::OPTIONS => Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .header(ALLOW, ALLOWED_METHODS)
+                .body(Full::new(Bytes::new()))
                 .unwrap()),
+            _ => {
+                let mut response =
+                    error_response(StatusCode::METHOD_NOT_ALLOWED, "Method Not Allowed");
+                response
+                    .headers_mut()
+                    .insert(ALLOW, HeaderValue::from_static(ALLOWED_METHODS));
+                Ok(response)
+            }
+        }
+    }
+
+    async fn handle(&self, route: Route) -> Result<Response<Full<Bytes>>, Infallible> {
+        match route {
+            Route::Node => self.node_handler().await,
+            Route::Health => self.health_handler().await,
+            Route::Services => self.services_handler().await,
+            Route::ServiceHealth(service_id) => self.service_health_handler(&service_id).await,
         }
     }
 
@@ -152,22 +358,17 @@ impl NodeApi {
     }
 
     async fn health_handler(&self) -> Result<Response<Full<Bytes>>, Infallible> {
-        match self.health {
-            NodeHealth::Healthy => Ok(Response::new(Full::new(Bytes::from("")))),
-            NodeHealth::PartiallyHealthy => Ok(Response::builder()
-                .status(StatusCode::PARTIAL_CONTENT)
-                .body(Full::new(Bytes::from("")))
-                .unwrap()),
-            NodeHealth::Unhealthy => Ok(Response::builder()
-                .status(StatusCode::SERVICE_UNAVAILABLE)
-                .body(Full::new(Bytes::from("")))
-                .unwrap()),
-        }
+        let status = match self.health() {
+            NodeHealth::Healthy => StatusCode::OK,
+            NodeHealth::PartiallyHealthy => StatusCode::PARTIAL_CONTENT,
+            NodeHealth::Unhealthy => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        Ok(empty_response(status))
     }
 
     async fn services_handler(&self) -> Result<Response<Full<Bytes>>, Infallible> {
         let response = json!({
-            "services": self.node_services,
+            "services": self.state.read().unwrap().node_services,
         });
 
         Ok(json_response(response))
@@ -175,36 +376,100 @@ impl NodeApi {
 
     async fn service_health_handler(
         &self,
-        req: Request<Incoming>,
+        service_id: &str,
     ) -> Result<Response<Full<Bytes>>, Infallible> {
-        let path = req.uri().path();
-        let service_id = path
-            .trim_start_matches(&format!("{}/node/services/", BASE_URL))
-            .trim_end_matches("/health");
+        let status = self
+            .state
+            .read()
+            .unwrap()
+            .node_services
+            .iter()
+            .find(|service| service.id == service_id)
+            .map(|service| service.status);
 
-        for service in self.node_services.iter() {
-            if service.id == service_id {
-                return match service.status {
-                    ServiceStatus::Up => Ok(Response::new(Full::new(Bytes::from("")))),
-                    ServiceStatus::Down => Ok(Response::builder()
-                        .status(StatusCode::SERVICE_UNAVAILABLE)
-                        .body(Full::new(Bytes::from("")))
-                        .unwrap()),
-                    ServiceStatus::Initializing => Ok(Response::builder()
-                        .status(StatusCode::PARTIAL_CONTENT)
-                        .body(Full::new(Bytes::from("")))
-                        .unwrap()),
-                };
+        Ok(match status {
+            Some(ServiceStatus::Up) => empty_response(StatusCode::OK),
+            Some(ServiceStatus::Initializing) => empty_response(StatusCode::PARTIAL_CONTENT),
+            Some(ServiceStatus::Down) => empty_response(StatusCode::SERVICE_UNAVAILABLE),
+            None => error_response(
+                StatusCode::NOT_FOUND,
+                format!("Service with serviceId {} not found", service_id),
+            ),
+        })
+    }
+}
+
+/// Endpoints of the Node API spec.
+enum Route {
+    /// `/eigen/node`
+    Node,
+    /// `/eigen/node/health`
+    Health,
+    /// `/eigen/node/services`
+    Services,
+    /// `/eigen/node/services/{service_id}/health`
+    ServiceHealth(String),
+}
+
+impl Route {
+    fn parse(path: &str) -> Option<Self> {
+        let path = path.strip_prefix(BASE_URL)?.strip_prefix("/node")?;
+        match path {
+            "" => Some(Route::Node),
+            "/health" => Some(Route::Health),
+            "/services" => Some(Route::Services),
+            _ => {
+                let service_id = path.strip_prefix("/services/")?.strip_suffix("/health")?;
+                if service_id.is_empty() || service_id.contains('/') {
+                    return None;
+                }
+                Some(Route::ServiceHealth(service_id.to_string()))
             }
         }
+    }
+}
 
-        Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .body(Full::new(Bytes::from("")))
-            .unwrap())
+/// Parses a `vMAJOR.MINOR.PATCH` version, the `v` being optional.
+fn parse_sem_ver(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let sem_ver = (parts.next()??, parts.next()??, parts.next()??);
+    match parts.next() {
+        Some(_) => None,
+        None => Some(sem_ver),
     }
 }
 
+/// A client's spec version is compatible if it has the same major version as
+/// [`SPEC_SEM_VER`] or, while the major version is 0, the same minor version.
+fn is_compatible_spec_version((major, minor, _): (u64, u64, u64)) -> bool {
+    let (spec_major, spec_minor, _) = parse_sem_ver(SPEC_SEM_VER).unwrap();
+    major == spec_major && (spec_major != 0 || minor == spec_minor)
+}
+
+fn empty_response(status: StatusCode) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .body(Full::new(Bytes::new()))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: impl Into<String>) -> Response<Full<Bytes>> {
+    let mut response = json_response(json!({ "error": message.into() }));
+    *response.status_mut() = status;
+    response
+}
+
+/// Answers a HEAD request with the headers, including the length, of the GET response.
+fn without_body(response: Response<Full<Bytes>>) -> Response<Full<Bytes>> {
+    let (mut parts, body) = response.into_parts();
+    let content_length = body.size_hint().exact().unwrap_or_default();
+    parts
+        .headers
+        .insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+    Response::from_parts(parts, Full::new(Bytes::new()))
+}
+
 fn json_response<T: Serialize>(data: T) -> Response<Full<Bytes>> {
     let body = serde_json::to_string(&data).unwrap();
     Response::builder()
@@ -212,3 +477,365 @@ fn json_response<T: Serialize>(data: T) -> Response<Full<Bytes>> {
         .body(Full::new(Bytes::from(body.to_string())))
         .unwrap()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::bls::KeyPair;
+    use reqwest::{Client, Method};
+    use std::time::Duration;
+
+    /// Serves `node_api` on a free loopback port, returning the base URL of its endpoints.
+    async fn serve_node_api(node_api: &NodeApi, shutdown: &CancellationToken) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn({
+            let node_api = node_api.clone();
+            let shutdown = shutdown.clone();
+            async move { node_api.serve(listener, shutdown).await.unwrap() }
+        });
+        format!("http://{}{}/node", address, BASE_URL)
+    }
+
+    async fn start_node_api() -> (NodeApi, CancellationToken, String) {
+        let node_api = NodeApi::new("test-node", "v1.2.3", "127.0.0.1:0");
+        let shutdown = CancellationToken::new();
+        let url = serve_node_api(&node_api, &shutdown).await;
+        (node_api, shutdown, url)
+    }
+
+    async fn request(method: Method, url: String) -> reqwest::Response {
+        let response = Client::new().request(method, url).send().await.unwrap();
+        assert_eq!(response.headers()[SPEC_VERSION_HEADER], SPEC_SEM_VER);
+        response
+    }
+
+    async fn assert_json_error(response: reqwest::Response, status: u16) {
+        assert_eq!(response.status(), status);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let node_api = NodeApi::new("test-node", "v1.2.3", "127.0.0.1:0");
+        let clone = node_api.clone();
+        let services = |node_api: &NodeApi| {
+            let state = node_api.state.read().unwrap();
+            state
+                .node_services
+                .iter()
+                .map(|s| (s.id.clone(), s.status))
+                .collect::<Vec<_>>()
+        };
+
+        clone.update_health(NodeHealth::PartiallyHealthy);
+        assert_eq!(node_api.health(), NodeHealth::PartiallyHealthy);
+
+        clone.register_new_service(NodeService::new("db", "Database", "", ServiceStatus::Up));
+        node_api
+            .update_service_status("db", ServiceStatus::Down)
+            .unwrap();
+        assert_eq!(services(&clone), [("db".to_string(), ServiceStatus::Down)]);
+        assert_eq!(services(&node_api), services(&clone));
+
+        clone.register_health_check(
+            NodeService::new("provider", "Provider", "", ServiceStatus::Initializing),
+            || async { ServiceStatus::Up },
+        );
+        assert_eq!(node_api.state.read().unwrap().health_checks.len(), 1);
+
+        node_api.deregister_service("db").unwrap();
+        node_api.deregister_service("provider").unwrap();
+        assert!(services(&clone).is_empty());
+        assert!(clone.state.read().unwrap().health_checks.is_empty());
+        assert!(clone.deregister_service("db").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_node_endpoint() {
+        let (_node_api, shutdown, url) = start_node_api().await;
+
+        let response = request(Method::GET, url).await;
+        assert_eq!(response.status(), 200);
+        assert_eq!(response.headers()["content-type"], "application/json");
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "node_name": "test-node",
+                "spec_version": SPEC_SEM_VER,
+                "node_version": "v1.2.3",
+            })
+        );
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint() {
+        let (node_api, shutdown, url) = start_node_api().await;
+
+        for (health, status) in [
+            (NodeHealth::Healthy, 200),
+            (NodeHealth::PartiallyHealthy, 206),
+            (NodeHealth::Unhealthy, 503),
+        ] {
+            node_api.update_health(health);
+            let response = request(Method::GET, format!("{}/health", url)).await;
+            assert_eq!(response.status(), status);
+            assert!(response.bytes().await.unwrap().is_empty());
+        }
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_services_endpoints() {
+        let (node_api, shutdown, url) = start_node_api().await;
+
+        let response = request(Method::GET, format!("{}/services", url)).await;
+        assert_eq!(response.status(), 200);
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(body, json!({ "services": [] }));
+
+        node_api.register_new_service(NodeService::new(
+            "db",
+            "Database",
+            "Stores tasks",
+            ServiceStatus::Up,
+        ));
+        let response = request(Method::GET, format!("{}/services", url)).await;
+        let body: serde_json::Value =
+            serde_json::from_slice(&response.bytes().await.unwrap()).unwrap();
+        assert_eq!(
+            body,
+            json!({
+                "services": [{
+                    "id": "db",
+                    "name": "Database",
+                    "description": "Stores tasks",
+                    "status": "Up",
+                }],
+            })
+        );
+
+        for (service_status, status) in [
+            (ServiceStatus::Up, 200),
+            (ServiceStatus::Initializing, 206),
+            (ServiceStatus::Down, 503),
+        ] {
+            node_api
+                .update_service_status("db", service_status)
+                .unwrap();
+            let response = request(Method::GET, format!("{}/services/db/health", url)).await;
+            assert_eq!(response.status(), status);
+            assert!(response.bytes().await.unwrap().is_empty());
+        }
+
+        let response = request(Method::GET, format!("{}/services/cache/health", url)).await;
+        assert_json_error(response, 404).await;
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_methods() {
+        let (_node_api, shutdown, url) = start_node_api().await;
+
+        for path in ["", "/health", "/services"] {
+            let get = request(Method::GET, format!("{}{}", url, path)).await;
+            let head = request(Method::HEAD, format!("{}{}", url, path)).await;
+            assert_eq!(head.status(), get.status());
+            assert_eq!(
+                head.headers()["content-length"],
+                get.headers()["content-length"]
+            );
+            assert!(head.bytes().await.unwrap().is_empty());
+
+            let options = request(Method::OPTIONS, format!("{}{}", url, path)).await;
+            assert_eq!(options.status(), 204);
+            assert_eq!(options.headers()["allow"], ALLOWED_METHODS);
+
+            let post = request(Method::POST, format!("{}{}", url, path)).await;
+            assert_eq!(post.headers()["allow"], ALLOWED_METHODS);
+            assert_json_error(post, 405).await;
+        }
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_unknown_paths() {
+        let (_node_api, shutdown, url) = start_node_api().await;
+
+        for path in [
+            "/",
+            "/unknown",
+            "/services/",
+            "/services//health",
+            "/services/a/b/health",
+        ] {
+            let response = request(Method::GET, format!("{}{}", url, path)).await;
+            assert_json_error(response, 404).await;
+        }
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_spec_version_negotiation() {
+        let (_node_api, shutdown, url) = start_node_api().await;
+        let request_with_version = |version: &'static str| {
+            Client::new()
+                .get(url.clone())
+                .header(SPEC_VERSION_HEADER, version)
+                .send()
+        };
+
+        for version in ["v0.0.1", "0.0.1", "v0.0.9"] {
+            assert_eq!(request_with_version(version).await.unwrap().status(), 200);
+        }
+        for version in ["v0.1.0", "v1.0.1"] {
+            assert_json_error(request_with_version(version).await.unwrap(), 406).await;
+        }
+        for version in ["latest", "v0.0", "v0.0.1.2"] {
+            assert_json_error(request_with_version(version).await.unwrap(), 400).await;
+        }
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_health_checks() {
+        let node_api = NodeApi::new("test-node", "v1.2.3", "127.0.0.1:0")
+            .with_health_check_timeout(Duration::from_millis(100));
+        let service = |id: &str| NodeService::new(id, id, id, ServiceStatus::Initializing);
+        let status = |node_api: &NodeApi, id: &str| {
+            let state = node_api.state.read().unwrap();
+            state
+                .node_services
+                .iter()
+                .find(|s| s.id == id)
+                .unwrap()
+                .status
+        };
+
+        let liveness = health::Liveness::new(true);
+        node_api.register_health_check(service("subscription"), liveness.clone());
+        node_api.register_health_check(service("provider"), || async { ServiceStatus::Up });
+        node_api.evaluate_health_checks().await;
+        assert_eq!(status(&node_api, "subscription"), ServiceStatus::Up);
+        assert_eq!(node_api.health(), NodeHealth::Healthy);
+
+        liveness.set_alive(false);
+        node_api.evaluate_health_checks().await;
+        assert_eq!(status(&node_api, "subscription"), ServiceStatus::Down);
+        assert_eq!(node_api.health(), NodeHealth::PartiallyHealthy);
+
+        node_api.register_health_check(service("slow"), || async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            ServiceStatus::Up
+        });
+        node_api.register_health_check(service("provider"), || async {
+            panic!("provider check panicked")
+        });
+        node_api.evaluate_health_checks().await;
+        assert_eq!(status(&node_api, "slow"), ServiceStatus::Down);
+        assert_eq!(status(&node_api, "provider"), ServiceStatus::Down);
+        assert_eq!(node_api.health(), NodeHealth::Unhealthy);
+
+        node_api.deregister_service("slow").unwrap();
+        node_api.deregister_service("provider").unwrap();
+        liveness.set_alive(true);
+        node_api.evaluate_health_checks().await;
+        assert_eq!(node_api.health(), NodeHealth::Healthy);
+    }
+
+    #[tokio::test]
+    async fn test_bls_key_health_check() {
+        let key_pair = KeyPair::gen_random();
+        assert_eq!(
+            health::BlsKeyHealthCheck::new(key_pair.clone())
+                .check()
+                .await,
+            ServiceStatus::Up
+        );
+
+        let other_key_pair = KeyPair::gen_random();
+        let mismatched = KeyPair {
+            priv_key: key_pair.priv_key,
+            pub_key: other_key_pair.pub_key,
+        };
+        assert_eq!(
+            health::BlsKeyHealthCheck::new(mismatched).check().await,
+            ServiceStatus::Down
+        );
+        let unset = KeyPair::new(Default::default());
+        assert_eq!(
+            health::BlsKeyHealthCheck::new(unset).check().await,
+            ServiceStatus::Down
+        );
+    }
+
+    #[tokio::test]
+    async fn test_health_checks_run_while_serving() {
+        let node_api = NodeApi::new("test-node", "v1.2.3", "127.0.0.1:0")
+            .with_health_check_interval(Duration::from_millis(20));
+        let liveness = health::Liveness::new(false);
+        node_api.register_health_check(
+            NodeService::new(
+                "subscription",
+                "Subscription",
+                "",
+                ServiceStatus::Initializing,
+            ),
+            liveness.clone(),
+        );
+        let shutdown = CancellationToken::new();
+        let url = format!("{}/health", serve_node_api(&node_api, &shutdown).await);
+        let await_status = |status: u16| {
+            let url = url.clone();
+            async move {
+                for _ in 0..250 {
+                    if request(Method::GET, url.clone()).await.status() == status {
+                        return;
+                    }
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                panic!("node health never reported {}", status);
+            }
+        };
+
+        await_status(503).await;
+        liveness.set_alive(true);
+        await_status(200).await;
+        shutdown.cancel();
+    }
+
+    #[tokio::test]
+    async fn test_zero_health_check_interval() {
+        let node_api = NodeApi::new("test-node", "v1.2.3", "127.0.0.1:0")
+            .with_health_check_interval(Duration::ZERO);
+        node_api.register_health_check(
+            NodeService::new("provider", "Provider", "", ServiceStatus::Initializing),
+            || async { ServiceStatus::Down },
+        );
+        let shutdown = CancellationToken::new();
+        let url = serve_node_api(&node_api, &shutdown).await;
+
+        // Health is only evaluated on demand
+        assert_eq!(
+            request(Method::GET, format!("{}/health", url))
+                .await
+                .status(),
+            200
+        );
+        node_api.evaluate_health_checks().await;
+        assert_eq!(
+            request(Method::GET, format!("{}/health", url))
+                .await
+                .status(),
+            503
+        );
+        shutdown.cancel();
+    }
+}
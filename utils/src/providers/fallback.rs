@@ -0,0 +1,827 @@
+//! Provider spreading requests over several RPC endpoints, failing over between them and
+//! optionally requiring a quorum of them to agree on critical reads.
+use alloy_json_rpc::{RequestPacket, ResponsePacket, ResponsePayload, SerializedRequest};
+use alloy_network::Ethereum;
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_client::{BuiltInConnectionString, RpcClient};
+use alloy_transport::{
+    BoxTransport, TransportError, TransportErrorKind, TransportFut, TransportResult,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tower::Service;
+
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: u32 = 3;
+pub const DEFAULT_FAILURE_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Weight given to the latest request when updating an endpoint's success rate and latency.
+const SCORE_SMOOTHING: f64 = 0.2;
+/// Success rates closer than this to each other are ranked as equal, so that an endpoint
+/// recovering from a failure is not ranked behind another for long.
+const SUCCESS_RATE_TOLERANCE: f64 = 0.05;
+/// Number of installed filters and sent transactions whose endpoint is remembered.
+const PINNED_CAPACITY: usize = 1024;
+
+/// Health of an endpoint, as seen by the requests sent to it.
+#[derive(Debug, Clone)]
+pub struct EndpointHealth {
+    pub url: String,
+    /// Exponentially weighted share of requests that succeeded, between 0 and 1.
+    pub success_rate: f64,
+    /// Exponentially weighted latency of the requests that succeeded.
+    pub latency: Duration,
+    pub consecutive_failures: u32,
+    last_failure: Option<Instant>,
+}
+
+impl EndpointHealth {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            success_rate: 1.0,
+            latency: Duration::ZERO,
+            consecutive_failures: 0,
+            last_failure: None,
+        }
+    }
+
+    fn record_success(&mut self, latency: Duration) {
+        self.success_rate += SCORE_SMOOTHING * (1.0 - self.success_rate);
+        self.latency = if self.latency.is_zero() {
+            latency
+        } else {
+            self.latency.mul_f64(1.0 - SCORE_SMOOTHING) + latency.mul_f64(SCORE_SMOOTHING)
+        };
+        self.consecutive_failures = 0;
+    }
+
+    fn record_failure(&mut self) {
+        self.success_rate -= SCORE_SMOOTHING * self.success_rate;
+        self.consecutive_failures += 1;
+        self.last_failure = Some(Instant::now());
+    }
+
+    /// Whether the endpoint failed too often in a row and is still cooling down.
+    fn is_cooling_down(&self, max_consecutive_failures: u32, cooldown: Duration) -> bool {
+        match self.last_failure {
+            Some(last_failure) => {
+                self.consecutive_failures >= max_consecutive_failures
+                    && last_failure.elapsed() < cooldown
+            }
+            None => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    transport: BoxTransport,
+    health: Mutex<EndpointHealth>,
+}
+
+impl Endpoint {
+    /// Sends `request`, recording a failure for transport errors and retryable error
+    /// responses such as rate limits.
+    async fn send(&self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let started_at = Instant::now();
+        let response = self.transport.clone().call(request).await;
+        let failed = match &response {
+            Ok(ResponsePacket::Single(response)) => match &response.payload {
+                ResponsePayload::Failure(error) => error.is_retry_err(),
+                ResponsePayload::Success(_) => false,
+            },
+            Ok(ResponsePacket::Batch(_)) => false,
+            Err(_) => true,
+        };
+        let mut health = self.health.lock().unwrap();
+        if failed {
+            health.record_failure();
+        } else {
+            health.record_success(started_at.elapsed());
+        }
+        response
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Quorum {
+    threshold: usize,
+    methods: HashSet<String>,
+}
+
+/// Endpoints holding the state that later requests depend on.
+#[derive(Debug, Default)]
+struct Affinity {
+    /// Endpoint that answered the last request.
+    last: Option<usize>,
+    /// Endpoints that installed filters or sent transactions, by filter id or hash.
+    pinned: HashMap<String, usize>,
+    pinned_order: VecDeque<String>,
+}
+
+impl Affinity {
+    fn pin(&mut self, key: String, endpoint: usize) {
+        if self.pinned.insert(key.clone(), endpoint).is_none() {
+            self.pinned_order.push_back(key);
+        }
+        while self.pinned_order.len() > PINNED_CAPACITY {
+            if let Some(oldest) = self.pinned_order.pop_front() {
+                self.pinned.remove(&oldest);
+            }
+        }
+    }
+
+    fn unpin(&mut self, key: &str) {
+        if self.pinned.remove(key).is_some() {
+            self.pinned_order.retain(|pinned| pinned != key);
+        }
+    }
+}
+
+/// How a request depends on state held by the endpoint that served an earlier request.
+#[derive(Debug)]
+enum Stickiness {
+    /// Installs a filter or sends a transaction, identified by the result.
+    Pins,
+    /// Uses a filter, which only the endpoint that installed it knows.
+    Filter(String),
+    /// Uninstalls a filter, which only the endpoint that installed it knows.
+    Unpins(String),
+    /// Reads a transaction receipt, which the endpoint that sent it knows first.
+    Receipt(String),
+}
+
+impl Stickiness {
+    fn of(request: &SerializedRequest) -> Option<Self> {
+        let key = || {
+            let params: Vec<serde_json::Value> =
+                serde_json::from_str(request.params()?.get()).ok()?;
+            params.first()?.as_str().map(state_key)
+        };
+        match request.method() {
+            "eth_newFilter"
+            | "eth_newBlockFilter"
+            | "eth_newPendingTransactionFilter"
+            | "eth_sendRawTransaction" => Some(Self::Pins),
+            "eth_getFilterChanges" | "eth_getFilterLogs" => key().map(Self::Filter),
+            "eth_uninstallFilter" => key().map(Self::Unpins),
+            "eth_getTransactionReceipt" => key().map(Self::Receipt),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a request reads the chain at an explicit block number or hash, rather than at a
+/// tag such as `latest` that endpoints at different heights resolve to different blocks.
+fn pinned_to_block(request: &SerializedRequest) -> bool {
+    let Some(params) = request
+        .params()
+        .and_then(|params| serde_json::from_str::<serde_json::Value>(params.get()).ok())
+    else {
+        return false;
+    };
+    let block = match request.method() {
+        "eth_getBlockByNumber" | "eth_getBlockTransactionCountByNumber" => params.get(0),
+        "eth_call" | "eth_getBalance" | "eth_getCode" | "eth_getTransactionCount" => params.get(1),
+        "eth_getStorageAt" | "eth_getProof" => params.get(2),
+        "eth_getLogs" => {
+            return params.get(0).is_some_and(|filter| {
+                filter.get("blockHash").is_some()
+                    || ["fromBlock", "toBlock"]
+                        .iter()
+                        .all(|key| filter.get(key).is_some_and(is_block_number))
+            })
+        }
+        _ => None,
+    };
+    // A block number, or an EIP-1898 block number or hash object
+    block.is_some_and(|block| {
+        is_block_number(block)
+            || block.get("blockHash").is_some()
+            || block.get("blockNumber").is_some_and(is_block_number)
+    })
+}
+
+fn is_block_number(value: &serde_json::Value) -> bool {
+    value.as_str().is_some_and(|value| value.starts_with("0x"))
+}
+
+/// Normalises a filter id or transaction hash, which nodes may zero-pad differently.
+fn state_key(id: &str) -> String {
+    let id = id.strip_prefix("0x").unwrap_or(id);
+    id.trim_start_matches('0').to_ascii_lowercase()
+}
+
+/// Transport sending each request to the healthiest of several endpoints, and to the next
+/// one when it fails.
+///
+/// Endpoints are ranked by success rate, with rates within `SUCCESS_RATE_TOLERANCE` of each
+/// other ranked as equal. Among those, the endpoint that answered the last request keeps
+/// answering, so that consecutive reads such as `eth_blockNumber` and `eth_getLogs` see the
+/// same chain. The others are ranked by latency, with endpoints whose latency was not
+/// measured yet ranked after the endpoints listed before them, and ties going to the
+/// endpoint listed first. An endpoint failing `max_consecutive_failures` requests in a row
+/// is only tried after every other endpoint until its cooldown has passed.
+///
+/// Filters are only used through the endpoint that installed them, and the receipts of sent
+/// transactions are first read from the endpoint that sent them.
+///
+/// Requests to the methods given to [`with_quorum`](Self::with_quorum) at an explicit block
+/// are instead sent to every endpoint, and only answered once `threshold` of them returned
+/// the same successful response.
+#[derive(Debug, Clone)]
+pub struct FallbackTransport {
+    endpoints: Arc<Vec<Arc<Endpoint>>>,
+    affinity: Arc<Mutex<Affinity>>,
+    max_consecutive_failures: u32,
+    failure_cooldown: Duration,
+    quorum: Option<Quorum>,
+}
+
+impl FallbackTransport {
+    /// Creates a transport over `(url, transport)` endpoints, in order of preference.
+    pub fn new(endpoints: Vec<(String, BoxTransport)>) -> Self {
+        Self {
+            endpoints: Arc::new(
+                endpoints
+                    .into_iter()
+                    .map(|(url, transport)| {
+                        Arc::new(Endpoint {
+                            transport,
+                            health: Mutex::new(EndpointHealth::new(&url)),
+                        })
+                    })
+                    .collect(),
+            ),
+            affinity: Arc::new(Mutex::new(Affinity::default())),
+            max_consecutive_failures: DEFAULT_MAX_CONSECUTIVE_FAILURES,
+            failure_cooldown: DEFAULT_FAILURE_COOLDOWN,
+            quorum: None,
+        }
+    }
+
+    /// Connects to HTTP, WebSocket or IPC endpoints, in order of preference. Endpoints that
+    /// cannot be connected to are left out, and an error is only returned if none can.
+    pub async fn connect(urls: &[String]) -> TransportResult<Self> {
+        let mut endpoints = Vec::new();
+        let mut last_error = None;
+        for url in urls {
+            let connected = match BuiltInConnectionString::from_str(url) {
+                Ok(connection) => connection.connect_boxed().await,
+                Err(e) => Err(e),
+            };
+            match connected {
+                Ok(transport) => endpoints.push((url.clone(), transport)),
+                Err(e) => {
+                    log::warn!("Failed to connect to RPC endpoint {}: {}", url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        match (endpoints.is_empty(), last_error) {
+            (true, Some(e)) => Err(e),
+            (true, None) => Err(TransportErrorKind::custom_str("no RPC endpoints given")),
+            (false, _) => Ok(Self::new(endpoints)),
+        }
+    }
+
+    /// Skips an endpoint after this many consecutive failures.
+    pub fn with_max_consecutive_failures(mut self, max_consecutive_failures: u32) -> Self {
+        self.max_consecutive_failures = max_consecutive_failures.max(1);
+        self
+    }
+
+    /// How long an endpoint that failed too often in a row is skipped for.
+    pub fn with_failure_cooldown(mut self, failure_cooldown: Duration) -> Self {
+        self.failure_cooldown = failure_cooldown;
+        self
+    }
+
+    /// Requires `threshold` endpoints to return the same successful response to requests to
+    /// `methods` at an explicit block number or hash, such as the `eth_call`s of
+    /// `get_operators_stake_in_quorums_at_block`. Requests at a block tag such as `latest`
+    /// are answered by a single endpoint, as endpoints at different heights disagree on them.
+    pub fn with_quorum(mut self, threshold: usize, methods: &[&str]) -> Self {
+        self.quorum = Some(Quorum {
+            threshold: threshold.max(1),
+            methods: methods.iter().map(|method| method.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Health of every endpoint, in the order they were given.
+    pub fn endpoints_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints
+            .iter()
+            .map(|endpoint| endpoint.health.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Indices of the endpoints in the order requests should be tried.
+    fn ranked_endpoints(&self) -> Vec<usize> {
+        let health: Vec<EndpointHealth> = self.endpoints_health();
+        let best_success_rate = health
+            .iter()
+            .map(|health| health.success_rate)
+            .fold(0.0, f64::max);
+        let last = self.affinity.lock().unwrap().last;
+        let mut latency_before = Duration::ZERO;
+        let mut ranked: Vec<_> = health
+            .iter()
+            .enumerate()
+            .map(|(index, health)| {
+                // Unmeasured endpoints are not preferred over the ones listed before them
+                let latency = if health.latency.is_zero() {
+                    latency_before
+                } else {
+                    latency_before = latency_before.max(health.latency);
+                    health.latency
+                };
+                let success_tier =
+                    ((best_success_rate - health.success_rate) / SUCCESS_RATE_TOLERANCE) as u64;
+                (
+                    health.is_cooling_down(self.max_consecutive_failures, self.failure_cooldown),
+                    success_tier,
+                    last != Some(index),
+                    latency,
+                    index,
+                )
+            })
+            .collect();
+        ranked.sort();
+        ranked.into_iter().map(|(.., index)| index).collect()
+    }
+
+    /// Sends `request` to the ranked endpoints, or to the endpoint holding the state it
+    /// depends on, and remembers which endpoint answered.
+    async fn send(self, request: RequestPacket) -> TransportResult<ResponsePacket> {
+        let stickiness = match &request {
+            RequestPacket::Single(request) => Stickiness::of(request),
+            RequestPacket::Batch(_) => None,
+        };
+        let pinned = match &stickiness {
+            Some(Stickiness::Filter(key) | Stickiness::Unpins(key) | Stickiness::Receipt(key)) => {
+                self.affinity.lock().unwrap().pinned.get(key).copied()
+            }
+            _ => None,
+        };
+        let order = match (&stickiness, pinned) {
+            (Some(Stickiness::Filter(_) | Stickiness::Unpins(_)), Some(pinned)) => vec![pinned],
+            (Some(Stickiness::Receipt(_)), Some(pinned)) => std::iter::once(pinned)
+                .chain(
+                    self.ranked_endpoints()
+                        .into_iter()
+                        .filter(|index| *index != pinned),
+                )
+                .collect(),
+            _ => self.ranked_endpoints(),
+        };
+        let endpoints = order
+            .into_iter()
+            .map(|index| (index, self.endpoints[index].clone()))
+            .collect();
+
+        let (answered_by, response) = send_with_fallback(endpoints, request).await;
+        if let (Some(answered_by), Ok(response)) = (answered_by, &response) {
+            let mut affinity = self.affinity.lock().unwrap();
+            affinity.last = Some(answered_by);
+            match (stickiness, response) {
+                (Some(Stickiness::Pins), ResponsePacket::Single(response)) => {
+                    if let ResponsePayload::Success(result) = &response.payload {
+                        if let Ok(id) = serde_json::from_str::<String>(result.get()) {
+                            affinity.pin(state_key(&id), answered_by);
+                        }
+                    }
+                }
+                (Some(Stickiness::Unpins(key)), _) => affinity.unpin(&key),
+                _ => {}
+            }
+        }
+        response
+    }
+
+    fn requires_quorum(&self, request: &RequestPacket) -> Option<usize> {
+        let quorum = self.quorum.as_ref()?;
+        let mut requests = match request {
+            RequestPacket::Single(request) => vec![request],
+            RequestPacket::Batch(requests) => requests.iter().collect(),
+        }
+        .into_iter();
+        requests
+            .any(|request| quorum.methods.contains(request.method()) && pinned_to_block(request))
+            .then_some(quorum.threshold)
+    }
+}
+
+impl Service<RequestPacket> for FallbackTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        match self.requires_quorum(&request) {
+            Some(threshold) => {
+                let endpoints = self
+                    .ranked_endpoints()
+                    .into_iter()
+                    .map(|index| self.endpoints[index].clone())
+                    .collect();
+                Box::pin(send_with_quorum(endpoints, request, threshold))
+            }
+            None => Box::pin(self.clone().send(request)),
+        }
+    }
+}
+
+/// Sends `request` to each of the `(index, endpoint)` pairs in turn until one answers.
+/// Returns the index of the endpoint that answered, if any did.
+async fn send_with_fallback(
+    endpoints: Vec<(usize, Arc<Endpoint>)>,
+    request: RequestPacket,
+) -> (Option<usize>, TransportResult<ResponsePacket>) {
+    let mut last_response = None;
+    for (index, endpoint) in endpoints {
+        let response = endpoint.send(request.clone()).await;
+        match &response {
+            Ok(ResponsePacket::Single(single)) => match &single.payload {
+                ResponsePayload::Failure(error) if error.is_retry_err() => {}
+                _ => return (Some(index), response),
+            },
+            Ok(ResponsePacket::Batch(_)) => return (Some(index), response),
+            Err(e) => log::debug!("RPC endpoint failed, trying the next one: {}", e),
+        }
+        last_response = Some(response);
+    }
+    let response = last_response
+        .unwrap_or_else(|| Err(TransportErrorKind::custom_str("no RPC endpoints given")));
+    (None, response)
+}
+
+async fn send_with_quorum(
+    endpoints: Vec<Arc<Endpoint>>,
+    request: RequestPacket,
+    threshold: usize,
+) -> TransportResult<ResponsePacket> {
+    if endpoints.len() < threshold {
+        return Err(TransportErrorKind::custom_str(&format!(
+            "quorum of {} needs more than the {} RPC endpoints given",
+            threshold,
+            endpoints.len()
+        )));
+    }
+
+    let mut requests = JoinSet::new();
+    for endpoint in endpoints {
+        let request = request.clone();
+        requests.spawn(async move { endpoint.send(request).await });
+    }
+    let mut agreeing: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut failures = 0;
+    let mut last_failure = None;
+    while let Some(response) = requests.join_next().await {
+        let (response, key) = match response {
+            Ok(Ok(response)) => match response_key(&response) {
+                Ok(key) => (response, key),
+                // Endpoints agreeing on an error have not confirmed anything
+                Err(error) => {
+                    log::debug!(
+                        "RPC endpoint returned an error during quorum read: {}",
+                        error
+                    );
+                    last_failure = Some(error);
+                    failures += 1;
+                    continue;
+                }
+            },
+            Ok(Err(e)) => {
+                log::debug!("RPC endpoint failed during quorum read: {}", e);
+                last_failure = Some(e.to_string());
+                failures += 1;
+                continue;
+            }
+            Err(e) => {
+                log::error!("Quorum read task failed: {}", e);
+                failures += 1;
+                continue;
+            }
+        };
+        let count = agreeing.entry(key).or_default();
+        *count += 1;
+        if *count >= threshold {
+            return Ok(response);
+        }
+        // Stop early once the remaining endpoints cannot make any response reach the threshold
+        let best = agreeing.values().copied().max().unwrap_or_default();
+        if best + requests.len() < threshold {
+            break;
+        }
+    }
+    Err(TransportErrorKind::custom_str(&format!(
+        "quorum of {} not reached: {} distinct responses, {} endpoint failures, last failure: {}",
+        threshold,
+        agreeing.len(),
+        failures,
+        last_failure.as_deref().unwrap_or("none")
+    )))
+}
+
+/// Identifies responses carrying the same results, independently of batch order. Fails with
+/// the first error among the responses, if any.
+fn response_key(response: &ResponsePacket) -> Result<Vec<String>, String> {
+    let responses = match response {
+        ResponsePacket::Single(response) => std::slice::from_ref(response),
+        ResponsePacket::Batch(responses) => responses.as_slice(),
+    };
+    let mut key = responses
+        .iter()
+        .map(|response| match &response.payload {
+            ResponsePayload::Success(result) => Ok(format!("{:?}:{}", response.id, result.get())),
+            ResponsePayload::Failure(error) => Err(error.to_string()),
+        })
+        .collect::<Result<Vec<String>, String>>()?;
+    key.sort();
+    Ok(key)
+}
+
+/// Provider over a [`FallbackTransport`], usable as `Config::PH`.
+///
+/// Subscriptions are not supported, so `Config::PW` should stay a WebSocket provider.
+#[derive(Debug, Clone)]
+pub struct FallbackProvider {
+    provider: RootProvider<FallbackTransport, Ethereum>,
+}
+
+impl FallbackProvider {
+    pub fn new(transport: FallbackTransport, is_local: bool) -> Self {
+        Self {
+            provider: RootProvider::new(RpcClient::new(transport, is_local)),
+        }
+    }
+
+    /// Connects to HTTP, WebSocket or IPC endpoints, in order of preference.
+    pub async fn connect(urls: &[String]) -> TransportResult<Self> {
+        let transport = FallbackTransport::connect(urls).await?;
+        Ok(Self::new(transport, false))
+    }
+
+    pub fn transport(&self) -> &FallbackTransport {
+        self.provider.client().transport()
+    }
+
+    /// Health of every endpoint, in the order they were given.
+    pub fn endpoints_health(&self) -> Vec<EndpointHealth> {
+        self.transport().endpoints_health()
+    }
+}
+
+impl Provider<FallbackTransport, Ethereum> for FallbackProvider {
+    fn root(&self) -> &RootProvider<FallbackTransport, Ethereum> {
+        &self.provider
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{MockResponse, MockTransport};
+    use alloy_json_rpc::Id;
+    use alloy_primitives::{Address, B256};
+    use alloy_rpc_types::{Filter, Log};
+    use alloy_transport::Transport;
+    use serde_json::json;
+
+    /// Answers `eth_blockNumber` and `eth_call` with `block_number`, or drops the connection
+    /// if `block_number` is `None`.
+    fn endpoint(block_number: Option<u64>) -> (BoxTransport, MockTransport) {
+        let transport = MockTransport::new(move |method, _| match (block_number, method) {
+            (None, _) => MockResponse::BackendGone,
+            (Some(block_number), "eth_blockNumber" | "eth_call") => {
+                MockResponse::Result(format!("{:#x}", block_number).into())
+            }
+            (Some(_), _) => MockResponse::Error(-32601, "method not found"),
+        });
+        (Transport::boxed(transport.clone()), transport)
+    }
+
+    /// Installs filter `filter_id` and sends transaction `0x..01`, answering filter requests
+    /// only for that filter.
+    fn stateful_endpoint(filter_id: &'static str) -> (BoxTransport, MockTransport) {
+        let transport = MockTransport::new(move |method, params| match method {
+            "eth_blockNumber" => MockResponse::Result("0x10".into()),
+            "eth_newFilter" => MockResponse::Result(filter_id.into()),
+            "eth_sendRawTransaction" => {
+                MockResponse::Result(format!("{}", B256::with_last_byte(1)).into())
+            }
+            "eth_getFilterChanges" if params[0] == filter_id => MockResponse::Result(json!([])),
+            "eth_uninstallFilter" if params[0] == filter_id => MockResponse::Result(json!(true)),
+            "eth_getTransactionReceipt" => MockResponse::Result(json!(null)),
+            _ => MockResponse::Error(-32000, "filter not found"),
+        });
+        (Transport::boxed(transport.clone()), transport)
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_fails_over() {
+        let (down, down_calls) = endpoint(None);
+        let (up, up_calls) = endpoint(Some(16));
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(vec![("down".to_string(), down), ("up".to_string(), up)])
+                .with_max_consecutive_failures(2),
+            true,
+        );
+
+        for _ in 0..3 {
+            assert_eq!(provider.get_block_number().await.unwrap(), 16);
+        }
+        // The failing endpoint is ranked last after its first failure, and not retried
+        assert_eq!(down_calls.requests(), 1);
+        assert_eq!(up_calls.requests(), 3);
+
+        // Error responses are answers, not endpoint failures
+        assert!(provider.get_chain_id().await.is_err());
+        assert_eq!(up_calls.requests(), 4);
+        assert_eq!(down_calls.requests(), 1);
+
+        let health = provider.endpoints_health();
+        assert_eq!(health[0].url, "down");
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert!(health[0].success_rate < 1.0);
+        assert_eq!(health[1].consecutive_failures, 0);
+        assert_eq!(health[1].success_rate, 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_quorum() {
+        let endpoints = |block_numbers: &[Option<u64>]| {
+            block_numbers
+                .iter()
+                .enumerate()
+                .map(|(i, block_number)| (i.to_string(), endpoint(*block_number).0))
+                .collect::<Vec<_>>()
+        };
+        let call_at = |provider: &FallbackProvider, block: &'static str| {
+            let provider = provider.clone();
+            async move {
+                provider
+                    .raw_request::<_, String>("eth_call".into(), (json!({}), block))
+                    .await
+            }
+        };
+        let quorum_methods = &["eth_call", "eth_getBalance"];
+
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(endpoints(&[Some(16), Some(17), Some(16), None]))
+                .with_quorum(2, quorum_methods),
+            true,
+        );
+        assert_eq!(call_at(&provider, "0x10").await.unwrap(), "0x10");
+
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(endpoints(&[Some(16), Some(17), None]))
+                .with_quorum(2, quorum_methods),
+            true,
+        );
+        assert!(call_at(&provider, "0x10").await.is_err());
+        // Reads at a block tag, and methods outside the quorum, are answered by one endpoint
+        assert!(call_at(&provider, "latest").await.is_ok());
+        assert!(provider.get_block_number().await.is_ok());
+
+        // Endpoints agreeing on an error do not make a quorum
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(endpoints(&[Some(16), Some(16)])).with_quorum(2, quorum_methods),
+            true,
+        );
+        let err = provider
+            .raw_request::<_, String>("eth_getBalance".into(), (Address::ZERO, "0x10"))
+            .await
+            .unwrap_err();
+        assert!(err.as_error_resp().is_none());
+        assert!(err.to_string().contains("method not found"));
+
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(endpoints(&[Some(16)])).with_quorum(2, quorum_methods),
+            true,
+        );
+        assert!(call_at(&provider, "0x10").await.is_err());
+    }
+
+    #[test]
+    fn test_pinned_to_block() {
+        let request = |method: &'static str, params: serde_json::Value| {
+            alloy_json_rpc::Request::new(method, Id::Number(1), params)
+                .serialize()
+                .unwrap()
+        };
+        let to = Address::ZERO;
+
+        assert!(pinned_to_block(&request("eth_call", json!([{}, "0x10"]))));
+        assert!(pinned_to_block(&request(
+            "eth_call",
+            json!([{}, { "blockHash": B256::ZERO }])
+        )));
+        assert!(pinned_to_block(&request(
+            "eth_getBalance",
+            json!([to, "0x10"])
+        )));
+        assert!(pinned_to_block(&request(
+            "eth_getLogs",
+            json!([{ "fromBlock": "0x1", "toBlock": "0x10" }])
+        )));
+        assert!(!pinned_to_block(&request(
+            "eth_call",
+            json!([{}, "latest"])
+        )));
+        assert!(!pinned_to_block(&request("eth_call", json!([{}]))));
+        assert!(!pinned_to_block(&request(
+            "eth_getLogs",
+            json!([{ "fromBlock": "0x1", "toBlock": "latest" }])
+        )));
+        assert!(!pinned_to_block(&request("eth_blockNumber", json!([]))));
+    }
+
+    #[test]
+    fn test_ranked_endpoints() {
+        let endpoints = (0..3)
+            .map(|i| (i.to_string(), endpoint(Some(16)).0))
+            .collect();
+        let transport = FallbackTransport::new(endpoints);
+        let set_health = |index: usize, success_rate: f64, latency_ms: u64| {
+            let mut health = transport.endpoints[index].health.lock().unwrap();
+            health.success_rate = success_rate;
+            health.latency = Duration::from_millis(latency_ms);
+        };
+
+        // Unmeasured endpoints are ranked in list order, and not ahead of measured ones
+        assert_eq!(transport.ranked_endpoints(), vec![0, 1, 2]);
+        set_health(0, 1.0, 50);
+        assert_eq!(transport.ranked_endpoints(), vec![0, 1, 2]);
+        set_health(2, 1.0, 20);
+        assert_eq!(transport.ranked_endpoints(), vec![2, 0, 1]);
+
+        // Close success rates are ranked as equal, leaving the order to latency
+        set_health(2, 0.97, 20);
+        assert_eq!(transport.ranked_endpoints(), vec![2, 0, 1]);
+        set_health(2, 0.8, 20);
+        assert_eq!(transport.ranked_endpoints(), vec![0, 1, 2]);
+
+        // The endpoint that answered the last request keeps answering while as successful
+        transport.affinity.lock().unwrap().last = Some(1);
+        assert_eq!(transport.ranked_endpoints(), vec![1, 0, 2]);
+        set_health(1, 0.8, 0);
+        assert_eq!(transport.ranked_endpoints(), vec![0, 1, 2]);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_provider_sticks_to_stateful_endpoints() {
+        let (first, first_calls) = stateful_endpoint("0x1");
+        let (second, second_calls) = stateful_endpoint("0x2");
+        let provider = FallbackProvider::new(
+            FallbackTransport::new(vec![
+                ("first".to_string(), first),
+                ("second".to_string(), second),
+            ]),
+            true,
+        );
+        let prefer_second = || provider.transport().affinity.lock().unwrap().last = Some(1);
+
+        // Filters are only used through the endpoint that installed them
+        let filter_id = provider.new_filter(&Filter::new()).await.unwrap();
+        prefer_second();
+        assert_eq!(provider.get_block_number().await.unwrap(), 16);
+        assert_eq!((first_calls.requests(), second_calls.requests()), (1, 1));
+        let changes: Vec<Log> = provider.get_filter_changes(filter_id).await.unwrap();
+        assert!(changes.is_empty());
+        let uninstalled: bool = provider
+            .raw_request("eth_uninstallFilter".into(), (filter_id,))
+            .await
+            .unwrap();
+        assert!(uninstalled);
+        assert_eq!((first_calls.requests(), second_calls.requests()), (3, 1));
+        // Once uninstalled, the filter is no longer pinned
+        prefer_second();
+        assert!(provider.get_filter_changes::<Log>(filter_id).await.is_err());
+        assert_eq!((first_calls.requests(), second_calls.requests()), (3, 2));
+
+        // Receipts are first read from the endpoint that sent the transaction
+        let sent: B256 = provider
+            .raw_request("eth_sendRawTransaction".into(), ("0x00",))
+            .await
+            .unwrap();
+        assert_eq!((first_calls.requests(), second_calls.requests()), (3, 3));
+        provider.transport().affinity.lock().unwrap().last = Some(0);
+        assert!(provider
+            .get_transaction_receipt(sent)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!((first_calls.requests(), second_calls.requests()), (3, 4));
+    }
+}
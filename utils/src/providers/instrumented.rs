@@ -0,0 +1,307 @@
+//! Provider recording the EigenLayer `eigen_rpc_request_*` metrics for every JSON-RPC request.
+use alloy_json_rpc::{Id, RequestPacket, ResponsePacket, RpcError, RpcParam, RpcReturn};
+use alloy_network::Ethereum;
+use alloy_primitives::B256;
+use alloy_provider::{Provider, RootProvider};
+use alloy_pubsub::Subscription;
+use alloy_rpc_client::{BuiltInConnectionString, RpcClient};
+use alloy_rpc_types::{Block, Filter, Log, Transaction};
+use alloy_transport::{
+    BoxTransport, BoxTransportConnect, Transport, TransportError, TransportErrorKind, TransportFut,
+    TransportResult,
+};
+use async_trait::async_trait;
+use std::future::Future;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower::Service;
+
+use crate::metrics::{ClientRpcMetrics, RpcCallsCollector};
+
+/// Transport recording the duration, count and failures of every request sent through it.
+#[derive(Debug, Clone)]
+pub struct InstrumentedTransport<T> {
+    inner: T,
+    metrics: ClientRpcMetrics,
+}
+
+impl<T> InstrumentedTransport<T> {
+    pub fn new(inner: T, metrics: ClientRpcMetrics) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+impl<T: Transport + Clone> Service<RequestPacket> for InstrumentedTransport<T> {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let requests: Vec<(Id, String)> = match &request {
+            RequestPacket::Single(request) => vec![request],
+            RequestPacket::Batch(requests) => requests.iter().collect(),
+        }
+        .into_iter()
+        .map(|request| (request.id().clone(), request.method().to_string()))
+        .collect();
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "rpc_request",
+            method = %requests
+                .iter()
+                .map(|(_, method)| method.as_str())
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+
+        let metrics = self.metrics.clone();
+        let started_at = Instant::now();
+        let response = self.inner.call(request);
+        let response = async move {
+            let response = response.await;
+            record_response(&metrics, &requests, &response, started_at.elapsed());
+            response
+        };
+        #[cfg(feature = "tracing")]
+        let response = tracing::Instrument::instrument(response, span);
+        Box::pin(response)
+    }
+}
+
+fn record_response(
+    metrics: &ClientRpcMetrics,
+    requests: &[(Id, String)],
+    response: &TransportResult<ResponsePacket>,
+    duration: Duration,
+) {
+    for (_, method) in requests {
+        metrics.observe_rpc_request(method, duration);
+    }
+
+    let failures: Vec<(&str, &str)> = match response {
+        Err(e) => {
+            let kind = transport_error_kind(e);
+            requests
+                .iter()
+                .map(|(_, method)| (method.as_str(), kind))
+                .collect()
+        }
+        Ok(ResponsePacket::Single(response)) => match (response.payload.is_error(), requests) {
+            (true, [(_, method)]) => vec![(method.as_str(), "error_response")],
+            _ => vec![],
+        },
+        Ok(ResponsePacket::Batch(responses)) => responses
+            .iter()
+            .filter(|response| response.payload.is_error())
+            .filter_map(|response| requests.iter().find(|(id, _)| *id == response.id))
+            .map(|(_, method)| (method.as_str(), "error_response"))
+            .collect(),
+    };
+    for (method, kind) in failures {
+        log::debug!("RPC request {} failed: {}", method, kind);
+        #[cfg(feature = "tracing")]
+        tracing::debug!(method, kind, "RPC request failed");
+        metrics.add_rpc_request_error(method, kind);
+    }
+}
+
+fn transport_error_kind(error: &TransportError) -> &'static str {
+    match error {
+        RpcError::ErrorResp(_) => "error_response",
+        RpcError::NullResp => "null_response",
+        RpcError::SerError(_) => "serialization",
+        RpcError::DeserError { .. } => "deserialization",
+        RpcError::Transport(TransportErrorKind::HttpError(_)) => "http",
+        RpcError::Transport(TransportErrorKind::BackendGone) => "backend_gone",
+        RpcError::Transport(TransportErrorKind::MissingBatchResponse(_)) => {
+            "missing_batch_response"
+        }
+        RpcError::Transport(_) => "transport",
+        _ => "other",
+    }
+}
+
+/// Provider recording the `eigen_rpc_request_*` metrics for every request, usable as
+/// `Config::PH` or `Config::PW` with `InstrumentedTransport<T>` as the transport.
+///
+/// Subscriptions need the pubsub frontend of the wrapped transport, so they are made through
+/// an uninstrumented provider sharing the same connection, and only `eth_subscribe` itself is
+/// recorded.
+#[derive(Debug, Clone)]
+pub struct InstrumentedProvider<T: Transport + Clone> {
+    provider: RootProvider<InstrumentedTransport<T>, Ethereum>,
+    subscriber: RootProvider<T, Ethereum>,
+    metrics: ClientRpcMetrics,
+}
+
+impl<T: Transport + Clone> InstrumentedProvider<T> {
+    pub fn new(transport: T, is_local: bool, metrics: ClientRpcMetrics) -> Self {
+        Self {
+            provider: RootProvider::new(RpcClient::new(
+                InstrumentedTransport::new(transport.clone(), metrics.clone()),
+                is_local,
+            )),
+            subscriber: RootProvider::new(RpcClient::new(transport, is_local)),
+            metrics,
+        }
+    }
+
+    /// Records an `eth_subscribe` request made through the uninstrumented provider.
+    async fn record_subscribe<R>(
+        &self,
+        subscribe: impl Future<Output = TransportResult<R>>,
+    ) -> TransportResult<R> {
+        let started_at = Instant::now();
+        let result = subscribe.await;
+        self.metrics
+            .observe_rpc_request("eth_subscribe", started_at.elapsed());
+        if let Err(e) = &result {
+            self.metrics
+                .add_rpc_request_error("eth_subscribe", transport_error_kind(e));
+        }
+        result
+    }
+}
+
+impl InstrumentedProvider<BoxTransport> {
+    /// Connects to an HTTP, WebSocket or IPC endpoint, labelling its requests with the client
+    /// version it reports.
+    pub async fn connect(
+        url: &str,
+        rpc_calls_collector: &RpcCallsCollector,
+    ) -> TransportResult<Self> {
+        let connection = BuiltInConnectionString::from_str(url)?;
+        let transport = connection.connect_boxed().await?;
+        let is_local = connection.is_local();
+        let client_version =
+            RootProvider::<_, Ethereum>::new(RpcClient::new(transport.clone(), is_local))
+                .get_client_version()
+                .await?;
+        Ok(Self::new(
+            transport,
+            is_local,
+            rpc_calls_collector.for_client(&client_version),
+        ))
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone> Provider<InstrumentedTransport<T>, Ethereum>
+    for InstrumentedProvider<T>
+{
+    fn root(&self) -> &RootProvider<InstrumentedTransport<T>, Ethereum> {
+        &self.provider
+    }
+
+    async fn subscribe_blocks(&self) -> TransportResult<Subscription<Block>> {
+        self.record_subscribe(self.subscriber.subscribe_blocks())
+            .await
+    }
+
+    async fn subscribe_pending_transactions(&self) -> TransportResult<Subscription<B256>> {
+        self.record_subscribe(self.subscriber.subscribe_pending_transactions())
+            .await
+    }
+
+    async fn subscribe_full_pending_transactions(
+        &self,
+    ) -> TransportResult<Subscription<Transaction>> {
+        self.record_subscribe(self.subscriber.subscribe_full_pending_transactions())
+            .await
+    }
+
+    async fn subscribe_logs(&self, filter: &Filter) -> TransportResult<Subscription<Log>> {
+        self.record_subscribe(self.subscriber.subscribe_logs(filter))
+            .await
+    }
+
+    async fn subscribe<P, R>(&self, params: P) -> TransportResult<Subscription<R>>
+    where
+        P: RpcParam,
+        R: RpcReturn,
+        Self: Sized,
+    {
+        self.record_subscribe(self.subscriber.subscribe(params))
+            .await
+    }
+
+    async fn unsubscribe(&self, id: B256) -> TransportResult<()> {
+        Provider::unsubscribe(&self.subscriber, id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{MockResponse, MockTransport};
+    use prometheus::{Encoder, Registry, TextEncoder};
+
+    #[tokio::test]
+    async fn test_instrumented_provider_records_requests() {
+        let registry = Registry::new();
+        let collector = RpcCallsCollector::new("test-avs", &registry).unwrap();
+        // Answers `eth_blockNumber`, fails `eth_chainId` with a JSON-RPC error and drops the
+        // connection on anything else
+        let transport = MockTransport::new(|method, _| match method {
+            "eth_blockNumber" => MockResponse::Result("0x10".into()),
+            "eth_chainId" => MockResponse::Error(-32000, "unavailable"),
+            _ => MockResponse::BackendGone,
+        });
+        let provider = InstrumentedProvider::new(transport, true, collector.for_client("mock"));
+
+        assert_eq!(provider.get_block_number().await.unwrap(), 16);
+        assert_eq!(provider.get_block_number().await.unwrap(), 16);
+        assert!(provider.get_chain_id().await.is_err());
+        assert!(provider.get_gas_price().await.is_err());
+        // The mock transport has no pubsub frontend
+        assert!(provider.subscribe_logs(&Filter::new()).await.is_err());
+
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&registry.gather(), &mut buffer)
+            .unwrap();
+        let metrics = String::from_utf8(buffer).unwrap();
+        let labels = r#"avs_name="test-avs",client_version="mock""#;
+        for line in [
+            format!(
+                r#"eigen_rpc_request_total{{{},method="eth_blockNumber"}} 2"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_total{{{},method="eth_chainId"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_total{{{},method="eth_gasPrice"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_total{{{},method="eth_subscribe"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_errors_total{{{},kind="error_response",method="eth_chainId"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_errors_total{{{},kind="backend_gone",method="eth_gasPrice"}} 1"#,
+                labels
+            ),
+            format!(
+                r#"eigen_rpc_request_errors_total{{{},kind="transport",method="eth_subscribe"}} 1"#,
+                labels
+            ),
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "missing {}", line);
+        }
+        assert!(!metrics
+            .lines()
+            .any(|l| l.starts_with("eigen_rpc_request_errors_total")
+                && l.contains("eth_blockNumber")));
+    }
+}
@@ -0,0 +1,90 @@
+//! Transport answering JSON-RPC requests from a handler, for tests.
+use alloy_json_rpc::{
+    ErrorPayload, RequestPacket, Response, ResponsePacket, ResponsePayload, SerializedRequest,
+};
+use alloy_transport::{TransportError, TransportErrorKind, TransportFut};
+use serde_json::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::Service;
+
+/// How [`MockTransport`] answers a request.
+pub(crate) enum MockResponse {
+    Result(Value),
+    /// A JSON-RPC error response with the given code and message.
+    Error(i64, &'static str),
+    /// The connection is dropped, failing the whole request packet.
+    BackendGone,
+}
+
+type Handler = dyn Fn(&str, Value) -> MockResponse + Send + Sync;
+
+/// Transport answering every request, batched or not, with a handler given the method and
+/// params of the request.
+#[derive(Clone)]
+pub(crate) struct MockTransport {
+    handler: Arc<Handler>,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockTransport {
+    pub(crate) fn new(
+        handler: impl Fn(&str, Value) -> MockResponse + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            requests: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Number of requests answered so far, counting each request of a batch.
+    pub(crate) fn requests(&self) -> usize {
+        self.requests.load(Ordering::Relaxed)
+    }
+
+    fn answer(&self, request: &SerializedRequest) -> Option<Response> {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let params = request
+            .params()
+            .map(|params| serde_json::from_str(params.get()).unwrap())
+            .unwrap_or(Value::Null);
+        let payload = match (self.handler)(request.method(), params) {
+            MockResponse::Result(result) => {
+                ResponsePayload::Success(serde_json::value::to_raw_value(&result).unwrap())
+            }
+            MockResponse::Error(code, message) => ResponsePayload::Failure(ErrorPayload {
+                code,
+                message: message.to_string(),
+                data: None,
+            }),
+            MockResponse::BackendGone => return None,
+        };
+        Some(Response {
+            id: request.id().clone(),
+            payload,
+        })
+    }
+}
+
+impl Service<RequestPacket> for MockTransport {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = match &request {
+            RequestPacket::Single(request) => self.answer(request).map(ResponsePacket::Single),
+            RequestPacket::Batch(requests) => requests
+                .iter()
+                .map(|request| self.answer(request))
+                .collect::<Option<Vec<_>>>()
+                .map(ResponsePacket::Batch),
+        };
+        Box::pin(async move { response.ok_or_else(TransportErrorKind::backend_gone) })
+    }
+}
@@ -0,0 +1,5 @@
+//! Ethereum providers usable as `Config::PH` and `Config::PW`.
+pub mod fallback;
+pub mod instrumented;
+#[cfg(test)]
+pub(crate) mod mock;
@@ -0,0 +1,144 @@
+use prometheus::{
+    exponential_buckets, linear_buckets, Histogram, HistogramOpts, HistogramVec, IntCounter,
+    IntCounterVec, Opts, Registry,
+};
+
+use crate::types::QuorumNum;
+
+use super::BlsAggregationError;
+
+const NAMESPACE: &str = "eigen";
+const SUBSYSTEM: &str = "bls_aggregation";
+
+/// Prometheus metrics recorded by the BLS aggregation service.
+///
+/// The collectors are reference counted, so clones record into the same series.
+#[derive(Debug, Clone)]
+pub struct BlsAggregationMetrics {
+    tasks_initialized: IntCounter,
+    tasks_completed: IntCounter,
+    tasks_expired: IntCounter,
+    tasks_cancelled: IntCounter,
+    signatures_accepted: IntCounter,
+    signatures_rejected: IntCounterVec,
+    time_to_quorum_seconds: Histogram,
+    signed_stake_percentage: HistogramVec,
+}
+
+impl BlsAggregationMetrics {
+    /// Creates the metrics and registers them against `registry`.
+    pub fn new(registry: &Registry) -> Result<Self, prometheus::Error> {
+        let opts = |name: &str, help: &str| {
+            Opts::new(name, help)
+                .namespace(NAMESPACE)
+                .subsystem(SUBSYSTEM)
+        };
+
+        let metrics = Self {
+            tasks_initialized: IntCounter::with_opts(opts(
+                "tasks_initialized_total",
+                "Number of tasks initialized",
+            ))?,
+            tasks_completed: IntCounter::with_opts(opts(
+                "tasks_completed_total",
+                "Number of tasks that met their stake thresholds",
+            ))?,
+            tasks_expired: IntCounter::with_opts(opts(
+                "tasks_expired_total",
+                "Number of tasks that expired before meeting their stake thresholds",
+            ))?,
+            tasks_cancelled: IntCounter::with_opts(opts(
+                "tasks_cancelled_total",
+                "Number of tasks cancelled before meeting their stake thresholds",
+            ))?,
+            signatures_accepted: IntCounter::with_opts(opts(
+                "signatures_accepted_total",
+                "Number of operator signatures accepted",
+            ))?,
+            signatures_rejected: IntCounterVec::new(
+                opts(
+                    "signatures_rejected_total",
+                    "Number of operator signatures rejected, by reason",
+                ),
+                &["reason"],
+            )?,
+            time_to_quorum_seconds: Histogram::with_opts(
+                HistogramOpts::new(
+                    "time_to_quorum_seconds",
+                    "Time from task initialization until its stake thresholds were met",
+                )
+                .namespace(NAMESPACE)
+                .subsystem(SUBSYSTEM)
+                .buckets(exponential_buckets(0.05, 2.0, 12)?),
+            )?,
+            signed_stake_percentage: HistogramVec::new(
+                HistogramOpts::new(
+                    "signed_stake_percentage",
+                    "Percentage of a quorum's stake that signed the task's best response",
+                )
+                .namespace(NAMESPACE)
+                .subsystem(SUBSYSTEM)
+                .buckets(linear_buckets(10.0, 10.0, 10)?),
+                &["quorum_number"],
+            )?,
+        };
+
+        registry.register(Box::new(metrics.tasks_initialized.clone()))?;
+        registry.register(Box::new(metrics.tasks_completed.clone()))?;
+        registry.register(Box::new(metrics.tasks_expired.clone()))?;
+        registry.register(Box::new(metrics.tasks_cancelled.clone()))?;
+        registry.register(Box::new(metrics.signatures_accepted.clone()))?;
+        registry.register(Box::new(metrics.signatures_rejected.clone()))?;
+        registry.register(Box::new(metrics.time_to_quorum_seconds.clone()))?;
+        registry.register(Box::new(metrics.signed_stake_percentage.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub(crate) fn task_initialized(&self) {
+        self.tasks_initialized.inc();
+    }
+
+    pub(crate) fn task_completed(&self, time_to_quorum_seconds: f64) {
+        self.tasks_completed.inc();
+        self.time_to_quorum_seconds.observe(time_to_quorum_seconds);
+    }
+
+    pub(crate) fn task_expired(&self) {
+        self.tasks_expired.inc();
+    }
+
+    pub(crate) fn task_cancelled(&self) {
+        self.tasks_cancelled.inc();
+    }
+
+    pub(crate) fn signed_stake(&self, quorum_num: &QuorumNum, percentage: f64) {
+        self.signed_stake_percentage
+            .with_label_values(&[&quorum_num.0.to_string()])
+            .observe(percentage);
+    }
+
+    pub(crate) fn signature_processed(&self, result: &Result<(), BlsAggregationError>) {
+        match result {
+            Ok(()) => self.signatures_accepted.inc(),
+            Err(err) => self
+                .signatures_rejected
+                .with_label_values(&[rejection_reason(err)])
+                .inc(),
+        }
+    }
+}
+
+fn rejection_reason(err: &BlsAggregationError) -> &'static str {
+    match err {
+        BlsAggregationError::IncorrectSignatureError => "incorrect_signature",
+        BlsAggregationError::OperatorNotPartOfTaskQuorumError(..) => "operator_not_in_quorum",
+        BlsAggregationError::DuplicateSignatureError(..) => "duplicate_signature",
+        BlsAggregationError::EquivocationError(..) => "equivocation",
+        BlsAggregationError::TaskInitializationError(..) => "task_not_initialized",
+        BlsAggregationError::TaskExpiredError(_)
+        | BlsAggregationError::TaskCancelledError(_)
+        | BlsAggregationError::TaskCompletedError(_) => "task_finished",
+        _ => "other",
+    }
+}
@@ -1,22 +1,25 @@
 use crate::crypto::bls::{G1Point, G2Point, Signature};
 use crate::services::avs_registry::AvsRegistryServiceTrait;
 use crate::types::{
-    bytes_to_quorum_ids, OperatorAvsState, OperatorId, QuorumNum, QuorumThresholdPercentage,
-    TaskIndex, TaskResponse, TaskResponseDigest,
+    bytes_to_quorum_ids, AvsError, OperatorAvsState, OperatorId, QuorumNum,
+    QuorumThresholdPercentage, TaskIndex, TaskResponse, TaskResponseDigest,
 };
-use crate::Config;
 use alloy_primitives::{keccak256, Bytes, U256};
 use async_trait::async_trait;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 use thiserror::Error;
-use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::sync::{broadcast, mpsc, oneshot, Semaphore};
+use tokio::time::Instant;
 
-use super::avs_registry::AvsRegistryServiceChainCaller;
-use super::operator_info::OperatorInfoServiceTrait;
+use metrics::BlsAggregationMetrics;
+use storage::{run_blocking, unix_millis, StoredSignature, StoredTask, TaskStore};
+
+pub mod metrics;
+pub mod storage;
 
 #[derive(Debug, Clone, Error)]
 pub enum BlsAggregationError {
@@ -26,6 +29,13 @@ pub enum BlsAggregationError {
     OperatorNotPartOfTaskQuorumError(OperatorId, TaskIndex),
     HashFunctionError(String),
     IncorrectSignatureError,
+    StorageError(String),
+    TaskCancelledError(TaskIndex),
+    TaskCompletedError(TaskIndex),
+    InvalidQuorumThresholdsError(String, TaskIndex),
+    DuplicateTaskIndexError(TaskIndex),
+    DuplicateSignatureError(OperatorId, TaskIndex),
+    EquivocationError(OperatorId, TaskIndex),
 }
 
 impl Display for BlsAggregationError {
@@ -61,6 +71,39 @@ impl Display for BlsAggregationError {
                     e, task_index
                 )
             }
+            BlsAggregationError::StorageError(e) => {
+                write!(f, "Task storage error: {}", e)
+            }
+            BlsAggregationError::TaskCancelledError(task_index) => {
+                write!(f, "Task cancelled for task index: {}", task_index)
+            }
+            BlsAggregationError::TaskCompletedError(task_index) => {
+                write!(f, "Task already completed for task index: {}", task_index)
+            }
+            BlsAggregationError::InvalidQuorumThresholdsError(e, task_index) => {
+                write!(
+                    f,
+                    "Invalid quorum thresholds: {} for task index: {}",
+                    e, task_index
+                )
+            }
+            BlsAggregationError::DuplicateTaskIndexError(task_index) => {
+                write!(f, "Task already initialized for task index: {}", task_index)
+            }
+            BlsAggregationError::DuplicateSignatureError(operator_id, task_index) => {
+                write!(
+                    f,
+                    "Operator with id: {} already signed task index: {}",
+                    operator_id, task_index
+                )
+            }
+            BlsAggregationError::EquivocationError(operator_id, task_index) => {
+                write!(
+                    f,
+                    "Operator with id: {} signed conflicting responses for task index: {}",
+                    operator_id, task_index
+                )
+            }
         }
     }
 }
@@ -79,6 +122,29 @@ pub struct BlsAggregationServiceResponse {
     pub quorum_apk_indices: Vec<u32>,
     pub total_stake_indices: Vec<u32>,
     pub non_signer_stake_indices: Vec<Vec<u32>>,
+    /// Stake gathered by every digest signed for the task. Only filled in on
+    /// [`BlsAggregationError::TaskExpiredError`] responses, sorted closest-to-quorum first.
+    pub digest_reports: Vec<DigestAggregationReport>,
+}
+
+/// How much stake signed one of the competing responses to a task.
+#[derive(Debug, Default, Clone)]
+pub struct DigestAggregationReport {
+    pub task_response: TaskResponse,
+    pub task_response_digest: TaskResponseDigest,
+    pub signers_operator_ids: HashSet<OperatorId>,
+    pub signed_stake_per_quorum: HashMap<QuorumNum, U256>,
+    /// Signed stake as a percentage of each quorum's total stake.
+    pub signed_stake_percentage_per_quorum: HashMap<QuorumNum, f64>,
+}
+
+impl DigestAggregationReport {
+    fn lowest_signed_stake_percentage(&self) -> f64 {
+        self.signed_stake_percentage_per_quorum
+            .values()
+            .copied()
+            .fold(f64::INFINITY, f64::min)
+    }
 }
 
 #[derive(Debug, Clone, Default)]
@@ -89,6 +155,195 @@ struct AggregatedOperators {
     signers_operator_ids_set: HashSet<OperatorId>,
 }
 
+/// Per-task state owned by a single task aggregator.
+struct SingleTaskState {
+    task_index: TaskIndex,
+    task_created_block: u32,
+    quorum_numbers: Bytes,
+    quorum_threshold_percentages_map: HashMap<QuorumNum, QuorumThresholdPercentage>,
+    operators_avs_state_dict: HashMap<OperatorId, OperatorAvsState>,
+    total_stake_per_quorum: HashMap<QuorumNum, U256>,
+    quorum_apks_g1: Vec<G1Point>,
+    aggregated_operators_dict: HashMap<TaskResponseDigest, AggregatedOperators>,
+    /// Digest of the first response that met the stake thresholds, once one has been sent.
+    responded_digest: Option<TaskResponseDigest>,
+    /// When to send an improved response for signatures aggregated since the last one, with
+    /// continued aggregation.
+    improved_response_due: Option<Instant>,
+    /// First verified signature received from each operator.
+    operator_signed_digests: HashMap<OperatorId, OperatorSignedDigest>,
+    started_at: Instant,
+}
+
+/// A verified signature by an operator over a task response.
+#[derive(Debug, Clone)]
+pub struct OperatorSignedDigest {
+    pub task_response: TaskResponse,
+    pub task_response_digest: TaskResponseDigest,
+    pub bls_signature: Signature,
+}
+
+/// Two valid signatures by the same operator over different responses to the same task.
+///
+/// Both signatures verify against the operator's registered G2 pubkey, so the evidence can
+/// be checked independently of the aggregator.
+#[derive(Debug, Clone)]
+pub struct EquivocationEvidence {
+    pub task_index: TaskIndex,
+    pub operator_id: OperatorId,
+    pub first: OperatorSignedDigest,
+    pub second: OperatorSignedDigest,
+}
+
+/// Snapshot of an in-flight task, as returned by
+/// [`BlsAggregationService::get_task_status`].
+///
+/// Stake and signers are summed over every digest signed so far, whether or not the operators
+/// agree on the response.
+#[derive(Debug, Clone, Default)]
+pub struct TaskStatus {
+    pub task_index: TaskIndex,
+    pub signed_stake_per_quorum: HashMap<QuorumNum, U256>,
+    pub total_stake_per_quorum: HashMap<QuorumNum, U256>,
+    pub signers_operator_ids: HashSet<OperatorId>,
+    pub task_response_digests: Vec<TaskResponseDigest>,
+    pub time_remaining: Duration,
+}
+
+/// Requests sent to a running single task aggregator from outside its signature stream.
+#[derive(Debug)]
+enum TaskControlMessage {
+    Cancel(oneshot::Sender<()>),
+    ExtendExpiry(Duration, oneshot::Sender<()>),
+    GetStatus(oneshot::Sender<TaskStatus>),
+}
+
+impl SingleTaskState {
+    /// The task response signed by the operators that signed `task_response_digest`.
+    fn task_response(&self, task_response_digest: &TaskResponseDigest) -> TaskResponse {
+        self.aggregated_operators_dict
+            .get(task_response_digest)
+            .and_then(|aggregated_operators| {
+                aggregated_operators
+                    .signers_operator_ids_set
+                    .iter()
+                    .find_map(|operator_id| self.operator_signed_digests.get(operator_id))
+            })
+            .map(|signed_digest| signed_digest.task_response.clone())
+            .unwrap_or_default()
+    }
+
+    fn digest_reports(&self) -> Vec<DigestAggregationReport> {
+        let mut digest_reports: Vec<DigestAggregationReport> = self
+            .aggregated_operators_dict
+            .iter()
+            .map(|(task_response_digest, aggregated_operators)| {
+                let task_response = self.task_response(task_response_digest);
+
+                let signed_stake_percentage_per_quorum = self
+                    .quorum_threshold_percentages_map
+                    .keys()
+                    .map(|quorum_num| {
+                        let signed_stake = aggregated_operators
+                            .signers_total_stake_per_quorum
+                            .get(quorum_num)
+                            .copied()
+                            .unwrap_or_default();
+                        let total_stake = self
+                            .total_stake_per_quorum
+                            .get(quorum_num)
+                            .copied()
+                            .unwrap_or_default();
+                        (
+                            quorum_num.clone(),
+                            stake_percentage(signed_stake, total_stake),
+                        )
+                    })
+                    .collect();
+
+                DigestAggregationReport {
+                    task_response,
+                    task_response_digest: *task_response_digest,
+                    signers_operator_ids: aggregated_operators.signers_operator_ids_set.clone(),
+                    signed_stake_per_quorum: aggregated_operators
+                        .signers_total_stake_per_quorum
+                        .clone(),
+                    signed_stake_percentage_per_quorum,
+                }
+            })
+            .collect();
+
+        digest_reports.sort_by(|a, b| {
+            b.lowest_signed_stake_percentage()
+                .total_cmp(&a.lowest_signed_stake_percentage())
+        });
+        digest_reports
+    }
+
+    fn status(&self, task_expiry_deadline: Instant) -> TaskStatus {
+        let mut signed_stake_per_quorum: HashMap<QuorumNum, U256> = HashMap::new();
+        let mut signers_operator_ids = HashSet::new();
+        for aggregated_operators in self.aggregated_operators_dict.values() {
+            for (quorum_num, stake) in &aggregated_operators.signers_total_stake_per_quorum {
+                *signed_stake_per_quorum
+                    .entry(quorum_num.clone())
+                    .or_default() += stake;
+            }
+            signers_operator_ids.extend(aggregated_operators.signers_operator_ids_set.iter());
+        }
+
+        TaskStatus {
+            task_index: self.task_index,
+            signed_stake_per_quorum,
+            total_stake_per_quorum: self.total_stake_per_quorum.clone(),
+            signers_operator_ids,
+            task_response_digests: self.aggregated_operators_dict.keys().cloned().collect(),
+            time_remaining: task_expiry_deadline.saturating_duration_since(Instant::now()),
+        }
+    }
+}
+
+/// Configuration for verifying incoming signatures in batches rather than one at a time.
+///
+/// Signatures are buffered for up to `window` (or until `max_batch_size` are buffered) and
+/// then checked together with a single multi-pairing.
+#[derive(Debug, Clone)]
+pub struct BatchVerificationConfig {
+    pub window: Duration,
+    pub max_batch_size: usize,
+}
+
+impl Default for BatchVerificationConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_millis(100),
+            max_batch_size: 128,
+        }
+    }
+}
+
+/// Configuration for continuing to aggregate signatures after the stake thresholds are met.
+///
+/// The first response meeting the thresholds is sent as usual. Later signatures for the same
+/// digest are collected for up to `response_interval` and then emit a single updated
+/// [`BlsAggregationServiceResponse`] with fewer non-signers, until the task expires or
+/// `full_participation_percentage` of the stake in every quorum has signed, which is sent
+/// right away.
+#[derive(Debug, Clone)]
+pub struct ContinuedAggregationConfig {
+    pub full_participation_percentage: QuorumThresholdPercentage,
+    pub response_interval: Duration,
+}
+
+impl Default for ContinuedAggregationConfig {
+    fn default() -> Self {
+        Self {
+            full_participation_percentage: QuorumThresholdPercentage(100),
+            response_interval: Duration::from_secs(1),
+        }
+    }
+}
+
 #[async_trait]
 pub trait BlsAggregationService {
     async fn initialize_new_task(
@@ -107,18 +362,77 @@ pub trait BlsAggregationService {
         bls_signature: Signature,
         operator_id: OperatorId,
     ) -> Result<(), BlsAggregationError>;
+
+    /// Stops aggregating the task. A response carrying
+    /// [`BlsAggregationError::TaskCancelledError`] is broadcast to subscribers.
+    async fn cancel_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError>;
+
+    /// Pushes the task's expiry back by `extension`.
+    async fn extend_task_expiry(
+        &self,
+        task_index: TaskIndex,
+        extension: Duration,
+    ) -> Result<(), BlsAggregationError>;
+
+    async fn get_task_status(
+        &self,
+        task_index: TaskIndex,
+    ) -> Result<TaskStatus, BlsAggregationError>;
+
+    /// Waits for the first response sent for the task, returning its error if the task
+    /// failed. Resolves immediately if the response is still in the recent responses buffer.
+    async fn await_task_result(
+        &self,
+        task_index: TaskIndex,
+    ) -> Result<BlsAggregationServiceResponse, BlsAggregationError>;
+}
+
+/// Receiving ends of the channels feeding a single task aggregator.
+struct TaskReceivers {
+    rx: mpsc::Receiver<SignedTaskResponseDigest>,
+    control_rx: mpsc::Receiver<TaskControlMessage>,
+}
+
+/// Number of responses kept for subscribers and waiters that attach after a task finished.
+pub const RECENT_RESPONSES_CAPACITY: usize = 100;
+
+/// Number of equivocation evidence entries kept until taken with
+/// [`BlsAggregatorService::take_equivocation_evidence`].
+pub const EQUIVOCATION_EVIDENCE_CAPACITY: usize = 1000;
+
+/// Number of tasks whose last response is remembered, so that late signatures for them are
+/// rejected as such rather than as signatures for uninitialized tasks.
+pub const TASK_OUTCOMES_CAPACITY: usize = 1000;
+
+/// Number of signature checks, single or batched, that run at once by default.
+pub const DEFAULT_MAX_CONCURRENT_VERIFICATIONS: usize = 16;
+
+/// Responses sent recently, together with the callers waiting on a task's response.
+#[derive(Default)]
+struct ResponseBuffer {
+    recent_responses: VecDeque<BlsAggregationServiceResponse>,
+    task_result_txs: HashMap<TaskIndex, Vec<oneshot::Sender<BlsAggregationServiceResponse>>>,
+    /// Error of the last response sent for each task, `None` if it met its thresholds.
+    task_outcomes: VecDeque<(TaskIndex, Option<BlsAggregationError>)>,
 }
 
 #[derive(Clone)]
-pub struct BlsAggregatorService<T, I>
+pub struct BlsAggregatorService<A>
 where
-    T: Config,
-    I: OperatorInfoServiceTrait,
+    A: AvsRegistryServiceTrait,
 {
     pub aggregated_responses_tx: broadcast::Sender<BlsAggregationServiceResponse>,
     pub signed_task_resps_txs:
         Arc<Mutex<HashMap<TaskIndex, mpsc::Sender<SignedTaskResponseDigest>>>>,
-    pub avs_registry_service: AvsRegistryServiceChainCaller<T, I>,
+    task_control_txs: Arc<Mutex<HashMap<TaskIndex, mpsc::Sender<TaskControlMessage>>>>,
+    response_buffer: Arc<Mutex<ResponseBuffer>>,
+    equivocation_evidence: Arc<Mutex<VecDeque<EquivocationEvidence>>>,
+    verification_permits: Arc<Semaphore>,
+    pub avs_registry_service: A,
+    pub batch_verification_config: Option<BatchVerificationConfig>,
+    pub task_store: Option<Arc<dyn TaskStore>>,
+    pub continued_aggregation_config: Option<ContinuedAggregationConfig>,
+    pub metrics: Option<BlsAggregationMetrics>,
 }
 
 #[derive(Debug)]
@@ -130,10 +444,9 @@ pub struct SignedTaskResponseDigest {
 }
 
 #[async_trait]
-impl<T, I> BlsAggregationService for BlsAggregatorService<T, I>
+impl<A> BlsAggregationService for BlsAggregatorService<A>
 where
-    T: Config,
-    I: OperatorInfoServiceTrait,
+    A: AvsRegistryServiceTrait,
 {
     async fn initialize_new_task(
         &self,
@@ -145,33 +458,42 @@ where
     ) -> Result<(), BlsAggregationError> {
         log::debug!("AggregatorService initializing new task: {:?}", task_index);
 
-        let (tx, rx) = mpsc::channel(100);
-        self.signed_task_resps_txs
-            .lock()
-            .unwrap()
-            .insert(task_index, tx);
-
-        let avs_registry_service = self.avs_registry_service.clone();
-        let aggregated_responses_tx = self.aggregated_responses_tx.clone();
-        let signed_task_resps_txs = Arc::clone(&self.signed_task_resps_txs);
+        validate_quorum_thresholds(task_index, &quorum_numbers, &quorum_threshold_percentages)?;
+        // Registering first claims the task index, so concurrent initializations of the same
+        // index cannot both get past this point
+        let task_receivers = self.register_task(task_index)?;
 
-        tokio::spawn(async move {
-            let service_clone = BlsAggregatorService {
-                avs_registry_service,
-                aggregated_responses_tx,
-                signed_task_resps_txs,
+        if let Some(task_store) = &self.task_store {
+            let stored_task = StoredTask {
+                task_index,
+                task_created_block,
+                quorum_numbers: quorum_numbers.clone(),
+                quorum_threshold_percentages: quorum_threshold_percentages.clone(),
+                expires_at_ms: unix_millis(SystemTime::now() + time_to_expiry),
+                signatures: Vec::new(),
             };
-            service_clone
-                .single_task_aggregator(
-                    task_index,
-                    task_created_block,
-                    quorum_numbers,
-                    quorum_threshold_percentages,
-                    time_to_expiry,
-                    rx,
-                )
-                .await;
-        });
+            let stored = run_blocking(task_store, move |task_store| {
+                task_store.store_task(&stored_task)
+            })
+            .await;
+            if let Err(err) = stored {
+                self.unregister_task(task_index);
+                return Err(err);
+            }
+        }
+
+        self.spawn_single_task_aggregator(
+            task_index,
+            task_created_block,
+            quorum_numbers,
+            quorum_threshold_percentages,
+            time_to_expiry,
+            Vec::new(),
+            task_receivers,
+        );
+        if let Some(metrics) = &self.metrics {
+            metrics.task_initialized();
+        }
 
         Ok(())
     }
@@ -183,60 +505,328 @@ where
         bls_signature: Signature,
         operator_id: OperatorId,
     ) -> Result<(), BlsAggregationError> {
-        let tx_opt = {
-            let task_resps_txs = self.signed_task_resps_txs.lock().unwrap();
-            task_resps_txs.get(&task_index).cloned()
-        };
+        let result = self
+            .send_signature_to_task(task_index, task_response, bls_signature, operator_id)
+            .await;
+        if let Some(metrics) = &self.metrics {
+            metrics.signature_processed(&result);
+        }
+        result
+    }
 
-        if let Some(tx) = tx_opt {
-            let (tx_res, rx_res) = oneshot::channel();
-            let send_result = tx
-                .send(SignedTaskResponseDigest {
-                    task_response,
-                    bls_signature,
-                    operator_id,
-                    signature_verification_error_tx: tx_res,
-                })
-                .await;
+    async fn cancel_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError> {
+        self.send_task_control_message(task_index, TaskControlMessage::Cancel)
+            .await
+    }
 
-            send_result.map_err(|_| {
-                BlsAggregationError::ProcessNewSignature(
-                    "Failed to send signed task response digest".to_string(),
-                    task_index,
-                )
-            })?;
+    async fn extend_task_expiry(
+        &self,
+        task_index: TaskIndex,
+        extension: Duration,
+    ) -> Result<(), BlsAggregationError> {
+        self.send_task_control_message(task_index, |tx| {
+            TaskControlMessage::ExtendExpiry(extension, tx)
+        })
+        .await
+    }
 
-            rx_res.await.map_err(|_| {
-                BlsAggregationError::ProcessNewSignature(
-                    "Failed to receive signature verification result".to_string(),
+    async fn get_task_status(
+        &self,
+        task_index: TaskIndex,
+    ) -> Result<TaskStatus, BlsAggregationError> {
+        self.send_task_control_message(task_index, TaskControlMessage::GetStatus)
+            .await
+    }
+
+    async fn await_task_result(
+        &self,
+        task_index: TaskIndex,
+    ) -> Result<BlsAggregationServiceResponse, BlsAggregationError> {
+        let rx = {
+            let mut response_buffer = self.response_buffer.lock().unwrap();
+            if let Some(response) = response_buffer
+                .recent_responses
+                .iter()
+                .find(|response| response.task_index == task_index)
+            {
+                return into_task_result(response.clone());
+            }
+
+            if !self
+                .signed_task_resps_txs
+                .lock()
+                .unwrap()
+                .contains_key(&task_index)
+            {
+                return Err(BlsAggregationError::TaskInitializationError(
+                    "Task not initialized".to_string(),
                     task_index,
-                )
-            })?
-        } else {
-            Err(BlsAggregationError::TaskInitializationError(
-                "Task not initialized".to_string(),
+                ));
+            }
+
+            let (tx, rx) = oneshot::channel();
+            response_buffer
+                .task_result_txs
+                .entry(task_index)
+                .or_default()
+                .push(tx);
+            rx
+        };
+
+        let response = rx.await.map_err(|_| {
+            BlsAggregationError::ProcessNewSignature(
+                "Task aggregator stopped without a response".to_string(),
                 task_index,
-            ))
-        }
+            )
+        })?;
+        into_task_result(response)
     }
 }
 
-impl<T, I> BlsAggregatorService<T, I>
+impl<A> BlsAggregatorService<A>
 where
-    T: Config,
-    I: OperatorInfoServiceTrait,
+    A: AvsRegistryServiceTrait,
 {
     pub fn new(
         aggregated_responses_tx: broadcast::Sender<BlsAggregationServiceResponse>,
-        avs_registry_service: AvsRegistryServiceChainCaller<T, I>,
+        avs_registry_service: A,
     ) -> Self {
         Self {
             aggregated_responses_tx,
             signed_task_resps_txs: Arc::new(Mutex::new(HashMap::new())),
+            task_control_txs: Arc::new(Mutex::new(HashMap::new())),
+            response_buffer: Arc::new(Mutex::new(ResponseBuffer::default())),
+            equivocation_evidence: Arc::new(Mutex::new(VecDeque::new())),
+            verification_permits: Arc::new(Semaphore::new(DEFAULT_MAX_CONCURRENT_VERIFICATIONS)),
             avs_registry_service,
+            batch_verification_config: None,
+            task_store: None,
+            continued_aggregation_config: None,
+            metrics: None,
+        }
+    }
+
+    /// Runs at most `max_concurrent_verifications` signature checks at once across all tasks,
+    /// counting a batch as one check. The pairings run on the blocking thread pool, so that
+    /// a flood of signatures cannot starve the async executor. Defaults to
+    /// [`DEFAULT_MAX_CONCURRENT_VERIFICATIONS`].
+    pub fn with_max_concurrent_verifications(
+        mut self,
+        max_concurrent_verifications: usize,
+    ) -> Self {
+        self.verification_permits = Arc::new(Semaphore::new(max_concurrent_verifications.max(1)));
+        self
+    }
+
+    /// Enables batched signature verification for tasks initialized after this call.
+    pub fn with_batch_verification(mut self, config: BatchVerificationConfig) -> Self {
+        self.batch_verification_config = Some(config);
+        self
+    }
+
+    /// Journals initialized tasks and accepted signatures to `task_store`, so that in-flight
+    /// tasks can be resumed with [`Self::resume_stored_tasks`] after a restart.
+    pub fn with_task_store(mut self, task_store: Arc<dyn TaskStore>) -> Self {
+        self.task_store = Some(task_store);
+        self
+    }
+
+    /// Subscribes to aggregated responses, returning the buffered recent responses alongside
+    /// the receiver so that responses sent before subscribing are not missed. No response is
+    /// both in the returned buffer and delivered on the receiver.
+    pub fn subscribe_with_recent_responses(
+        &self,
+    ) -> (
+        Vec<BlsAggregationServiceResponse>,
+        broadcast::Receiver<BlsAggregationServiceResponse>,
+    ) {
+        let response_buffer = self.response_buffer.lock().unwrap();
+        (
+            response_buffer.recent_responses.iter().cloned().collect(),
+            self.aggregated_responses_tx.subscribe(),
+        )
+    }
+
+    /// Records task and signature metrics into `metrics`.
+    pub fn with_metrics(mut self, metrics: BlsAggregationMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Takes the equivocation evidence recorded since the last call, across all tasks.
+    ///
+    /// At most [`EQUIVOCATION_EVIDENCE_CAPACITY`] pieces of evidence are kept between calls;
+    /// older evidence is dropped first.
+    pub fn take_equivocation_evidence(&self) -> Vec<EquivocationEvidence> {
+        self.equivocation_evidence
+            .lock()
+            .unwrap()
+            .drain(..)
+            .collect()
+    }
+
+    /// Keeps aggregating signatures after a task's stake thresholds are met, emitting improved
+    /// responses as described in [`ContinuedAggregationConfig`].
+    ///
+    /// Subscribers will receive several successful responses for the same task index and
+    /// should only act on the one they need, e.g. the latest before submitting on chain.
+    pub fn with_continued_aggregation(mut self, config: ContinuedAggregationConfig) -> Self {
+        self.continued_aggregation_config = Some(config);
+        self
+    }
+
+    /// Restarts aggregation for every unexpired task in the task store, replaying the
+    /// signatures that were accepted before the restart. Expired tasks, and tasks with invalid
+    /// quorum thresholds, are dropped from the store.
+    ///
+    /// Returns the indices of the resumed tasks.
+    pub async fn resume_stored_tasks(&self) -> Result<Vec<TaskIndex>, BlsAggregationError> {
+        let Some(task_store) = &self.task_store else {
+            return Ok(Vec::new());
+        };
+
+        let mut resumed_task_indices = Vec::new();
+        for stored_task in run_blocking(task_store, |task_store| task_store.load_tasks()).await? {
+            let Some(time_to_expiry) = stored_task.time_to_expiry() else {
+                log::info!(
+                    "Dropping stored task {} as it expired while the aggregator was down",
+                    stored_task.task_index
+                );
+                let task_index = stored_task.task_index;
+                run_blocking(task_store, move |task_store| {
+                    task_store.remove_task(task_index)
+                })
+                .await?;
+                continue;
+            };
+            if let Err(e) = validate_quorum_thresholds(
+                stored_task.task_index,
+                &stored_task.quorum_numbers,
+                &stored_task.quorum_threshold_percentages,
+            ) {
+                log::error!("Dropping stored task {}: {}", stored_task.task_index, e);
+                let task_index = stored_task.task_index;
+                run_blocking(task_store, move |task_store| {
+                    task_store.remove_task(task_index)
+                })
+                .await?;
+                continue;
+            }
+            let task_receivers = match self.register_task(stored_task.task_index) {
+                Ok(task_receivers) => task_receivers,
+                Err(e) => {
+                    log::warn!("Not resuming stored task {}: {}", stored_task.task_index, e);
+                    continue;
+                }
+            };
+
+            log::info!(
+                "Resuming stored task {} with {} accepted signatures",
+                stored_task.task_index,
+                stored_task.signatures.len()
+            );
+            resumed_task_indices.push(stored_task.task_index);
+            self.spawn_single_task_aggregator(
+                stored_task.task_index,
+                stored_task.task_created_block,
+                stored_task.quorum_numbers,
+                stored_task.quorum_threshold_percentages,
+                time_to_expiry,
+                stored_task.signatures,
+                task_receivers,
+            );
         }
+
+        Ok(resumed_task_indices)
+    }
+
+    /// Claims `task_index` for a new task aggregator, returning the receiving ends of its
+    /// channels. Fails if a task with the same index is already running.
+    fn register_task(&self, task_index: TaskIndex) -> Result<TaskReceivers, BlsAggregationError> {
+        let (tx, rx) = mpsc::channel(100);
+        match self.signed_task_resps_txs.lock().unwrap().entry(task_index) {
+            Entry::Occupied(_) => {
+                return Err(BlsAggregationError::DuplicateTaskIndexError(task_index))
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(tx);
+            }
+        }
+        let (control_tx, control_rx) = mpsc::channel(10);
+        self.task_control_txs
+            .lock()
+            .unwrap()
+            .insert(task_index, control_tx);
+
+        Ok(TaskReceivers { rx, control_rx })
+    }
+
+    /// Releases a task index claimed by [`Self::register_task`] whose aggregator was never
+    /// spawned.
+    fn unregister_task(&self, task_index: TaskIndex) {
+        self.signed_task_resps_txs
+            .lock()
+            .unwrap()
+            .remove(&task_index);
+        self.task_control_txs.lock().unwrap().remove(&task_index);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_single_task_aggregator(
+        &self,
+        task_index: TaskIndex,
+        task_created_block: u32,
+        quorum_numbers: Bytes,
+        quorum_threshold_percentages: Vec<QuorumThresholdPercentage>,
+        time_to_expiry: Duration,
+        restored_signatures: Vec<StoredSignature>,
+        task_receivers: TaskReceivers,
+    ) {
+        let service_clone = self.clone();
+
+        tokio::spawn(async move {
+            let task_store = service_clone.task_store.clone();
+            let signed_task_resps_txs = Arc::clone(&service_clone.signed_task_resps_txs);
+            let task_control_txs = Arc::clone(&service_clone.task_control_txs);
+            let response_buffer = Arc::clone(&service_clone.response_buffer);
+            service_clone
+                .single_task_aggregator(
+                    task_index,
+                    task_created_block,
+                    quorum_numbers,
+                    quorum_threshold_percentages,
+                    time_to_expiry,
+                    restored_signatures,
+                    task_receivers.rx,
+                    task_receivers.control_rx,
+                )
+                .await;
+
+            signed_task_resps_txs.lock().unwrap().remove(&task_index);
+            task_control_txs.lock().unwrap().remove(&task_index);
+            // Waiters still registered here will never get a response
+            response_buffer
+                .lock()
+                .unwrap()
+                .task_result_txs
+                .remove(&task_index);
+            if let Some(task_store) = task_store {
+                let removed = run_blocking(&task_store, move |task_store| {
+                    task_store.remove_task(task_index)
+                })
+                .await;
+                if let Err(e) = removed {
+                    log::error!(
+                        "Failed to remove task {} from task store: {}",
+                        task_index,
+                        e
+                    );
+                }
+            }
+        });
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn single_task_aggregator(
         self,
         task_index: TaskIndex,
@@ -244,7 +834,9 @@ where
         quorum_numbers: Bytes,
         quorum_threshold_percentages: Vec<QuorumThresholdPercentage>,
         time_to_expiry: Duration,
+        restored_signatures: Vec<StoredSignature>,
         mut rx: mpsc::Receiver<SignedTaskResponseDigest>,
+        mut control_rx: mpsc::Receiver<TaskControlMessage>,
     ) {
         let quorum_numbers_vec: Vec<QuorumNum> = bytes_to_quorum_ids(&quorum_numbers);
         let quorum_threshold_percentages_map: HashMap<QuorumNum, QuorumThresholdPercentage> =
@@ -261,16 +853,14 @@ where
         {
             Ok(state) => state,
             Err(e) => {
-                self.aggregated_responses_tx
-                    .send(BlsAggregationServiceResponse {
-                        err: Some(BlsAggregationError::TaskInitializationError(
-                            e.to_string(),
-                            task_index,
-                        )),
+                self.send_aggregated_response(BlsAggregationServiceResponse {
+                    err: Some(BlsAggregationError::TaskInitializationError(
+                        e.to_string(),
                         task_index,
-                        ..Default::default()
-                    })
-                    .unwrap();
+                    )),
+                    task_index,
+                    ..Default::default()
+                });
                 return;
             }
         };
@@ -282,16 +872,14 @@ where
         {
             Ok(state) => state,
             Err(e) => {
-                self.aggregated_responses_tx
-                    .send(BlsAggregationServiceResponse {
-                        err: Some(BlsAggregationError::TaskInitializationError(
-                            e.to_string(),
-                            task_index,
-                        )),
+                self.send_aggregated_response(BlsAggregationServiceResponse {
+                    err: Some(BlsAggregationError::TaskInitializationError(
+                        e.to_string(),
                         task_index,
-                        ..Default::default()
-                    })
-                    .unwrap();
+                    )),
+                    task_index,
+                    ..Default::default()
+                });
                 return;
             }
         };
@@ -306,92 +894,736 @@ where
             .map(|state| state.agg_pubkey_g1.clone())
             .collect();
 
-        let mut aggregated_operators_dict: HashMap<TaskResponseDigest, AggregatedOperators> =
-            HashMap::new();
+        let mut task = SingleTaskState {
+            task_index,
+            task_created_block,
+            quorum_numbers,
+            quorum_threshold_percentages_map,
+            operators_avs_state_dict,
+            total_stake_per_quorum,
+            quorum_apks_g1,
+            aggregated_operators_dict: HashMap::new(),
+            responded_digest: None,
+            improved_response_due: None,
+            operator_signed_digests: HashMap::new(),
+            started_at: Instant::now(),
+        };
 
-        let task_expired_timer = tokio::time::sleep(time_to_expiry);
+        // Signatures restored from the task store were verified before they were journaled
+        for restored_signature in restored_signatures {
+            if !task
+                .operators_avs_state_dict
+                .contains_key(&restored_signature.operator_id)
+            {
+                continue;
+            }
+            let task_response_digest = keccak256(&restored_signature.task_response);
+            let (signature_verification_error_tx, _) = oneshot::channel();
+            let signed_task_response_digest = SignedTaskResponseDigest {
+                task_response: restored_signature.task_response,
+                bls_signature: restored_signature.bls_signature,
+                operator_id: restored_signature.operator_id,
+                signature_verification_error_tx,
+            };
+            if self
+                .aggregate_verified_signature(
+                    &mut task,
+                    signed_task_response_digest,
+                    task_response_digest,
+                )
+                .await
+            {
+                return;
+            }
+        }
 
+        let mut task_expiry_deadline = Instant::now() + time_to_expiry;
+        let task_expired_timer = tokio::time::sleep_until(task_expiry_deadline);
         tokio::pin!(task_expired_timer);
 
-        loop {
+        // Only armed while signatures are buffered for batch verification
+        let mut pending_batch: Vec<SignedTaskResponseDigest> = Vec::new();
+        let batch_timer = tokio::time::sleep(Duration::ZERO);
+        tokio::pin!(batch_timer);
+
+        // Only armed while an improved response is due
+        let improved_response_timer = tokio::time::sleep(Duration::ZERO);
+        tokio::pin!(improved_response_timer);
+
+        loop {
+            if let Some(improved_response_due) = task.improved_response_due {
+                if improved_response_timer.deadline() != improved_response_due {
+                    improved_response_timer
+                        .as_mut()
+                        .reset(improved_response_due);
+                }
+            }
             tokio::select! {
                 Some(signed_task_response_digest) = rx.recv() => {
                     log::debug!("Task received new signed task response digest: {:?}", signed_task_response_digest);
 
-                    let verification_result = self.verify_signature(task_index, &signed_task_response_digest, &operators_avs_state_dict).await;
-
-                    match verification_result {
-                        Ok(task_response_digest) => {
-                            let digest_aggregated_operators = aggregated_operators_dict.entry(task_response_digest)
-                                .or_default();
-
-                            let operator_avs_state = &operators_avs_state_dict[&signed_task_response_digest.operator_id];
-
-                            digest_aggregated_operators.signers_apk_g2.add(&G2Point::from_ark_g2(&operator_avs_state.operator_info.pubkeys.g2_pubkey));
-                            digest_aggregated_operators.signers_agg_sig_g1.add(&signed_task_response_digest.bls_signature);
-                            digest_aggregated_operators.signers_operator_ids_set.insert(signed_task_response_digest.operator_id);
+                    let Some(batch_config) = &self.batch_verification_config else {
+                        if self.process_signed_task_response_digest(&mut task, signed_task_response_digest).await {
+                            return;
+                        }
+                        continue;
+                    };
 
-                            for (quorum_num, stake) in &operator_avs_state.stake_per_quorum {
-                                *digest_aggregated_operators.signers_total_stake_per_quorum.entry(quorum_num.clone()).or_default() += stake;
-                            }
+                    if pending_batch.is_empty() {
+                        batch_timer.as_mut().reset(Instant::now() + batch_config.window);
+                    }
+                    pending_batch.push(signed_task_response_digest);
 
-                            if self.check_if_stake_thresholds_met(&digest_aggregated_operators.signers_total_stake_per_quorum, &total_stake_per_quorum, &quorum_threshold_percentages_map) {
-                                let non_signers_operator_ids: Vec<OperatorId> = operators_avs_state_dict.keys()
-                                    .filter(|&operator_id| !digest_aggregated_operators.signers_operator_ids_set.contains(operator_id))
-                                    .cloned()
-                                    .collect();
-
-                                let non_signers_g1_pubkeys: Vec<G1Point> = non_signers_operator_ids.iter()
-                                    .map(|operator_id| G1Point::from_ark_g1(&operators_avs_state_dict[operator_id].operator_info.pubkeys.g1_pubkey))
-                                    .collect();
-
-                                let indices = match self.avs_registry_service.get_check_signatures_indices(task_created_block.into(), quorum_numbers.clone(), non_signers_operator_ids.clone()).await {
-                                    Ok(indices) => indices,
-                                    Err(e) => {
-                                        self.aggregated_responses_tx.send(BlsAggregationServiceResponse {
-                                            err: Some(BlsAggregationError::TaskInitializationError(format!("Failed to get check signatures indices: {}", e), task_index)),
-                                            task_index,
-                                            ..Default::default()
-                                        }).unwrap();
-                                        return;
-                                    }
-                                };
-
-                                self.aggregated_responses_tx.send(BlsAggregationServiceResponse {
-                                    err: None,
+                    if pending_batch.len() >= batch_config.max_batch_size
+                        && self.process_signature_batch(&mut task, std::mem::take(&mut pending_batch)).await
+                    {
+                        return;
+                    }
+                }
+                _ = &mut batch_timer, if !pending_batch.is_empty() => {
+                    if self.process_signature_batch(&mut task, std::mem::take(&mut pending_batch)).await {
+                        return;
+                    }
+                }
+                _ = &mut improved_response_timer, if task.improved_response_due.is_some() => {
+                    task.improved_response_due = None;
+                    self.send_improved_response(&task).await;
+                }
+                Some(control_message) = control_rx.recv() => {
+                    match control_message {
+                        TaskControlMessage::Cancel(ack_tx) => {
+                            log::debug!("Cancelling task: {:?}", task_index);
+                            // A task that already responded keeps its response valid
+                            if task.responded_digest.is_none() {
+                                if let Some(metrics) = &self.metrics {
+                                    metrics.task_cancelled();
+                                }
+                                self.send_aggregated_response(BlsAggregationServiceResponse {
+                                    err: Some(BlsAggregationError::TaskCancelledError(task_index)),
                                     task_index,
-                                    task_response: signed_task_response_digest.task_response,
-                                    task_response_digest,
-                                    non_signers_pubkeys_g1: non_signers_g1_pubkeys,
-                                    quorum_apks_g1: quorum_apks_g1.clone(),
-                                    signers_apk_g2: digest_aggregated_operators.signers_apk_g2.clone(),
-                                    signers_agg_sig_g1: digest_aggregated_operators.signers_agg_sig_g1.clone(),
-                                    non_signer_quorum_bitmap_indices: indices.nonSignerQuorumBitmapIndices,
-                                    quorum_apk_indices: indices.quorumApkIndices,
-                                    total_stake_indices: indices.totalStakeIndices,
-                                    non_signer_stake_indices: indices.nonSignerStakeIndices,
-                                }).unwrap();
-
-                                return;
+                                    ..Default::default()
+                                });
+                            }
+                            let _ = ack_tx.send(());
+                            return;
+                        }
+                        TaskControlMessage::ExtendExpiry(extension, ack_tx) => {
+                            task_expiry_deadline += extension;
+                            task_expired_timer.as_mut().reset(task_expiry_deadline);
+                            if let Some(task_store) = &self.task_store {
+                                let time_remaining = task_expiry_deadline.saturating_duration_since(Instant::now());
+                                let expires_at_ms = unix_millis(SystemTime::now() + time_remaining);
+                                let updated = run_blocking(task_store, move |task_store| {
+                                    task_store.update_task_expiry(task_index, expires_at_ms)
+                                })
+                                .await;
+                                if let Err(e) = updated {
+                                    log::error!("Failed to store extended expiry for task {}: {}", task_index, e);
+                                }
                             }
+                            let _ = ack_tx.send(());
                         }
-                        Err(err) => {
-                            signed_task_response_digest.signature_verification_error_tx.send(Err(err)).unwrap();
+                        TaskControlMessage::GetStatus(status_tx) => {
+                            let _ = status_tx.send(task.status(task_expiry_deadline));
                         }
                     }
                 }
                 _ = &mut task_expired_timer => {
-                    self.aggregated_responses_tx.send(BlsAggregationServiceResponse {
+                    if task.responded_digest.is_some() {
+                        log::debug!("Stopping continued aggregation for task: {:?}", task_index);
+                        if task.improved_response_due.take().is_some() {
+                            self.send_improved_response(&task).await;
+                        }
+                        return;
+                    }
+                    let digest_reports = task.digest_reports();
+                    if let Some(metrics) = &self.metrics {
+                        metrics.task_expired();
+                        if let Some(best_digest_report) = digest_reports.first() {
+                            for (quorum_num, percentage) in &best_digest_report.signed_stake_percentage_per_quorum {
+                                metrics.signed_stake(quorum_num, *percentage);
+                            }
+                        }
+                    }
+                    self.send_aggregated_response(BlsAggregationServiceResponse {
                         err: Some(BlsAggregationError::TaskExpiredError(task_index)),
                         task_index,
+                        digest_reports,
                         ..Default::default()
-                    }).unwrap();
+                    });
                     return;
                 }
             }
         }
     }
 
+    /// Verifies a single signed task response and aggregates it if valid.
+    ///
+    /// Returns `true` once the task has completed and its aggregator should stop.
+    async fn process_signed_task_response_digest(
+        &self,
+        task: &mut SingleTaskState,
+        signed_task_response_digest: SignedTaskResponseDigest,
+    ) -> bool {
+        let verification_result = self
+            .verify_signature(
+                task.task_index,
+                &signed_task_response_digest,
+                &task.operators_avs_state_dict,
+            )
+            .await;
+
+        match verification_result {
+            Ok(task_response_digest) => {
+                if let Err(err) = self.check_first_signature_from_operator(
+                    task,
+                    &signed_task_response_digest,
+                    task_response_digest,
+                ) {
+                    let _ = signed_task_response_digest
+                        .signature_verification_error_tx
+                        .send(Err(err));
+                    return false;
+                }
+                self.journal_signature(task.task_index, &signed_task_response_digest)
+                    .await;
+                self.aggregate_verified_signature(
+                    task,
+                    signed_task_response_digest,
+                    task_response_digest,
+                )
+                .await
+            }
+            Err(err) => {
+                let _ = signed_task_response_digest
+                    .signature_verification_error_tx
+                    .send(Err(err));
+                false
+            }
+        }
+    }
+
+    /// Verifies a buffered batch of signed task responses with a single multi-pairing and
+    /// aggregates the valid ones in arrival order.
+    ///
+    /// If the batch check fails, the batch is bisected to find the offending signatures, so
+    /// the extra pairings are only paid when a bad signature is actually present.
+    ///
+    /// Returns `true` once the task has completed and its aggregator should stop.
+    async fn process_signature_batch(
+        &self,
+        task: &mut SingleTaskState,
+        batch: Vec<SignedTaskResponseDigest>,
+    ) -> bool {
+        log::debug!(
+            "Verifying batch of {} signatures for task index: {}",
+            batch.len(),
+            task.task_index
+        );
+
+        let mut candidates = Vec::with_capacity(batch.len());
+        let mut entries = Vec::with_capacity(batch.len());
+        for signed_task_response_digest in batch {
+            let Some(operator_avs_state) = task
+                .operators_avs_state_dict
+                .get(&signed_task_response_digest.operator_id)
+            else {
+                let err = BlsAggregationError::OperatorNotPartOfTaskQuorumError(
+                    signed_task_response_digest.operator_id,
+                    task.task_index,
+                );
+                let _ = signed_task_response_digest
+                    .signature_verification_error_tx
+                    .send(Err(err));
+                continue;
+            };
+
+            let task_response_digest = keccak256(&signed_task_response_digest.task_response);
+            entries.push((
+                signed_task_response_digest.bls_signature.clone(),
+                G2Point::from_ark_g2(&operator_avs_state.operator_info.pubkeys.g2_pubkey),
+                task_response_digest.0,
+            ));
+            candidates.push((signed_task_response_digest, task_response_digest));
+        }
+
+        // The pairings are CPU bound, so they run off the async executor
+        let batch_len = entries.len();
+        let _verification_permit = self
+            .verification_permits
+            .acquire()
+            .await
+            .expect("verification semaphore is never closed");
+        let valid = tokio::task::spawn_blocking(move || {
+            let mut valid = vec![false; entries.len()];
+            mark_valid_signatures(&entries, &mut valid);
+            valid
+        })
+        .await
+        .unwrap_or_else(|e| {
+            log::error!("Signature batch verification task failed: {}", e);
+            vec![false; batch_len]
+        });
+
+        let mut task_completed = false;
+        for ((signed_task_response_digest, task_response_digest), is_valid) in
+            candidates.into_iter().zip(valid)
+        {
+            if !is_valid {
+                let _ = signed_task_response_digest
+                    .signature_verification_error_tx
+                    .send(Err(BlsAggregationError::IncorrectSignatureError));
+            } else if task_completed {
+                // The response for this task has already been sent, so later signatures in
+                // the batch are valid but no longer needed.
+                let _ = signed_task_response_digest
+                    .signature_verification_error_tx
+                    .send(Ok(()));
+            } else if let Err(err) = self.check_first_signature_from_operator(
+                task,
+                &signed_task_response_digest,
+                task_response_digest,
+            ) {
+                let _ = signed_task_response_digest
+                    .signature_verification_error_tx
+                    .send(Err(err));
+            } else {
+                self.journal_signature(task.task_index, &signed_task_response_digest)
+                    .await;
+                task_completed = self
+                    .aggregate_verified_signature(
+                        task,
+                        signed_task_response_digest,
+                        task_response_digest,
+                    )
+                    .await;
+            }
+        }
+
+        task_completed
+    }
+
+    /// Adds a verified signature to the aggregate for its digest and sends the aggregated
+    /// response once the stake thresholds are met. With continued aggregation enabled, an
+    /// updated response is sent for every further signature on the responded digest.
+    ///
+    /// Returns `true` once the task has completed and its aggregator should stop.
+    async fn aggregate_verified_signature(
+        &self,
+        task: &mut SingleTaskState,
+        signed_task_response_digest: SignedTaskResponseDigest,
+        task_response_digest: TaskResponseDigest,
+    ) -> bool {
+        let task_index = task.task_index;
+        task.operator_signed_digests.insert(
+            signed_task_response_digest.operator_id,
+            OperatorSignedDigest {
+                task_response: signed_task_response_digest.task_response.clone(),
+                task_response_digest,
+                bls_signature: signed_task_response_digest.bls_signature.clone(),
+            },
+        );
+        let digest_aggregated_operators = task
+            .aggregated_operators_dict
+            .entry(task_response_digest)
+            .or_default();
+
+        let operator_avs_state =
+            &task.operators_avs_state_dict[&signed_task_response_digest.operator_id];
+
+        digest_aggregated_operators
+            .signers_apk_g2
+            .add(&G2Point::from_ark_g2(
+                &operator_avs_state.operator_info.pubkeys.g2_pubkey,
+            ));
+        digest_aggregated_operators
+            .signers_agg_sig_g1
+            .add(&signed_task_response_digest.bls_signature);
+        digest_aggregated_operators
+            .signers_operator_ids_set
+            .insert(signed_task_response_digest.operator_id);
+
+        for (quorum_num, stake) in &operator_avs_state.stake_per_quorum {
+            *digest_aggregated_operators
+                .signers_total_stake_per_quorum
+                .entry(quorum_num.clone())
+                .or_default() += stake;
+        }
+
+        let _ = signed_task_response_digest
+            .signature_verification_error_tx
+            .send(Ok(()));
+
+        match task.responded_digest {
+            Some(responded_digest) if responded_digest != task_response_digest => return false,
+            Some(_) => {
+                // Continued aggregation: full participation is sent right away, while other
+                // improvements are sent together once the response interval has passed
+                if self.has_full_participation(task, &task_response_digest) {
+                    task.improved_response_due = None;
+                    self.send_improved_response(task).await;
+                    return true;
+                }
+                if task.improved_response_due.is_none() {
+                    let response_interval = self
+                        .continued_aggregation_config
+                        .as_ref()
+                        .map(|config| config.response_interval)
+                        .unwrap_or_default();
+                    task.improved_response_due = Some(Instant::now() + response_interval);
+                }
+                return false;
+            }
+            None => {}
+        }
+
+        let signers_total_stake_per_quorum =
+            &task.aggregated_operators_dict[&task_response_digest].signers_total_stake_per_quorum;
+        if !self.check_if_stake_thresholds_met(
+            signers_total_stake_per_quorum,
+            &task.total_stake_per_quorum,
+            &task.quorum_threshold_percentages_map,
+        ) {
+            return false;
+        }
+
+        if let Err(e) = self.send_digest_response(task, task_response_digest).await {
+            self.send_aggregated_response(BlsAggregationServiceResponse {
+                err: Some(BlsAggregationError::TaskInitializationError(
+                    format!("Failed to get check signatures indices: {}", e),
+                    task_index,
+                )),
+                task_index,
+                ..Default::default()
+            });
+            return true;
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.task_completed(task.started_at.elapsed().as_secs_f64());
+            for (quorum_num, total_stake) in &task.total_stake_per_quorum {
+                let signed_stake = signers_total_stake_per_quorum
+                    .get(quorum_num)
+                    .copied()
+                    .unwrap_or_default();
+                metrics.signed_stake(quorum_num, stake_percentage(signed_stake, *total_stake));
+            }
+        }
+        task.responded_digest = Some(task_response_digest);
+
+        self.continued_aggregation_config.is_none()
+            || self.has_full_participation(task, &task_response_digest)
+    }
+
+    /// Whether `full_participation_percentage` of every quorum signed the digest, with
+    /// continued aggregation enabled.
+    fn has_full_participation(
+        &self,
+        task: &SingleTaskState,
+        task_response_digest: &TaskResponseDigest,
+    ) -> bool {
+        let Some(continued_aggregation_config) = &self.continued_aggregation_config else {
+            return false;
+        };
+        let full_participation_percentages_map = task
+            .quorum_threshold_percentages_map
+            .keys()
+            .map(|quorum_num| {
+                (
+                    quorum_num.clone(),
+                    continued_aggregation_config
+                        .full_participation_percentage
+                        .clone(),
+                )
+            })
+            .collect();
+        self.check_if_stake_thresholds_met(
+            &task.aggregated_operators_dict[task_response_digest].signers_total_stake_per_quorum,
+            &task.total_stake_per_quorum,
+            &full_participation_percentages_map,
+        )
+    }
+
+    /// Sends the response for the responded digest with the signatures aggregated since the
+    /// last response. A failure is logged, and the signatures are included in the next
+    /// improved response instead.
+    async fn send_improved_response(&self, task: &SingleTaskState) {
+        let Some(responded_digest) = task.responded_digest else {
+            return;
+        };
+        if let Err(e) = self.send_digest_response(task, responded_digest).await {
+            log::warn!(
+                "Failed to send improved response for task {}: {}",
+                task.task_index,
+                e
+            );
+        }
+    }
+
+    /// Sends the aggregated response for a digest, with the indices of its non-signers.
+    async fn send_digest_response(
+        &self,
+        task: &SingleTaskState,
+        task_response_digest: TaskResponseDigest,
+    ) -> Result<(), AvsError> {
+        let digest_aggregated_operators = &task.aggregated_operators_dict[&task_response_digest];
+        let non_signers_operator_ids: Vec<OperatorId> = task
+            .operators_avs_state_dict
+            .keys()
+            .filter(|&operator_id| {
+                !digest_aggregated_operators
+                    .signers_operator_ids_set
+                    .contains(operator_id)
+            })
+            .cloned()
+            .collect();
+
+        let non_signers_g1_pubkeys: Vec<G1Point> = non_signers_operator_ids
+            .iter()
+            .map(|operator_id| {
+                G1Point::from_ark_g1(
+                    &task.operators_avs_state_dict[operator_id]
+                        .operator_info
+                        .pubkeys
+                        .g1_pubkey,
+                )
+            })
+            .collect();
+
+        let indices = self
+            .avs_registry_service
+            .get_check_signatures_indices(
+                task.task_created_block.into(),
+                task.quorum_numbers.clone(),
+                non_signers_operator_ids,
+            )
+            .await?;
+
+        self.send_aggregated_response(BlsAggregationServiceResponse {
+            err: None,
+            task_index: task.task_index,
+            task_response: task.task_response(&task_response_digest),
+            task_response_digest,
+            non_signers_pubkeys_g1: non_signers_g1_pubkeys,
+            quorum_apks_g1: task.quorum_apks_g1.clone(),
+            signers_apk_g2: digest_aggregated_operators.signers_apk_g2.clone(),
+            signers_agg_sig_g1: digest_aggregated_operators.signers_agg_sig_g1.clone(),
+            non_signer_quorum_bitmap_indices: indices.nonSignerQuorumBitmapIndices,
+            quorum_apk_indices: indices.quorumApkIndices,
+            total_stake_indices: indices.totalStakeIndices,
+            non_signer_stake_indices: indices.nonSignerStakeIndices,
+            digest_reports: Vec::new(),
+        });
+        Ok(())
+    }
+
+    /// Hands a signature to its task aggregator and waits for the verification result.
+    async fn send_signature_to_task(
+        &self,
+        task_index: TaskIndex,
+        task_response: TaskResponse,
+        bls_signature: Signature,
+        operator_id: OperatorId,
+    ) -> Result<(), BlsAggregationError> {
+        let tx_opt = {
+            let task_resps_txs = self.signed_task_resps_txs.lock().unwrap();
+            task_resps_txs.get(&task_index).cloned()
+        };
+
+        if let Some(tx) = tx_opt {
+            let (tx_res, rx_res) = oneshot::channel();
+            let send_result = tx
+                .send(SignedTaskResponseDigest {
+                    task_response,
+                    bls_signature,
+                    operator_id,
+                    signature_verification_error_tx: tx_res,
+                })
+                .await;
+
+            // The task may have finished since its sender was looked up
+            send_result.map_err(|_| {
+                self.finished_task_error(task_index).unwrap_or(
+                    BlsAggregationError::ProcessNewSignature(
+                        "Failed to send signed task response digest".to_string(),
+                        task_index,
+                    ),
+                )
+            })?;
+
+            rx_res.await.map_err(|_| {
+                self.finished_task_error(task_index).unwrap_or(
+                    BlsAggregationError::ProcessNewSignature(
+                        "Failed to receive signature verification result".to_string(),
+                        task_index,
+                    ),
+                )
+            })?
+        } else {
+            Err(self.finished_task_error(task_index).unwrap_or(
+                BlsAggregationError::TaskInitializationError(
+                    "Task not initialized".to_string(),
+                    task_index,
+                ),
+            ))
+        }
+    }
+
+    /// Returns why a task that is no longer aggregating stopped accepting signatures, if it
+    /// expired, was cancelled or met its thresholds recently enough to be remembered.
+    fn finished_task_error(&self, task_index: TaskIndex) -> Option<BlsAggregationError> {
+        let response_buffer = self.response_buffer.lock().unwrap();
+        let (_, outcome) = response_buffer
+            .task_outcomes
+            .iter()
+            .find(|(finished_task_index, _)| *finished_task_index == task_index)?;
+        match outcome {
+            None => Some(BlsAggregationError::TaskCompletedError(task_index)),
+            Some(
+                err @ (BlsAggregationError::TaskExpiredError(_)
+                | BlsAggregationError::TaskCancelledError(_)),
+            ) => Some(err.clone()),
+            Some(_) => None,
+        }
+    }
+
+    /// Rejects a verified signature from an operator that already signed the task. If the
+    /// operator signed a different digest, the pair is recorded as equivocation evidence.
+    fn check_first_signature_from_operator(
+        &self,
+        task: &SingleTaskState,
+        signed_task_response_digest: &SignedTaskResponseDigest,
+        task_response_digest: TaskResponseDigest,
+    ) -> Result<(), BlsAggregationError> {
+        let operator_id = signed_task_response_digest.operator_id;
+        let Some(first) = task.operator_signed_digests.get(&operator_id) else {
+            return Ok(());
+        };
+
+        if first.task_response_digest == task_response_digest {
+            return Err(BlsAggregationError::DuplicateSignatureError(
+                operator_id,
+                task.task_index,
+            ));
+        }
+
+        log::warn!(
+            "Operator {} signed conflicting responses for task {}",
+            operator_id,
+            task.task_index
+        );
+        let mut equivocation_evidence = self.equivocation_evidence.lock().unwrap();
+        if equivocation_evidence.len() == EQUIVOCATION_EVIDENCE_CAPACITY {
+            equivocation_evidence.pop_front();
+        }
+        equivocation_evidence.push_back(EquivocationEvidence {
+            task_index: task.task_index,
+            operator_id,
+            first: first.clone(),
+            second: OperatorSignedDigest {
+                task_response: signed_task_response_digest.task_response.clone(),
+                task_response_digest,
+                bls_signature: signed_task_response_digest.bls_signature.clone(),
+            },
+        });
+        Err(BlsAggregationError::EquivocationError(
+            operator_id,
+            task.task_index,
+        ))
+    }
+
+    /// Buffers the response, resolves callers waiting on its task and broadcasts it.
+    /// Having no subscribers is not an error, as late subscribers can still read the buffer.
+    fn send_aggregated_response(&self, response: BlsAggregationServiceResponse) {
+        let mut response_buffer = self.response_buffer.lock().unwrap();
+
+        if let Some(task_result_txs) = response_buffer.task_result_txs.remove(&response.task_index)
+        {
+            for tx in task_result_txs {
+                let _ = tx.send(response.clone());
+            }
+        }
+
+        if response_buffer.recent_responses.len() == RECENT_RESPONSES_CAPACITY {
+            response_buffer.recent_responses.pop_front();
+        }
+        response_buffer.recent_responses.push_back(response.clone());
+
+        match response_buffer
+            .task_outcomes
+            .iter_mut()
+            .find(|(task_index, _)| *task_index == response.task_index)
+        {
+            Some((_, outcome)) => *outcome = response.err.clone(),
+            None => {
+                if response_buffer.task_outcomes.len() == TASK_OUTCOMES_CAPACITY {
+                    response_buffer.task_outcomes.pop_front();
+                }
+                response_buffer
+                    .task_outcomes
+                    .push_back((response.task_index, response.err.clone()));
+            }
+        }
+
+        if self.aggregated_responses_tx.send(response).is_err() {
+            log::debug!("No subscribers for aggregated responses, response kept in buffer");
+        }
+    }
+
+    async fn send_task_control_message<R>(
+        &self,
+        task_index: TaskIndex,
+        control_message: impl FnOnce(oneshot::Sender<R>) -> TaskControlMessage,
+    ) -> Result<R, BlsAggregationError> {
+        let control_tx = self
+            .task_control_txs
+            .lock()
+            .unwrap()
+            .get(&task_index)
+            .cloned()
+            .ok_or(BlsAggregationError::TaskInitializationError(
+                "Task not initialized".to_string(),
+                task_index,
+            ))?;
+
+        let (tx, rx) = oneshot::channel();
+        let task_closed = || {
+            BlsAggregationError::ProcessNewSignature(
+                "Task aggregator is no longer running".to_string(),
+                task_index,
+            )
+        };
+        control_tx
+            .send(control_message(tx))
+            .await
+            .map_err(|_| task_closed())?;
+        rx.await.map_err(|_| task_closed())
+    }
+
+    /// Records an accepted signature in the task store, if one is configured. Failures are
+    /// logged rather than surfaced, as they only affect recovery after a restart.
+    async fn journal_signature(
+        &self,
+        task_index: TaskIndex,
+        signed_task_response_digest: &SignedTaskResponseDigest,
+    ) {
+        let Some(task_store) = &self.task_store else {
+            return;
+        };
+        let stored_signature = StoredSignature {
+            task_response: signed_task_response_digest.task_response.clone(),
+            bls_signature: signed_task_response_digest.bls_signature.clone(),
+            operator_id: signed_task_response_digest.operator_id,
+        };
+        let stored = run_blocking(task_store, move |task_store| {
+            task_store.store_signature(task_index, &stored_signature)
+        })
+        .await;
+        if let Err(e) = stored {
+            log::error!("Failed to journal signature for task {}: {}", task_index, e);
+        }
+    }
+
     async fn verify_signature(
         &self,
         task_index: TaskIndex,
@@ -409,15 +1641,25 @@ where
 
         let task_response_digest = keccak256(&signed_task_response_digest.task_response);
 
-        let operator_g2_pubkey = &operator_avs_state.operator_info.pubkeys.g2_pubkey;
+        let operator_g2_pubkey =
+            G2Point::from_ark_g2(&operator_avs_state.operator_info.pubkeys.g2_pubkey);
+        let bls_signature = signed_task_response_digest.bls_signature.clone();
 
-        let signature_verified = signed_task_response_digest
-            .bls_signature
-            .verify(
-                &G2Point::from_ark_g2(operator_g2_pubkey),
-                &task_response_digest,
-            )
-            .map_err(|_| BlsAggregationError::IncorrectSignatureError)?;
+        // The pairings are CPU bound, so they run off the async executor
+        let _verification_permit = self
+            .verification_permits
+            .acquire()
+            .await
+            .expect("verification semaphore is never closed");
+        let signature_verified = tokio::task::spawn_blocking(move || {
+            bls_signature.verify(&operator_g2_pubkey, &task_response_digest)
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Signature verification task failed: {}", e);
+            BlsAggregationError::IncorrectSignatureError
+        })?
+        .map_err(|_| BlsAggregationError::IncorrectSignatureError)?;
 
         if signature_verified {
             Ok(task_response_digest)
@@ -450,3 +1692,934 @@ where
         true
     }
 }
+
+/// Percentage of `total_stake` that `signed_stake` represents, to two decimal places.
+fn stake_percentage(signed_stake: U256, total_stake: U256) -> f64 {
+    if total_stake.is_zero() {
+        return 0.0;
+    }
+    let basis_points = signed_stake.saturating_mul(U256::from(10_000)) / total_stake;
+    u64::try_from(basis_points).unwrap_or(u64::MAX) as f64 / 100.0
+}
+
+fn into_task_result(
+    response: BlsAggregationServiceResponse,
+) -> Result<BlsAggregationServiceResponse, BlsAggregationError> {
+    match response.err {
+        Some(err) => Err(err),
+        None => Ok(response),
+    }
+}
+
+/// Checks that every quorum in the `quorum_numbers` bitmap has exactly one threshold and that
+/// the thresholds are reachable, so a task is never started that could not complete.
+fn validate_quorum_thresholds(
+    task_index: TaskIndex,
+    quorum_numbers: &Bytes,
+    quorum_threshold_percentages: &[QuorumThresholdPercentage],
+) -> Result<(), BlsAggregationError> {
+    let invalid = |e: String| BlsAggregationError::InvalidQuorumThresholdsError(e, task_index);
+
+    if quorum_numbers.len() > 32 {
+        return Err(invalid(format!(
+            "quorum bitmap of {} bytes is longer than 32 bytes",
+            quorum_numbers.len()
+        )));
+    }
+    let quorum_ids = bytes_to_quorum_ids(quorum_numbers);
+    if quorum_ids.is_empty() {
+        return Err(invalid("no quorum numbers given".to_string()));
+    }
+    if quorum_ids.len() != quorum_threshold_percentages.len() {
+        return Err(invalid(format!(
+            "{} quorum numbers but {} threshold percentages",
+            quorum_ids.len(),
+            quorum_threshold_percentages.len()
+        )));
+    }
+
+    for (quorum_id, threshold) in quorum_ids.iter().zip(quorum_threshold_percentages) {
+        if threshold.0 > 100 {
+            return Err(invalid(format!(
+                "threshold {}% for quorum {} is above 100%",
+                threshold.0, quorum_id.0
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Marks which entries of a signature batch are valid, bisecting the batch whenever the
+/// combined check fails.
+fn mark_valid_signatures(entries: &[(Signature, G2Point, [u8; 32])], valid: &mut [bool]) {
+    if entries.is_empty() {
+        return;
+    }
+    if Signature::verify_batch(entries).unwrap_or(false) {
+        valid.fill(true);
+        return;
+    }
+    if entries.len() == 1 {
+        return;
+    }
+
+    let mid = entries.len() / 2;
+    let (left_entries, right_entries) = entries.split_at(mid);
+    let (left_valid, right_valid) = valid.split_at_mut(mid);
+    mark_valid_signatures(left_entries, left_valid);
+    mark_valid_signatures(right_entries, right_valid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::bls::KeyPair;
+    use crate::types::{
+        quorum_ids_to_bitmap, AvsError, OperatorInfo, OperatorPubkeys, QuorumAvsState,
+    };
+    use alloy_primitives::{FixedBytes, B256};
+    use eigen_contracts::OperatorStateRetriever;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use storage::InMemoryTaskStore;
+
+    const STAKE_PER_OPERATOR: u64 = 100;
+
+    /// Serves the same operators at every block, each with equal stake in quorum 0.
+    #[derive(Clone)]
+    struct MockAvsRegistryService {
+        operators_avs_state: HashMap<OperatorId, OperatorAvsState>,
+        quorums_avs_state: HashMap<QuorumNum, QuorumAvsState>,
+        check_signatures_indices_calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl AvsRegistryServiceTrait for MockAvsRegistryService {
+        async fn get_operators_avs_state_at_block(
+            &self,
+            _quorum_numbers: Bytes,
+            _block_number: u64,
+        ) -> Result<HashMap<OperatorId, OperatorAvsState>, AvsError> {
+            Ok(self.operators_avs_state.clone())
+        }
+
+        async fn get_quorums_avs_state_at_block(
+            &self,
+            _quorum_numbers: Bytes,
+            _block_number: u64,
+        ) -> Result<HashMap<QuorumNum, QuorumAvsState>, AvsError> {
+            Ok(self.quorums_avs_state.clone())
+        }
+
+        async fn get_check_signatures_indices(
+            &self,
+            _reference_block_number: u64,
+            _quorum_numbers: Bytes,
+            _non_signer_operator_ids: Vec<FixedBytes<32>>,
+        ) -> Result<OperatorStateRetriever::CheckSignaturesIndices, AvsError> {
+            self.check_signatures_indices_calls
+                .fetch_add(1, Ordering::SeqCst);
+            Ok(OperatorStateRetriever::CheckSignaturesIndices {
+                nonSignerQuorumBitmapIndices: vec![],
+                quorumApkIndices: vec![],
+                totalStakeIndices: vec![],
+                nonSignerStakeIndices: vec![],
+            })
+        }
+    }
+
+    fn setup(num_operators: u64) -> (Vec<(OperatorId, KeyPair)>, MockAvsRegistryService) {
+        let operators: Vec<(OperatorId, KeyPair)> = (1..=num_operators)
+            .map(|i| (B256::from(U256::from(i)), KeyPair::gen_random()))
+            .collect();
+        let operators_avs_state = operators
+            .iter()
+            .map(|(operator_id, keypair)| {
+                let operator_avs_state = OperatorAvsState {
+                    operator_id: *operator_id,
+                    operator_info: OperatorInfo {
+                        socket: "localhost:8080".to_string(),
+                        pubkeys: OperatorPubkeys {
+                            g1_pubkey: keypair.get_pub_key_g1().to_ark_g1(),
+                            g2_pubkey: keypair.get_pub_key_g2().to_ark_g2(),
+                        },
+                    },
+                    stake_per_quorum: HashMap::from([(
+                        QuorumNum(0),
+                        U256::from(STAKE_PER_OPERATOR),
+                    )]),
+                    block_number: 1,
+                };
+                (*operator_id, operator_avs_state)
+            })
+            .collect();
+        let quorums_avs_state = HashMap::from([(
+            QuorumNum(0),
+            QuorumAvsState {
+                quorum_number: QuorumNum(0),
+                total_stake: U256::from(STAKE_PER_OPERATOR * num_operators),
+                agg_pubkey_g1: G1Point::generator(),
+                block_number: 1,
+            },
+        )]);
+
+        (
+            operators,
+            MockAvsRegistryService {
+                operators_avs_state,
+                quorums_avs_state,
+                check_signatures_indices_calls: Arc::new(AtomicUsize::new(0)),
+            },
+        )
+    }
+
+    async fn initialize_task(
+        service: &BlsAggregatorService<MockAvsRegistryService>,
+        task_index: TaskIndex,
+        threshold_percentage: u8,
+        time_to_expiry: Duration,
+    ) {
+        service
+            .initialize_new_task(
+                task_index,
+                1,
+                quorum_ids_to_bitmap(&[QuorumNum(0)]),
+                vec![QuorumThresholdPercentage(threshold_percentage)],
+                time_to_expiry,
+            )
+            .await
+            .unwrap();
+    }
+
+    fn sign(keypair: &KeyPair, task_response: &TaskResponse) -> Signature {
+        keypair.sign_message(&keccak256(task_response).0)
+    }
+
+    /// Fails to store tasks while `fail_store_task` is set.
+    #[derive(Default)]
+    struct FlakyTaskStore {
+        fail_store_task: AtomicBool,
+        tasks: InMemoryTaskStore,
+    }
+
+    impl TaskStore for FlakyTaskStore {
+        fn store_task(&self, task: &StoredTask) -> Result<(), BlsAggregationError> {
+            if self.fail_store_task.load(Ordering::SeqCst) {
+                return Err(BlsAggregationError::StorageError("disk full".to_string()));
+            }
+            self.tasks.store_task(task)
+        }
+
+        fn store_signature(
+            &self,
+            task_index: TaskIndex,
+            signature: &StoredSignature,
+        ) -> Result<(), BlsAggregationError> {
+            self.tasks.store_signature(task_index, signature)
+        }
+
+        fn update_task_expiry(
+            &self,
+            task_index: TaskIndex,
+            expires_at_ms: u64,
+        ) -> Result<(), BlsAggregationError> {
+            self.tasks.update_task_expiry(task_index, expires_at_ms)
+        }
+
+        fn remove_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError> {
+            self.tasks.remove_task(task_index)
+        }
+
+        fn load_tasks(&self) -> Result<Vec<StoredTask>, BlsAggregationError> {
+            self.tasks.load_tasks()
+        }
+    }
+
+    #[test]
+    fn test_validate_quorum_thresholds() {
+        let quorums = |quorum_ids: &[u8]| {
+            quorum_ids_to_bitmap(
+                &quorum_ids
+                    .iter()
+                    .map(|&id| QuorumNum(id))
+                    .collect::<Vec<_>>(),
+            )
+        };
+        let thresholds = |percentages: &[u8]| {
+            percentages
+                .iter()
+                .map(|&percentage| QuorumThresholdPercentage(percentage))
+                .collect::<Vec<_>>()
+        };
+        let is_invalid = |result: Result<(), BlsAggregationError>| {
+            matches!(
+                result,
+                Err(BlsAggregationError::InvalidQuorumThresholdsError(_, 7))
+            )
+        };
+
+        assert!(validate_quorum_thresholds(7, &quorums(&[0]), &thresholds(&[67])).is_ok());
+        assert!(validate_quorum_thresholds(7, &quorums(&[0, 3]), &thresholds(&[0, 100])).is_ok());
+
+        assert!(is_invalid(validate_quorum_thresholds(
+            7,
+            &quorums(&[]),
+            &thresholds(&[])
+        )));
+        assert!(is_invalid(validate_quorum_thresholds(
+            7,
+            &quorums(&[0, 1]),
+            &thresholds(&[67])
+        )));
+        assert!(is_invalid(validate_quorum_thresholds(
+            7,
+            &quorums(&[0]),
+            &thresholds(&[67, 67])
+        )));
+        assert!(is_invalid(validate_quorum_thresholds(
+            7,
+            &quorums(&[0]),
+            &thresholds(&[101])
+        )));
+        assert!(is_invalid(validate_quorum_thresholds(
+            7,
+            &Bytes::from(vec![1u8; 33]),
+            &thresholds(&[67])
+        )));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_initializations_of_a_task_index() {
+        let (_, avs_registry_service) = setup(1);
+        let (tx, _rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service)
+            .with_task_store(Arc::new(InMemoryTaskStore::new()));
+
+        let initializations: Vec<_> = (0..8)
+            .map(|_| {
+                let service = service.clone();
+                tokio::spawn(async move {
+                    service
+                        .initialize_new_task(
+                            1,
+                            1,
+                            quorum_ids_to_bitmap(&[QuorumNum(0)]),
+                            vec![QuorumThresholdPercentage(100)],
+                            Duration::from_secs(10),
+                        )
+                        .await
+                })
+            })
+            .collect();
+
+        let mut initialized = 0;
+        for initialization in initializations {
+            match initialization.await.unwrap() {
+                Ok(()) => initialized += 1,
+                Err(err) => assert!(matches!(
+                    err,
+                    BlsAggregationError::DuplicateTaskIndexError(1)
+                )),
+            }
+        }
+        assert_eq!(initialized, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_task_store_releases_task_index() {
+        let (_, avs_registry_service) = setup(1);
+        let (tx, _rx) = broadcast::channel(10);
+        let task_store = Arc::new(FlakyTaskStore::default());
+        let service =
+            BlsAggregatorService::new(tx, avs_registry_service).with_task_store(task_store.clone());
+
+        task_store.fail_store_task.store(true, Ordering::SeqCst);
+        let result = service
+            .initialize_new_task(
+                1,
+                1,
+                quorum_ids_to_bitmap(&[QuorumNum(0)]),
+                vec![QuorumThresholdPercentage(100)],
+                Duration::from_secs(10),
+            )
+            .await;
+        assert!(matches!(result, Err(BlsAggregationError::StorageError(_))));
+        assert!(service.get_task_status(1).await.is_err());
+
+        task_store.fail_store_task.store(false, Ordering::SeqCst);
+        initialize_task(&service, 1, 100, Duration::from_secs(10)).await;
+        assert!(service.get_task_status(1).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_resume_drops_tasks_with_invalid_thresholds() {
+        let (_, avs_registry_service) = setup(1);
+        let (tx, _rx) = broadcast::channel(10);
+        let task_store = Arc::new(InMemoryTaskStore::new());
+        let stored_task = |task_index, quorum_threshold_percentages| StoredTask {
+            task_index,
+            task_created_block: 1,
+            quorum_numbers: quorum_ids_to_bitmap(&[QuorumNum(0)]),
+            quorum_threshold_percentages,
+            expires_at_ms: unix_millis(SystemTime::now() + Duration::from_secs(10)),
+            signatures: vec![],
+        };
+        task_store
+            .store_task(&stored_task(1, vec![QuorumThresholdPercentage(100)]))
+            .unwrap();
+        task_store
+            .store_task(&stored_task(2, vec![QuorumThresholdPercentage(150)]))
+            .unwrap();
+
+        let service =
+            BlsAggregatorService::new(tx, avs_registry_service).with_task_store(task_store.clone());
+        assert_eq!(service.resume_stored_tasks().await.unwrap(), vec![1]);
+        let stored_task_indices: Vec<TaskIndex> = task_store
+            .load_tasks()
+            .unwrap()
+            .iter()
+            .map(|task| task.task_index)
+            .collect();
+        assert_eq!(stored_task_indices, vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_task_status_extend_expiry_and_cancel() {
+        let (operators, avs_registry_service) = setup(3);
+        let (tx, mut rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+        initialize_task(&service, 1, 100, Duration::from_millis(300)).await;
+
+        let task_response: TaskResponse = vec![1];
+        let (operator_id, keypair) = &operators[0];
+        service
+            .process_new_signature(
+                1,
+                task_response.clone(),
+                sign(keypair, &task_response),
+                *operator_id,
+            )
+            .await
+            .unwrap();
+
+        let status = service.get_task_status(1).await.unwrap();
+        assert_eq!(status.signers_operator_ids, HashSet::from([*operator_id]));
+        assert_eq!(
+            status.signed_stake_per_quorum[&QuorumNum(0)],
+            U256::from(100)
+        );
+        assert_eq!(
+            status.total_stake_per_quorum[&QuorumNum(0)],
+            U256::from(300)
+        );
+        assert_eq!(
+            status.task_response_digests,
+            vec![keccak256(&task_response)]
+        );
+
+        // The extended task outlives its original expiry
+        service
+            .extend_task_expiry(1, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(service.get_task_status(1).await.unwrap().time_remaining > Duration::from_secs(10));
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(service.get_task_status(1).await.is_ok());
+
+        service.cancel_task(1).await.unwrap();
+        let response = rx.recv().await.unwrap();
+        assert!(matches!(
+            response.err,
+            Some(BlsAggregationError::TaskCancelledError(1))
+        ));
+        assert!(service.get_task_status(1).await.is_err());
+        assert!(matches!(
+            service.cancel_task(2).await,
+            Err(BlsAggregationError::TaskInitializationError(_, 2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_late_signatures_for_finished_tasks() {
+        let (operators, avs_registry_service) = setup(2);
+        let (tx, mut rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+        let task_response: TaskResponse = vec![1];
+        let submit = |task_index, operator: &(OperatorId, KeyPair)| {
+            service.process_new_signature(
+                task_index,
+                task_response.clone(),
+                sign(&operator.1, &task_response),
+                operator.0,
+            )
+        };
+
+        initialize_task(&service, 1, 50, Duration::from_secs(10)).await;
+        submit(1, &operators[0]).await.unwrap();
+        assert!(rx.recv().await.unwrap().err.is_none());
+        assert!(matches!(
+            submit(1, &operators[1]).await,
+            Err(BlsAggregationError::TaskCompletedError(1))
+        ));
+
+        initialize_task(&service, 2, 100, Duration::from_secs(10)).await;
+        service.cancel_task(2).await.unwrap();
+        rx.recv().await.unwrap();
+        assert!(matches!(
+            submit(2, &operators[0]).await,
+            Err(BlsAggregationError::TaskCancelledError(2))
+        ));
+
+        initialize_task(&service, 3, 100, Duration::from_millis(50)).await;
+        rx.recv().await.unwrap();
+        assert!(matches!(
+            submit(3, &operators[0]).await,
+            Err(BlsAggregationError::TaskExpiredError(3))
+        ));
+
+        assert!(matches!(
+            submit(4, &operators[0]).await,
+            Err(BlsAggregationError::TaskInitializationError(_, 4))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_continued_aggregation_sends_improved_responses() {
+        let (operators, avs_registry_service) = setup(6);
+        let check_signatures_indices_calls =
+            Arc::clone(&avs_registry_service.check_signatures_indices_calls);
+        let (tx, mut rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service)
+            .with_continued_aggregation(ContinuedAggregationConfig {
+                response_interval: Duration::from_millis(100),
+                ..Default::default()
+            });
+        initialize_task(&service, 1, 50, Duration::from_secs(10)).await;
+
+        let task_response: TaskResponse = vec![1];
+        let sign_task = |operator: &(OperatorId, KeyPair)| {
+            let service = service.clone();
+            let (operator_id, signature) = (operator.0, sign(&operator.1, &task_response));
+            let task_response = task_response.clone();
+            async move {
+                service
+                    .process_new_signature(1, task_response, signature, operator_id)
+                    .await
+                    .unwrap()
+            }
+        };
+
+        for operator in &operators[..3] {
+            sign_task(operator).await;
+        }
+        assert_eq!(rx.recv().await.unwrap().non_signers_pubkeys_g1.len(), 3);
+
+        // Signatures arriving within the response interval are sent in a single response
+        sign_task(&operators[3]).await;
+        sign_task(&operators[4]).await;
+        let response = rx.recv().await.unwrap();
+        assert!(response.err.is_none());
+        assert_eq!(response.non_signers_pubkeys_g1.len(), 1);
+        assert_eq!(check_signatures_indices_calls.load(Ordering::SeqCst), 2);
+
+        // Full participation is sent right away and stops the task
+        sign_task(&operators[5]).await;
+        let response = rx.recv().await.unwrap();
+        assert!(response.non_signers_pubkeys_g1.is_empty());
+        assert_eq!(check_signatures_indices_calls.load(Ordering::SeqCst), 3);
+        assert!(service.get_task_status(1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signature_checks_wait_for_a_verification_permit() {
+        let (operators, avs_registry_service) = setup(1);
+        let (tx, _rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service)
+            .with_max_concurrent_verifications(1);
+        initialize_task(&service, 1, 100, Duration::from_secs(10)).await;
+
+        let permit = service.verification_permits.acquire().await.unwrap();
+        let task_response: TaskResponse = vec![1];
+        let (operator_id, keypair) = &operators[0];
+        let submission = tokio::spawn({
+            let service = service.clone();
+            let signature = sign(keypair, &task_response);
+            let (task_response, operator_id) = (task_response.clone(), *operator_id);
+            async move {
+                service
+                    .process_new_signature(1, task_response, signature, operator_id)
+                    .await
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!submission.is_finished());
+
+        drop(permit);
+        submission.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_verification_isolates_bad_signatures() {
+        let (operators, avs_registry_service) = setup(6);
+        let (tx, mut rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service).with_batch_verification(
+            BatchVerificationConfig {
+                window: Duration::from_millis(200),
+                max_batch_size: 128,
+            },
+        );
+        initialize_task(&service, 1, 50, Duration::from_secs(10)).await;
+
+        // Operators 1 and 4 sign with a key other than their registered one, so the batch
+        // fails as a whole and has to be bisected
+        let task_response: TaskResponse = vec![1];
+        let submissions: Vec<_> = operators
+            .iter()
+            .enumerate()
+            .map(|(i, (operator_id, keypair))| {
+                let signature = if i == 1 || i == 4 {
+                    sign(&KeyPair::gen_random(), &task_response)
+                } else {
+                    sign(keypair, &task_response)
+                };
+                let service = service.clone();
+                let (task_response, operator_id) = (task_response.clone(), *operator_id);
+                tokio::spawn(async move {
+                    service
+                        .process_new_signature(1, task_response, signature, operator_id)
+                        .await
+                })
+            })
+            .collect();
+
+        for (i, submission) in submissions.into_iter().enumerate() {
+            let result = submission.await.unwrap();
+            if i == 1 || i == 4 {
+                assert!(matches!(
+                    result,
+                    Err(BlsAggregationError::IncorrectSignatureError)
+                ));
+            } else {
+                assert!(result.is_ok());
+            }
+        }
+
+        // The first three valid signatures meet the threshold
+        let response = rx.recv().await.unwrap();
+        assert!(response.err.is_none());
+        assert_eq!(response.task_response, task_response);
+        assert_eq!(response.non_signers_pubkeys_g1.len(), 3);
+    }
+
+    /// The gathered series of metric `name`, with the given label value if any.
+    fn gathered_metric(
+        registry: &prometheus::Registry,
+        name: &str,
+        label_value: Option<&str>,
+    ) -> prometheus::proto::Metric {
+        let family = registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == name)
+            .unwrap_or_else(|| panic!("metric {} not gathered", name));
+        family
+            .get_metric()
+            .iter()
+            .find(|metric| match label_value {
+                Some(label_value) => metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_value() == label_value),
+                None => true,
+            })
+            .unwrap_or_else(|| panic!("no {:?} series of metric {}", label_value, name))
+            .clone()
+    }
+
+    #[tokio::test]
+    async fn test_metrics_record_task_outcomes() {
+        let (operators, avs_registry_service) = setup(3);
+        let registry = prometheus::Registry::new();
+        let (tx, _rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service)
+            .with_metrics(BlsAggregationMetrics::new(&registry).unwrap());
+        let task_response: TaskResponse = vec![1];
+        let submit = |task_index, signer: &KeyPair, operator_id| {
+            service.process_new_signature(
+                task_index,
+                task_response.clone(),
+                sign(signer, &task_response),
+                operator_id,
+            )
+        };
+
+        // Task 1 completes with two of three operators, after a duplicate and an incorrect
+        // signature are rejected
+        initialize_task(&service, 1, 60, Duration::from_secs(10)).await;
+        submit(1, &operators[0].1, operators[0].0).await.unwrap();
+        assert!(submit(1, &operators[0].1, operators[0].0).await.is_err());
+        assert!(submit(1, &KeyPair::gen_random(), operators[1].0)
+            .await
+            .is_err());
+        submit(1, &operators[1].1, operators[1].0).await.unwrap();
+        // Completion is recorded by the task aggregator before it stops
+        while service.get_task_status(1).await.is_ok() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(submit(1, &operators[2].1, operators[2].0).await.is_err());
+
+        // Task 2 expires with one of three operators, task 3 is cancelled
+        initialize_task(&service, 2, 60, Duration::from_millis(100)).await;
+        submit(2, &operators[2].1, operators[2].0).await.unwrap();
+        assert!(service.await_task_result(2).await.is_err());
+        initialize_task(&service, 3, 60, Duration::from_secs(10)).await;
+        service.cancel_task(3).await.unwrap();
+
+        let counter = |name, label_value| {
+            gathered_metric(&registry, name, label_value)
+                .get_counter()
+                .get_value()
+        };
+        assert_eq!(
+            counter("eigen_bls_aggregation_tasks_initialized_total", None),
+            3.0
+        );
+        assert_eq!(
+            counter("eigen_bls_aggregation_tasks_completed_total", None),
+            1.0
+        );
+        assert_eq!(
+            counter("eigen_bls_aggregation_tasks_expired_total", None),
+            1.0
+        );
+        assert_eq!(
+            counter("eigen_bls_aggregation_tasks_cancelled_total", None),
+            1.0
+        );
+        assert_eq!(
+            counter("eigen_bls_aggregation_signatures_accepted_total", None),
+            3.0
+        );
+        let rejected = |reason| {
+            counter(
+                "eigen_bls_aggregation_signatures_rejected_total",
+                Some(reason),
+            )
+        };
+        assert_eq!(rejected("duplicate_signature"), 1.0);
+        assert_eq!(rejected("incorrect_signature"), 1.0);
+        assert_eq!(rejected("task_finished"), 1.0);
+
+        let time_to_quorum = gathered_metric(
+            &registry,
+            "eigen_bls_aggregation_time_to_quorum_seconds",
+            None,
+        );
+        assert_eq!(time_to_quorum.get_histogram().get_sample_count(), 1);
+        // The completed and the expired task each record their signed stake
+        let signed_stake = gathered_metric(
+            &registry,
+            "eigen_bls_aggregation_signed_stake_percentage",
+            Some("0"),
+        );
+        assert_eq!(signed_stake.get_histogram().get_sample_count(), 2);
+        assert_eq!(signed_stake.get_histogram().get_sample_sum(), 66.66 + 33.33);
+    }
+
+    #[tokio::test]
+    async fn test_await_task_result() {
+        let (operators, avs_registry_service) = setup(2);
+        let (tx, _) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+        assert!(matches!(
+            service.await_task_result(1).await,
+            Err(BlsAggregationError::TaskInitializationError(_, 1))
+        ));
+
+        initialize_task(&service, 1, 100, Duration::from_secs(10)).await;
+        let waiter = tokio::spawn({
+            let service = service.clone();
+            async move { service.await_task_result(1).await }
+        });
+        let task_response: TaskResponse = vec![1];
+        for (operator_id, keypair) in &operators {
+            service
+                .process_new_signature(
+                    1,
+                    task_response.clone(),
+                    sign(keypair, &task_response),
+                    *operator_id,
+                )
+                .await
+                .unwrap();
+        }
+        assert_eq!(waiter.await.unwrap().unwrap().task_response, task_response);
+        // Finished tasks are answered from the recent responses
+        assert_eq!(
+            service.await_task_result(1).await.unwrap().task_response,
+            task_response
+        );
+
+        initialize_task(&service, 2, 100, Duration::from_millis(50)).await;
+        assert!(matches!(
+            service.await_task_result(2).await,
+            Err(BlsAggregationError::TaskExpiredError(2))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_recent_responses_are_buffered_without_subscribers() {
+        let (_, avs_registry_service) = setup(1);
+        let (tx, rx) = broadcast::channel(10);
+        drop(rx);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+
+        for task_index in 0..RECENT_RESPONSES_CAPACITY as TaskIndex + 5 {
+            service.send_aggregated_response(BlsAggregationServiceResponse {
+                task_index,
+                ..Default::default()
+            });
+        }
+
+        let (recent_responses, mut rx) = service.subscribe_with_recent_responses();
+        assert_eq!(recent_responses.len(), RECENT_RESPONSES_CAPACITY);
+        assert_eq!(recent_responses[0].task_index, 5);
+        service.send_aggregated_response(BlsAggregationServiceResponse {
+            task_index: 200,
+            ..Default::default()
+        });
+        assert_eq!(rx.recv().await.unwrap().task_index, 200);
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_and_equivocating_signatures() {
+        let (operators, avs_registry_service) = setup(3);
+        let (tx, _rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+        initialize_task(&service, 1, 100, Duration::from_secs(10)).await;
+
+        let (operator_id, keypair) = &operators[0];
+        let (first_response, second_response): (TaskResponse, TaskResponse) = (vec![1], vec![2]);
+        service
+            .process_new_signature(
+                1,
+                first_response.clone(),
+                sign(keypair, &first_response),
+                *operator_id,
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            service
+                .process_new_signature(
+                    1,
+                    first_response.clone(),
+                    sign(keypair, &first_response),
+                    *operator_id,
+                )
+                .await,
+            Err(BlsAggregationError::DuplicateSignatureError(id, 1)) if id == *operator_id
+        ));
+        assert!(matches!(
+            service
+                .process_new_signature(
+                    1,
+                    second_response.clone(),
+                    sign(keypair, &second_response),
+                    *operator_id,
+                )
+                .await,
+            Err(BlsAggregationError::EquivocationError(id, 1)) if id == *operator_id
+        ));
+
+        // Neither rejected signature counts towards the task
+        let status = service.get_task_status(1).await.unwrap();
+        assert_eq!(
+            status.signed_stake_per_quorum[&QuorumNum(0)],
+            U256::from(100)
+        );
+
+        let equivocation_evidence = service.take_equivocation_evidence();
+        assert_eq!(equivocation_evidence.len(), 1);
+        assert_eq!(equivocation_evidence[0].operator_id, *operator_id);
+        assert_eq!(
+            equivocation_evidence[0].first.task_response_digest,
+            keccak256(&first_response)
+        );
+        assert_eq!(
+            equivocation_evidence[0].second.task_response_digest,
+            keccak256(&second_response)
+        );
+        assert!(service.take_equivocation_evidence().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_task_reports_stake_per_digest() {
+        let (operators, avs_registry_service) = setup(4);
+        let (tx, _rx) = broadcast::channel(10);
+        let service = BlsAggregatorService::new(tx, avs_registry_service);
+        initialize_task(&service, 1, 100, Duration::from_millis(200)).await;
+
+        let (majority_response, minority_response): (TaskResponse, TaskResponse) =
+            (vec![1], vec![2]);
+        for (i, (operator_id, keypair)) in operators.iter().take(3).enumerate() {
+            let task_response = if i == 0 {
+                &minority_response
+            } else {
+                &majority_response
+            };
+            service
+                .process_new_signature(
+                    1,
+                    task_response.clone(),
+                    sign(keypair, task_response),
+                    *operator_id,
+                )
+                .await
+                .unwrap();
+        }
+
+        assert!(matches!(
+            service.await_task_result(1).await,
+            Err(BlsAggregationError::TaskExpiredError(1))
+        ));
+        let (recent_responses, _) = service.subscribe_with_recent_responses();
+        let digest_reports = &recent_responses[0].digest_reports;
+        assert_eq!(digest_reports.len(), 2);
+
+        // Closest to quorum first
+        assert_eq!(digest_reports[0].task_response, majority_response);
+        assert_eq!(
+            digest_reports[0].task_response_digest,
+            keccak256(&majority_response)
+        );
+        assert_eq!(
+            digest_reports[0].signers_operator_ids,
+            HashSet::from([operators[1].0, operators[2].0])
+        );
+        assert_eq!(
+            digest_reports[0].signed_stake_per_quorum[&QuorumNum(0)],
+            U256::from(200)
+        );
+        assert_eq!(
+            digest_reports[0].signed_stake_percentage_per_quorum[&QuorumNum(0)],
+            50.0
+        );
+        assert_eq!(digest_reports[1].task_response, minority_response);
+        assert_eq!(
+            digest_reports[1].signed_stake_percentage_per_quorum[&QuorumNum(0)],
+            25.0
+        );
+    }
+
+    #[test]
+    fn test_stake_percentage() {
+        assert_eq!(stake_percentage(U256::from(1), U256::from(3)), 33.33);
+        assert_eq!(stake_percentage(U256::from(5), U256::from(5)), 100.0);
+        assert_eq!(stake_percentage(U256::from(5), U256::ZERO), 0.0);
+        assert_eq!(
+            stake_percentage(U256::MAX, U256::from(1)),
+            u64::MAX as f64 / 100.0
+        );
+    }
+}
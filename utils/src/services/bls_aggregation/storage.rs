@@ -0,0 +1,420 @@
+use alloy_primitives::Bytes;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crypto::bls::Signature;
+use crate::types::{OperatorId, QuorumThresholdPercentage, TaskIndex, TaskResponse};
+
+use super::BlsAggregationError;
+
+/// A task initialized on the aggregator, together with every signature accepted for it so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTask {
+    pub task_index: TaskIndex,
+    pub task_created_block: u32,
+    pub quorum_numbers: Bytes,
+    pub quorum_threshold_percentages: Vec<QuorumThresholdPercentage>,
+    /// Wall-clock expiry of the task, in milliseconds since the unix epoch.
+    pub expires_at_ms: u64,
+    pub signatures: Vec<StoredSignature>,
+}
+
+/// Converts a wall-clock time into the millisecond timestamps stored in [`StoredTask`].
+pub fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+impl StoredTask {
+    /// Time left before the task expires, or `None` if it already has.
+    pub fn time_to_expiry(&self) -> Option<Duration> {
+        let expires_at = UNIX_EPOCH + Duration::from_millis(self.expires_at_ms);
+        expires_at.duration_since(SystemTime::now()).ok()
+    }
+}
+
+/// A signature that passed verification and was added to a task's aggregate.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSignature {
+    pub task_response: TaskResponse,
+    pub bls_signature: Signature,
+    pub operator_id: OperatorId,
+}
+
+/// Storage backend used by the BLS aggregation service to survive restarts.
+///
+/// Implementations must be durable by the time a method returns, and may block to get there;
+/// the service calls them on the blocking thread pool and does not retry failed writes.
+pub trait TaskStore: Send + Sync + 'static {
+    fn store_task(&self, task: &StoredTask) -> Result<(), BlsAggregationError>;
+
+    fn store_signature(
+        &self,
+        task_index: TaskIndex,
+        signature: &StoredSignature,
+    ) -> Result<(), BlsAggregationError>;
+
+    fn update_task_expiry(
+        &self,
+        task_index: TaskIndex,
+        expires_at_ms: u64,
+    ) -> Result<(), BlsAggregationError>;
+
+    fn remove_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError>;
+
+    fn load_tasks(&self) -> Result<Vec<StoredTask>, BlsAggregationError>;
+}
+
+/// Runs `op` against `task_store` on the blocking thread pool, so that stores syncing to disk
+/// do not hold up the async executor.
+pub(super) async fn run_blocking<R, F>(
+    task_store: &Arc<dyn TaskStore>,
+    op: F,
+) -> Result<R, BlsAggregationError>
+where
+    R: Send + 'static,
+    F: FnOnce(&dyn TaskStore) -> Result<R, BlsAggregationError> + Send + 'static,
+{
+    let task_store = Arc::clone(task_store);
+    tokio::task::spawn_blocking(move || op(task_store.as_ref()))
+        .await
+        .map_err(storage_error)?
+}
+
+/// Non-durable [`TaskStore`], useful for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryTaskStore {
+    tasks: Mutex<HashMap<TaskIndex, StoredTask>>,
+}
+
+impl InMemoryTaskStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl TaskStore for InMemoryTaskStore {
+    fn store_task(&self, task: &StoredTask) -> Result<(), BlsAggregationError> {
+        self.tasks
+            .lock()
+            .unwrap()
+            .insert(task.task_index, task.clone());
+        Ok(())
+    }
+
+    fn store_signature(
+        &self,
+        task_index: TaskIndex,
+        signature: &StoredSignature,
+    ) -> Result<(), BlsAggregationError> {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(&task_index) {
+            task.signatures.push(signature.clone());
+        }
+        Ok(())
+    }
+
+    fn update_task_expiry(
+        &self,
+        task_index: TaskIndex,
+        expires_at_ms: u64,
+    ) -> Result<(), BlsAggregationError> {
+        if let Some(task) = self.tasks.lock().unwrap().get_mut(&task_index) {
+            task.expires_at_ms = expires_at_ms;
+        }
+        Ok(())
+    }
+
+    fn remove_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError> {
+        self.tasks.lock().unwrap().remove(&task_index);
+        Ok(())
+    }
+
+    fn load_tasks(&self) -> Result<Vec<StoredTask>, BlsAggregationError> {
+        Ok(self.tasks.lock().unwrap().values().cloned().collect())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum JournalEntry {
+    TaskInitialized(StoredTask),
+    SignatureAccepted {
+        task_index: TaskIndex,
+        signature: StoredSignature,
+    },
+    ExpiryUpdated {
+        task_index: TaskIndex,
+        expires_at_ms: u64,
+    },
+    TaskRemoved(TaskIndex),
+}
+
+/// [`TaskStore`] backed by an append-only journal file with one JSON entry per line.
+///
+/// Every write is synced to disk before returning. The journal is replayed and compacted
+/// down to the live tasks when the store is opened. An unreadable last entry, left behind by
+/// a crash mid-write, is skipped; unreadable entries anywhere else fail the replay, as
+/// compacting past them would lose the state they recorded.
+#[derive(Debug)]
+pub struct FileTaskStore {
+    path: PathBuf,
+    journal: Mutex<File>,
+    tasks: Mutex<HashMap<TaskIndex, StoredTask>>,
+}
+
+impl FileTaskStore {
+    /// Opens the journal at `path`, creating it (and its parent directories) if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, BlsAggregationError> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(storage_error)?;
+        }
+
+        let mut tasks = HashMap::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path).map_err(storage_error)?);
+            let lines = reader
+                .lines()
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(storage_error)?;
+            let entries: Vec<_> = lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .collect();
+            for (position, (line_index, line)) in entries.iter().enumerate() {
+                let entry = match serde_json::from_str::<JournalEntry>(line) {
+                    Ok(entry) => entry,
+                    // A crash mid-write can leave a truncated last line behind
+                    Err(e) if position + 1 == entries.len() => {
+                        log::warn!(
+                            "Skipping truncated last task journal entry in {:?}: {}",
+                            path,
+                            e
+                        );
+                        continue;
+                    }
+                    Err(e) => {
+                        return Err(BlsAggregationError::StorageError(format!(
+                            "unreadable task journal entry on line {} of {:?}: {}",
+                            line_index + 1,
+                            path,
+                            e
+                        )))
+                    }
+                };
+                apply_entry(&mut tasks, entry);
+            }
+        }
+
+        let journal = compact_journal(&path, &tasks)?;
+
+        Ok(Self {
+            path,
+            journal: Mutex::new(journal),
+            tasks: Mutex::new(tasks),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn append(&self, entry: JournalEntry) -> Result<(), BlsAggregationError> {
+        let mut line = serde_json::to_vec(&entry).map_err(storage_error)?;
+        line.push(b'\n');
+
+        let mut journal = self.journal.lock().unwrap();
+        journal.write_all(&line).map_err(storage_error)?;
+        journal.sync_data().map_err(storage_error)?;
+        drop(journal);
+
+        apply_entry(&mut self.tasks.lock().unwrap(), entry);
+        Ok(())
+    }
+}
+
+impl TaskStore for FileTaskStore {
+    fn store_task(&self, task: &StoredTask) -> Result<(), BlsAggregationError> {
+        self.append(JournalEntry::TaskInitialized(task.clone()))
+    }
+
+    fn store_signature(
+        &self,
+        task_index: TaskIndex,
+        signature: &StoredSignature,
+    ) -> Result<(), BlsAggregationError> {
+        self.append(JournalEntry::SignatureAccepted {
+            task_index,
+            signature: signature.clone(),
+        })
+    }
+
+    fn update_task_expiry(
+        &self,
+        task_index: TaskIndex,
+        expires_at_ms: u64,
+    ) -> Result<(), BlsAggregationError> {
+        self.append(JournalEntry::ExpiryUpdated {
+            task_index,
+            expires_at_ms,
+        })
+    }
+
+    fn remove_task(&self, task_index: TaskIndex) -> Result<(), BlsAggregationError> {
+        self.append(JournalEntry::TaskRemoved(task_index))
+    }
+
+    fn load_tasks(&self) -> Result<Vec<StoredTask>, BlsAggregationError> {
+        Ok(self.tasks.lock().unwrap().values().cloned().collect())
+    }
+}
+
+fn apply_entry(tasks: &mut HashMap<TaskIndex, StoredTask>, entry: JournalEntry) {
+    match entry {
+        JournalEntry::TaskInitialized(task) => {
+            tasks.insert(task.task_index, task);
+        }
+        JournalEntry::SignatureAccepted {
+            task_index,
+            signature,
+        } => {
+            if let Some(task) = tasks.get_mut(&task_index) {
+                task.signatures.push(signature);
+            }
+        }
+        JournalEntry::ExpiryUpdated {
+            task_index,
+            expires_at_ms,
+        } => {
+            if let Some(task) = tasks.get_mut(&task_index) {
+                task.expires_at_ms = expires_at_ms;
+            }
+        }
+        JournalEntry::TaskRemoved(task_index) => {
+            tasks.remove(&task_index);
+        }
+    }
+}
+
+/// Rewrites the journal so it only contains the given tasks, returning the file opened for
+/// appending. The new journal is written next to the old one and renamed over it, so a crash
+/// during compaction leaves the previous journal intact.
+fn compact_journal(
+    path: &Path,
+    tasks: &HashMap<TaskIndex, StoredTask>,
+) -> Result<File, BlsAggregationError> {
+    let tmp_path = path.with_extension("compacting");
+    let mut tmp = File::create(&tmp_path).map_err(storage_error)?;
+    for task in tasks.values() {
+        let mut line = serde_json::to_vec(&JournalEntry::TaskInitialized(task.clone()))
+            .map_err(storage_error)?;
+        line.push(b'\n');
+        tmp.write_all(&line).map_err(storage_error)?;
+    }
+    tmp.sync_all().map_err(storage_error)?;
+    fs::rename(&tmp_path, path).map_err(storage_error)?;
+
+    OpenOptions::new()
+        .append(true)
+        .open(path)
+        .map_err(storage_error)
+}
+
+fn storage_error(e: impl std::fmt::Display) -> BlsAggregationError {
+    BlsAggregationError::StorageError(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::bls::KeyPair;
+    use alloy_primitives::B256;
+
+    #[test]
+    fn test_file_task_store_replays_journal() {
+        let path = std::env::temp_dir()
+            .join(format!("eigen-task-store-{}", uuid::Uuid::new_v4()))
+            .join("tasks.journal");
+
+        let task = |task_index| StoredTask {
+            task_index,
+            task_created_block: 10,
+            quorum_numbers: Bytes::from(vec![1u8]),
+            quorum_threshold_percentages: vec![QuorumThresholdPercentage(67)],
+            expires_at_ms: 1_000,
+            signatures: vec![],
+        };
+        let signature = StoredSignature {
+            task_response: vec![1, 2, 3],
+            bls_signature: KeyPair::gen_random().sign_message(&[7u8; 32]),
+            operator_id: B256::repeat_byte(1),
+        };
+
+        {
+            let store = FileTaskStore::open(&path).unwrap();
+            store.store_task(&task(1)).unwrap();
+            store.store_task(&task(2)).unwrap();
+            store.store_signature(1, &signature).unwrap();
+            store.remove_task(2).unwrap();
+        }
+
+        let store = FileTaskStore::open(&path).unwrap();
+        let tasks = store.load_tasks().unwrap();
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(tasks[0].task_index, 1);
+        assert_eq!(tasks[0].signatures.len(), 1);
+        assert_eq!(tasks[0].signatures[0].operator_id, signature.operator_id);
+        assert!(tasks[0].time_to_expiry().is_none());
+
+        // Compaction on open drops the removed task from the journal itself
+        let journal = fs::read_to_string(&path).unwrap();
+        assert_eq!(journal.lines().count(), 1);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_file_task_store_rejects_corrupt_journal() {
+        let path = std::env::temp_dir()
+            .join(format!("eigen-task-store-{}", uuid::Uuid::new_v4()))
+            .join("tasks.journal");
+        let task = StoredTask {
+            task_index: 1,
+            task_created_block: 10,
+            quorum_numbers: Bytes::from(vec![1u8]),
+            quorum_threshold_percentages: vec![QuorumThresholdPercentage(67)],
+            expires_at_ms: 1_000,
+            signatures: vec![],
+        };
+        let entry = serde_json::to_string(&JournalEntry::TaskInitialized(task)).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+
+        // A torn last entry is dropped
+        fs::write(&path, format!("{}\n{{\"TaskRem", entry)).unwrap();
+        assert_eq!(
+            FileTaskStore::open(&path)
+                .unwrap()
+                .load_tasks()
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // A corrupt entry followed by others is an error, and the journal is left as is
+        let corrupt = format!("{{\"TaskRem\n{}\n", entry);
+        fs::write(&path, &corrupt).unwrap();
+        assert!(matches!(
+            FileTaskStore::open(&path),
+            Err(BlsAggregationError::StorageError(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), corrupt);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -1,20 +1,24 @@
-use alloy_primitives::{Address, FixedBytes, Log};
+use alloy_primitives::{Address, FixedBytes, Log, B256};
+use alloy_rpc_types::Log as RpcLog;
 
 use alloy_sol_types::SolEvent;
 
 use async_trait::async_trait;
 use eigen_contracts::{BlsApkRegistry, RegistryCoordinator};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::iter::zip;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tokio::sync::oneshot;
-use tokio::task;
+use tokio::task::{self, JoinHandle};
+use tokio_util::sync::CancellationToken;
 
 use crate::avs_registry::reader::AvsRegistryChainReaderTrait;
 use crate::avs_registry::subscriber::AvsRegistryChainSubscriberTrait;
 use crate::avs_registry::AvsRegistryContractManager;
 use crate::crypto::bls::{G1Point, G2Point};
+use crate::subscriptions::resilient::DEFAULT_REORG_DEPTH;
+use crate::subscriptions::LogEvent;
 use crate::types::{operator_id_from_g1_pubkey, OperatorId, OperatorInfo, OperatorPubkeys, Socket};
 use crate::Config;
 
@@ -22,6 +26,69 @@ use super::OperatorInfoServiceTrait;
 
 const DEFAULT_LOG_FILTER_QUERY_BLOCK_RANGE: u64 = 10_000;
 
+/// Socket replaced by each socket update of a block, by block hash and log index.
+type BlockSocketUpdates = HashMap<(Option<B256>, Option<u64>), (OperatorId, Option<Socket>)>;
+
+/// Socket replaced by each socket update, restored if the update is removed by a reorg.
+///
+/// Updates are forgotten once older than the reorg depth tracked by the subscription, as
+/// their removal is no longer reported.
+#[derive(Debug, Default)]
+struct SocketUpdates {
+    updates: BTreeMap<u64, BlockSocketUpdates>,
+}
+
+impl SocketUpdates {
+    fn insert(&mut self, log: &RpcLog, operator_id: OperatorId, previous_socket: Option<Socket>) {
+        // Pending logs cannot be reorganised out of the chain
+        let Some(block_number) = log.block_number else {
+            return;
+        };
+        self.updates.entry(block_number).or_default().insert(
+            (log.block_hash, log.log_index),
+            (operator_id, previous_socket),
+        );
+        let oldest = block_number.saturating_sub(DEFAULT_REORG_DEPTH);
+        self.updates = self.updates.split_off(&oldest);
+    }
+
+    /// Forgets an update removed by a reorg, returning the socket to restore for its operator.
+    ///
+    /// Removals may arrive in any order. While a later update of the same operator is still
+    /// applied, the removed update's previous socket is handed down to it instead, so that
+    /// reverting it restores the socket from before both updates.
+    fn remove(&mut self, log: &RpcLog) -> Option<(OperatorId, Option<Socket>)> {
+        let block_number = log.block_number?;
+        let block_updates = self.updates.get_mut(&block_number)?;
+        let (operator_id, previous_socket) =
+            block_updates.remove(&(log.block_hash, log.log_index))?;
+        if block_updates.is_empty() {
+            self.updates.remove(&block_number);
+        }
+
+        let position = (block_number, log.log_index);
+        let next_update = self
+            .updates
+            .range_mut(block_number..)
+            .flat_map(|(block_number, block_updates)| {
+                block_updates
+                    .iter_mut()
+                    .map(move |((_, log_index), update)| ((*block_number, *log_index), update))
+            })
+            .filter(|(update_position, (update_operator_id, _))| {
+                *update_position > position && *update_operator_id == operator_id
+            })
+            .min_by_key(|(update_position, _)| *update_position);
+        match next_update {
+            Some((_, (_, next_previous_socket))) => {
+                *next_previous_socket = previous_socket;
+                None
+            }
+            None => Some((operator_id, previous_socket)),
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct OperatorsInfoServiceInMemory<T: Config> {
     log_filter_query_block_range: u64,
@@ -43,9 +110,12 @@ pub struct Resp {
 }
 
 impl<T: Config> OperatorsInfoServiceInMemory<T> {
+    /// Creates the service and starts indexing operators in a background task, which runs
+    /// until `shutdown` is cancelled.
     pub fn new(
         avs_registry_manager: AvsRegistryContractManager<T>,
         log_filter_query_block_range: Option<u64>,
+        shutdown: CancellationToken,
     ) -> Self {
         let (query_sender, query_receiver) = mpsc::channel(100);
         let log_filter_query_block_range =
@@ -60,80 +130,90 @@ impl<T: Config> OperatorsInfoServiceInMemory<T> {
             socket_dict: Arc::new(Mutex::new(HashMap::new())),
         };
 
-        service.clone().start_service_in_task(query_receiver);
+        service
+            .clone()
+            .start_service_in_task(query_receiver, shutdown);
 
         service
     }
 
-    pub fn start_service_in_task(self, mut query_receiver: Receiver<Query>) {
+    /// Indexes operator registrations and answers queries until `shutdown` is cancelled.
+    /// Queries sent before cancellation are still answered before the task exits.
+    pub fn start_service_in_task(
+        self,
+        mut query_receiver: Receiver<Query>,
+        shutdown: CancellationToken,
+    ) -> JoinHandle<()> {
         let avs_registry_manager = self.avs_registry_manager.clone();
         let pubkey_dict = self.pubkey_dict.clone();
         let operator_addr_to_id = self.operator_addr_to_id.clone();
         let socket_dict = self.socket_dict.clone();
 
         task::spawn(async move {
-            let mut new_pubkey_registration_stream = avs_registry_manager
-                .subscribe_to_new_pubkey_registrations()
-                .await
-                .unwrap();
-            let mut new_socket_registration_stream = avs_registry_manager
-                .subscribe_to_operator_socket_updates()
+            let subscriptions = async {
+                let new_pubkey_registration_stream = avs_registry_manager
+                    .subscribe_to_new_pubkey_registrations()
+                    .await
+                    .unwrap();
+                let new_socket_registration_stream = avs_registry_manager
+                    .subscribe_to_operator_socket_updates()
+                    .await
+                    .unwrap();
+
+                // Fill the pubkey_dict db with the operators and pubkeys found
+                if let Err(e) = query_past_registered_operator_events_and_fill_db::<T>(
+                    &avs_registry_manager,
+                    &pubkey_dict,
+                    &operator_addr_to_id,
+                    &socket_dict,
+                    self.log_filter_query_block_range,
+                )
                 .await
-                .unwrap();
-
-            // Fill the pubkey_dict db with the operators and pubkeys found
-            if let Err(e) = query_past_registered_operator_events_and_fill_db::<T>(
-                &avs_registry_manager,
-                &pubkey_dict,
-                &operator_addr_to_id,
-                &socket_dict,
-                self.log_filter_query_block_range,
-            )
-            .await
-            {
-                log::error!("Error querying past registered operator events: {:?}", e);
-                panic!("Error querying past registered operator events");
-            }
+                {
+                    log::error!("Error querying past registered operator events: {:?}", e);
+                    panic!("Error querying past registered operator events");
+                }
+                (
+                    new_pubkey_registration_stream,
+                    new_socket_registration_stream,
+                )
+            };
+            let streams = tokio::select! {
+                streams = subscriptions => streams,
+                _ = shutdown.cancelled() => return,
+            };
+            let (mut new_pubkey_registration_stream, mut new_socket_registration_stream) = streams;
+            let mut socket_updates = SocketUpdates::default();
 
             loop {
                 tokio::select! {
-                    Some(event) = query_receiver.recv() => {
-                            let Query {
-                                operator_addr,
-                                resp_sender,
-                            } = event;
-                            {
-                                let pubkeys_lock = pubkey_dict.lock().unwrap();
-                                let pubkeys = pubkeys_lock.get(&operator_addr).cloned();
-                                drop(pubkeys_lock);
-
-                                let operator_id = operator_addr_to_id
-                                    .lock()
-                                    .unwrap()
-                                    .get(&operator_addr)
-                                    .cloned();
-                                let socket = operator_id
-                                    .as_ref()
-                                    .and_then(|id| {
-                                        let socket_lock = socket_dict.lock().unwrap();
-                                        let socket = socket_lock.get(id).cloned();
-                                        drop(socket_lock);
-
-                                        socket
-                                    });
-
-                                let operator_info = OperatorInfo {
-                                    socket: socket.unwrap_or_default(),
-                                    pubkeys: pubkeys.clone().unwrap_or_default(),
-                                };
-                                let operator_exists = pubkeys.is_some();
-                                let _ = resp_sender.send(Resp {
-                                    operator_info,
-                                    operator_exists,
-                                });
-                            }
+                    _ = shutdown.cancelled() => {
+                        query_receiver.close();
+                        while let Some(query) = query_receiver.recv().await {
+                            answer_query(query, &pubkey_dict, &operator_addr_to_id, &socket_dict);
+                        }
+                        log::info!("Operators info service stopped");
+                        return;
                     }
-                    Ok(new_pubkey_registration_event) = new_pubkey_registration_stream.recv() => {
+                    Some(query) = query_receiver.recv() => {
+                        answer_query(query, &pubkey_dict, &operator_addr_to_id, &socket_dict);
+                    }
+                    Some(new_pubkey_registration_event) = new_pubkey_registration_stream.recv() => {
+                        let new_pubkey_registration_event = match new_pubkey_registration_event {
+                            LogEvent::Added(log) => log,
+                            LogEvent::Removed(log) => {
+                                let removed_event = BlsApkRegistry::NewPubkeyRegistration::decode_log(&log.inner, true).unwrap();
+                                let operator_addr = removed_event.operator;
+                                pubkey_dict.lock().unwrap().remove(&operator_addr);
+                                operator_addr_to_id.lock().unwrap().remove(&operator_addr);
+                                log::warn!(
+                                    "Removed operator pubkeys after their registration was reorganised out of the chain. Block: {:?}, Operator Address: {:?}",
+                                    log.block_number,
+                                    operator_addr,
+                                );
+                                continue;
+                            }
+                        };
                         let block_number = new_pubkey_registration_event.block_number;
                         let new_pubkey_registration_event: Log<BlsApkRegistry::NewPubkeyRegistration> = BlsApkRegistry::NewPubkeyRegistration::decode_log(&new_pubkey_registration_event.inner, true).unwrap();
                         let operator_addr = new_pubkey_registration_event.operator;
@@ -167,7 +247,30 @@ impl<T: Config> OperatorsInfoServiceInMemory<T> {
                             pubkey_g2.to_bytes(),
                         );
                     }
-                    Ok(new_socket_registration_event) = new_socket_registration_stream.recv() => {
+                    Some(new_socket_registration_event) = new_socket_registration_stream.recv() => {
+                        let new_socket_registration_event = match new_socket_registration_event {
+                            LogEvent::Added(log) => log,
+                            LogEvent::Removed(log) => {
+                                let Some((operator_id, previous_socket)) =
+                                    socket_updates.remove(&log)
+                                else {
+                                    continue;
+                                };
+                                let mut socket_dict_lock = socket_dict.lock().unwrap();
+                                match previous_socket {
+                                    Some(socket) => socket_dict_lock.insert(operator_id, socket),
+                                    None => socket_dict_lock.remove(&operator_id),
+                                };
+                                drop(socket_dict_lock);
+                                log::warn!(
+                                    "Reverted socket update reorganised out of the chain. Block: {:?}, Operator ID: {:?}",
+                                    log.block_number,
+                                    operator_id,
+                                );
+                                continue;
+                            }
+                        };
+                        let socket_update_log = new_socket_registration_event.clone();
                         let new_socket_registration_event = RegistryCoordinator::OperatorSocketUpdate::decode_log(&new_socket_registration_event.inner, true).unwrap();
 
                         let operator_id = new_socket_registration_event.operatorId;
@@ -180,15 +283,47 @@ impl<T: Config> OperatorsInfoServiceInMemory<T> {
                         );
 
                         let mut socket_dict_lock = socket_dict.lock().unwrap();
-                        socket_dict_lock.insert(operator_id, socket);
+                        let previous_socket = socket_dict_lock.insert(operator_id, socket);
                         drop(socket_dict_lock);
+                        socket_updates.insert(&socket_update_log, operator_id, previous_socket);
                     }
                 }
             }
-        });
+        })
     }
 }
 
+fn answer_query(
+    query: Query,
+    pubkey_dict: &Mutex<HashMap<Address, OperatorPubkeys>>,
+    operator_addr_to_id: &Mutex<HashMap<Address, OperatorId>>,
+    socket_dict: &Mutex<HashMap<OperatorId, Socket>>,
+) {
+    let Query {
+        operator_addr,
+        resp_sender,
+    } = query;
+    let pubkeys = pubkey_dict.lock().unwrap().get(&operator_addr).cloned();
+    let operator_id = operator_addr_to_id
+        .lock()
+        .unwrap()
+        .get(&operator_addr)
+        .cloned();
+    let socket = operator_id
+        .as_ref()
+        .and_then(|id| socket_dict.lock().unwrap().get(id).cloned());
+
+    let operator_info = OperatorInfo {
+        socket: socket.unwrap_or_default(),
+        pubkeys: pubkeys.clone().unwrap_or_default(),
+    };
+    let operator_exists = pubkeys.is_some();
+    let _ = resp_sender.send(Resp {
+        operator_info,
+        operator_exists,
+    });
+}
+
 pub async fn query_past_registered_operator_events_and_fill_db<T: Config>(
     avs_registry_manager: &AvsRegistryContractManager<T>,
     pubkey_dict: &Arc<Mutex<HashMap<Address, OperatorPubkeys>>>,
@@ -248,3 +383,84 @@ impl<T: Config> OperatorInfoServiceTrait for OperatorsInfoServiceInMemory<T> {
         Ok(resp_receiver.await.ok().map(|resp| resp.operator_info))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn socket_update(block_number: u64, block_hash: u8) -> RpcLog {
+        RpcLog {
+            block_number: Some(block_number),
+            block_hash: Some(B256::repeat_byte(block_hash)),
+            log_index: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_socket_updates_are_pruned_after_the_reorg_depth() {
+        let operator_id = OperatorId::repeat_byte(1);
+        let other_operator_id = OperatorId::repeat_byte(2);
+        let mut socket_updates = SocketUpdates::default();
+        socket_updates.insert(&socket_update(10, 1), operator_id, None);
+        socket_updates.insert(&socket_update(11, 2), operator_id, Some("a".to_string()));
+        assert_eq!(
+            socket_updates.remove(&socket_update(11, 2)),
+            Some((operator_id, Some("a".to_string())))
+        );
+        assert_eq!(socket_updates.remove(&socket_update(11, 2)), None);
+        // Updates from another block with the same number are not reverted
+        assert_eq!(socket_updates.remove(&socket_update(10, 3)), None);
+
+        socket_updates.insert(
+            &socket_update(10 + DEFAULT_REORG_DEPTH, 4),
+            other_operator_id,
+            None,
+        );
+        assert_eq!(
+            socket_updates.remove(&socket_update(10, 1)),
+            Some((operator_id, None))
+        );
+        socket_updates.insert(&socket_update(10, 1), operator_id, None);
+        socket_updates.insert(
+            &socket_update(11 + DEFAULT_REORG_DEPTH, 5),
+            other_operator_id,
+            None,
+        );
+        assert_eq!(socket_updates.remove(&socket_update(10, 1)), None);
+        assert_eq!(
+            socket_updates.updates.keys().copied().collect::<Vec<_>>(),
+            vec![10 + DEFAULT_REORG_DEPTH, 11 + DEFAULT_REORG_DEPTH]
+        );
+    }
+
+    #[test]
+    fn test_socket_updates_revert_in_any_order() {
+        let operator_id = OperatorId::repeat_byte(1);
+        let socket = |socket: &str| Some(socket.to_string());
+        let (first_update, second_update) = (socket_update(10, 1), socket_update(11, 2));
+        for reverted_updates in [
+            [&second_update, &first_update],
+            [&first_update, &second_update],
+        ] {
+            // The operator updates s0 to s1 in block 10, then s1 to s2 in block 11
+            let mut socket_dict = HashMap::from([(operator_id, "s2".to_string())]);
+            let mut socket_updates = SocketUpdates::default();
+            socket_updates.insert(&first_update, operator_id, socket("s0"));
+            socket_updates.insert(&second_update, operator_id, socket("s1"));
+
+            // Both blocks are reorganised out of the chain
+            for reverted_update in reverted_updates {
+                if let Some((operator_id, previous_socket)) = socket_updates.remove(reverted_update)
+                {
+                    match previous_socket {
+                        Some(socket) => socket_dict.insert(operator_id, socket),
+                        None => socket_dict.remove(&operator_id),
+                    };
+                }
+            }
+            assert_eq!(socket_dict.get(&operator_id), socket("s0").as_ref());
+            assert!(socket_updates.updates.is_empty());
+        }
+    }
+}
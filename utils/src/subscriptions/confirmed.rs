@@ -0,0 +1,233 @@
+//! Holds back the logs of a subscription until enough blocks were built on top of them.
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types::Log;
+use alloy_transport::Transport;
+use std::collections::BTreeMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+
+use super::resilient::DEFAULT_REORG_DEPTH;
+use super::{LogEvent, LogSubscription, EVENT_CHANNEL_CAPACITY};
+
+pub const DEFAULT_HEAD_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Most confirmations supported on top of subscriptions tracking [`DEFAULT_REORG_DEPTH`]
+/// blocks, which leaves the deeper half of those blocks to report removals of confirmed logs.
+pub const MAX_CONFIRMATIONS: u64 = DEFAULT_REORG_DEPTH / 2;
+
+/// Returns `confirmations` clamped to [`MAX_CONFIRMATIONS`], as removals of logs confirmed
+/// beyond the reorg depth of the wrapped subscription could never be reported.
+pub fn clamp_confirmations(confirmations: u64) -> u64 {
+    if confirmations > MAX_CONFIRMATIONS {
+        log::warn!(
+            "{} log confirmations exceed the supported maximum, using {} instead",
+            confirmations,
+            MAX_CONFIRMATIONS
+        );
+    }
+    confirmations.min(MAX_CONFIRMATIONS)
+}
+
+/// Delivers the logs of a [`LogSubscription`] once `confirmations` blocks were built on top
+/// of their block, polling the chain head for new blocks.
+///
+/// Logs removed before being confirmed are dropped without being delivered. Removals of
+/// confirmed logs, from reorganisations deeper than `confirmations`, are delivered so that
+/// the state derived from them can be reverted. This requires `confirmations` to be below the
+/// reorg depth of the wrapped subscription, see [`clamp_confirmations`].
+#[derive(Debug, Clone)]
+pub struct ConfirmedLogSubscriber<P, T> {
+    provider: P,
+    confirmations: u64,
+    head_poll_interval: Duration,
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<P, T> ConfirmedLogSubscriber<P, T>
+where
+    P: Provider<T, Ethereum> + Clone + 'static,
+    T: Transport + Clone,
+{
+    pub fn new(provider: P, confirmations: u64) -> Self {
+        Self {
+            provider,
+            confirmations,
+            head_poll_interval: DEFAULT_HEAD_POLL_INTERVAL,
+            _transport: PhantomData,
+        }
+    }
+
+    /// Polls the chain head for new blocks at `head_poll_interval`.
+    pub fn with_head_poll_interval(mut self, head_poll_interval: Duration) -> Self {
+        self.head_poll_interval = head_poll_interval;
+        self
+    }
+
+    /// Wraps `subscription`, which is returned unchanged when no confirmations are required.
+    pub fn confirm(self, subscription: LogSubscription) -> LogSubscription {
+        if self.confirmations == 0 {
+            return subscription;
+        }
+        let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let connected = subscription.liveness();
+        let task = tokio::spawn(self.run(subscription, sender));
+        LogSubscription {
+            receiver,
+            task,
+            connected,
+        }
+    }
+
+    async fn run(self, mut subscription: LogSubscription, sender: mpsc::Sender<LogEvent>) {
+        let mut pending = PendingLogs::new(self.confirmations);
+        let mut head_poll = tokio::time::interval(self.head_poll_interval);
+        head_poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let events = tokio::select! {
+                event = subscription.recv() => match event {
+                    Some(event) => pending.push(event),
+                    None => return,
+                },
+                _ = head_poll.tick() => match self.provider.get_block_number().await {
+                    Ok(head) => pending.advance(head),
+                    Err(e) => {
+                        log::warn!("Failed to get the chain head: {}", e);
+                        continue;
+                    }
+                },
+                _ = sender.closed() => return,
+            };
+            for event in events {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Logs waiting for confirmations, by block number.
+struct PendingLogs {
+    confirmations: u64,
+    head: u64,
+    logs: BTreeMap<u64, Vec<Log>>,
+}
+
+impl PendingLogs {
+    fn new(confirmations: u64) -> Self {
+        Self {
+            confirmations,
+            head: 0,
+            logs: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the events to deliver after receiving `event`.
+    fn push(&mut self, event: LogEvent) -> Vec<LogEvent> {
+        match event {
+            LogEvent::Added(log) => match log.block_number {
+                Some(block_number) => {
+                    self.logs.entry(block_number).or_default().push(log);
+                    self.advance(block_number)
+                }
+                None => vec![LogEvent::Added(log)],
+            },
+            LogEvent::Removed(log) => {
+                let pending = log.block_number.and_then(|block_number| {
+                    let block_logs = self.logs.get_mut(&block_number)?;
+                    let index = block_logs.iter().position(|pending| {
+                        pending.block_hash == log.block_hash && pending.log_index == log.log_index
+                    })?;
+                    block_logs.remove(index);
+                    if block_logs.is_empty() {
+                        self.logs.remove(&block_number);
+                    }
+                    Some(())
+                });
+                match pending {
+                    Some(()) => vec![],
+                    None => vec![LogEvent::Removed(log)],
+                }
+            }
+        }
+    }
+
+    /// Returns the logs confirmed once the chain reaches `head`.
+    fn advance(&mut self, head: u64) -> Vec<LogEvent> {
+        self.head = self.head.max(head);
+        let unconfirmed = self
+            .logs
+            .split_off(&(self.head.saturating_sub(self.confirmations) + 1));
+        std::mem::replace(&mut self.logs, unconfirmed)
+            .into_values()
+            .flatten()
+            .map(LogEvent::Added)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::B256;
+
+    fn log(block_number: u64, block_hash: u8, log_index: u64) -> Log {
+        Log {
+            block_number: Some(block_number),
+            block_hash: Some(B256::repeat_byte(block_hash)),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pending_logs_wait_for_confirmations() {
+        let mut pending = PendingLogs::new(2);
+
+        assert!(pending.push(LogEvent::Added(log(10, 1, 0))).is_empty());
+        assert!(pending.push(LogEvent::Added(log(10, 1, 1))).is_empty());
+        assert!(pending.push(LogEvent::Added(log(11, 2, 0))).is_empty());
+        assert!(pending.advance(11).is_empty());
+        assert_eq!(
+            pending.advance(12),
+            vec![
+                LogEvent::Added(log(10, 1, 0)),
+                LogEvent::Added(log(10, 1, 1))
+            ]
+        );
+        // A log arriving after its block was confirmed is delivered right away
+        assert_eq!(
+            pending.push(LogEvent::Added(log(9, 3, 0))),
+            vec![LogEvent::Added(log(9, 3, 0))]
+        );
+        assert_eq!(pending.advance(13), vec![LogEvent::Added(log(11, 2, 0))]);
+    }
+
+    #[test]
+    fn test_pending_logs_removals() {
+        let mut pending = PendingLogs::new(2);
+        pending.push(LogEvent::Added(log(10, 1, 0)));
+        pending.push(LogEvent::Added(log(11, 2, 0)));
+
+        // Unconfirmed logs are dropped
+        assert!(pending.push(LogEvent::Removed(log(11, 2, 0))).is_empty());
+        assert_eq!(pending.advance(13), vec![LogEvent::Added(log(10, 1, 0))]);
+
+        // Confirmed logs are reported as removed
+        assert_eq!(
+            pending.push(LogEvent::Removed(log(10, 1, 0))),
+            vec![LogEvent::Removed(log(10, 1, 0))]
+        );
+    }
+
+    #[test]
+    fn test_confirmations_are_clamped_below_the_reorg_depth() {
+        assert_eq!(clamp_confirmations(0), 0);
+        assert_eq!(clamp_confirmations(MAX_CONFIRMATIONS), MAX_CONFIRMATIONS);
+        assert_eq!(clamp_confirmations(DEFAULT_REORG_DEPTH), MAX_CONFIRMATIONS);
+        assert_eq!(clamp_confirmations(u64::MAX), MAX_CONFIRMATIONS);
+    }
+}
@@ -0,0 +1,48 @@
+//! Log subscriptions that survive dropped connections and chain reorganisations.
+use alloy_rpc_types::Log;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+use crate::node_api::health::Liveness;
+
+pub mod confirmed;
+pub mod polling;
+pub mod resilient;
+
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A change to the logs matching a subscription's filter.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    /// A log included in the chain.
+    Added(Log),
+    /// A previously added log whose block was reorganised out of the chain.
+    Removed(Log),
+}
+
+/// Events produced by a background task, which is stopped when this is dropped.
+#[derive(Debug)]
+pub struct LogSubscription {
+    receiver: mpsc::Receiver<LogEvent>,
+    task: JoinHandle<()>,
+    connected: Liveness,
+}
+
+impl LogSubscription {
+    /// Waits for the next event. Returns `None` only if the subscription task stopped.
+    pub async fn recv(&mut self) -> Option<LogEvent> {
+        self.receiver.recv().await
+    }
+
+    /// Alive while the subscription is connected to the node, and not while it reconnects
+    /// or after its polls failed, during which no new logs are delivered.
+    pub fn liveness(&self) -> Liveness {
+        self.connected.clone()
+    }
+}
+
+impl Drop for LogSubscription {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,226 @@
+//! Log subscription polling `eth_getLogs`, for endpoints without `eth_subscribe`.
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types::Filter;
+use alloy_transport::{Transport, TransportResult};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::MissedTickBehavior;
+
+use super::resilient::{
+    get_logs_since, LogTracker, DEFAULT_BACKFILL_BLOCK_RANGE, DEFAULT_REORG_DEPTH,
+};
+use super::{LogEvent, LogSubscription, EVENT_CHANNEL_CAPACITY};
+use crate::node_api::health::Liveness;
+
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Polls the logs matching a filter with `eth_getLogs`, from `reorg_depth` blocks before the
+/// last block seen to the latest block, so that subscriptions also work over HTTP.
+///
+/// Unlike `eth_newFilter`, this keeps no state on the node, so it survives filters expiring
+/// and requests being load balanced across nodes. Logs are only delivered once, and logs of
+/// the last `reorg_depth` blocks no longer returned by a poll are delivered as
+/// [`LogEvent::Removed`], newest first.
+#[derive(Debug, Clone)]
+pub struct PollingLogSubscriber<P, T> {
+    provider: P,
+    filter: Filter,
+    poll_interval: Duration,
+    block_range: u64,
+    reorg_depth: u64,
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<P, T> PollingLogSubscriber<P, T>
+where
+    P: Provider<T, Ethereum> + Clone + 'static,
+    T: Transport + Clone,
+{
+    pub fn new(provider: P, filter: Filter) -> Self {
+        Self {
+            provider,
+            filter,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            block_range: DEFAULT_BACKFILL_BLOCK_RANGE,
+            reorg_depth: DEFAULT_REORG_DEPTH,
+            _transport: PhantomData,
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Polls in `eth_getLogs` requests spanning at most this many blocks.
+    pub fn with_block_range(mut self, block_range: u64) -> Self {
+        self.block_range = block_range.max(1);
+        self
+    }
+
+    /// Polls again the logs of this many blocks before the last block seen, to de-duplicate
+    /// them and detect their removal.
+    pub fn with_reorg_depth(mut self, reorg_depth: u64) -> Self {
+        self.reorg_depth = reorg_depth;
+        self
+    }
+
+    /// Starts polling from the latest block, failing if it cannot be fetched. Failed polls
+    /// are retried at the next interval until the returned subscription is dropped.
+    pub async fn subscribe(self) -> TransportResult<LogSubscription> {
+        let from_block = self.provider.get_block_number().await?;
+        let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let connected = Liveness::new(true);
+        let task = tokio::spawn(self.run(from_block, sender, connected.clone()));
+        Ok(LogSubscription {
+            receiver,
+            task,
+            connected,
+        })
+    }
+
+    async fn run(self, from_block: u64, sender: mpsc::Sender<LogEvent>, connected: Liveness) {
+        let mut tracker = LogTracker::new(from_block, self.reorg_depth);
+        let mut poll = tokio::time::interval(self.poll_interval);
+        poll.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {}
+                _ = sender.closed() => return,
+            }
+            let polled = get_logs_since(
+                &self.provider,
+                &self.filter,
+                tracker.backfill_from(),
+                self.block_range,
+            );
+            let (from_block, to_block, logs) = match polled.await {
+                Ok(polled) => polled,
+                Err(e) => {
+                    log::warn!("Failed to poll logs: {}", e);
+                    connected.set_alive(false);
+                    continue;
+                }
+            };
+            connected.set_alive(true);
+            for event in tracker.reconcile(from_block, to_block, logs) {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::mock::{MockResponse, MockTransport};
+    use alloy_primitives::B256;
+    use alloy_provider::RootProvider;
+    use alloy_rpc_client::RpcClient;
+    use alloy_rpc_types::Log;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// Chain head and logs, answering `eth_blockNumber` and `eth_getLogs` unless `down`.
+    #[derive(Clone, Default)]
+    struct MockChain {
+        state: Arc<Mutex<(u64, Vec<Log>)>>,
+        down: Arc<AtomicBool>,
+    }
+
+    impl MockChain {
+        fn transport(&self) -> MockTransport {
+            let chain = self.clone();
+            MockTransport::new(move |method, params| {
+                if chain.down.load(Ordering::Relaxed) {
+                    return MockResponse::BackendGone;
+                }
+                let (head, logs) = chain.state.lock().unwrap().clone();
+                match method {
+                    "eth_blockNumber" => MockResponse::Result(format!("{:#x}", head).into()),
+                    "eth_getLogs" => {
+                        let (filter,): (Filter,) = serde_json::from_value(params).unwrap();
+                        let blocks =
+                            filter.get_from_block().unwrap()..=filter.get_to_block().unwrap();
+                        let logs: Vec<_> = logs
+                            .into_iter()
+                            .filter(|log| blocks.contains(&log.block_number.unwrap()))
+                            .collect();
+                        MockResponse::Result(serde_json::to_value(logs).unwrap())
+                    }
+                    _ => MockResponse::Error(-32601, "method not found"),
+                }
+            })
+        }
+    }
+
+    fn log(block_number: u64, block_hash: u8) -> Log {
+        Log {
+            block_number: Some(block_number),
+            block_hash: Some(B256::repeat_byte(block_hash)),
+            log_index: Some(0),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polling_log_subscription() {
+        let chain = MockChain::default();
+        chain.state.lock().unwrap().0 = 10;
+        let provider = RootProvider::<_, Ethereum>::new(RpcClient::new(chain.transport(), true));
+        let mut subscription = PollingLogSubscriber::new(provider, Filter::new())
+            .with_poll_interval(Duration::from_millis(10))
+            .subscribe()
+            .await
+            .unwrap();
+
+        *chain.state.lock().unwrap() = (11, vec![log(11, 1)]);
+        assert_eq!(subscription.recv().await, Some(LogEvent::Added(log(11, 1))));
+
+        // Block 11 is replaced, and its log is polled again as it is within the reorg depth
+        *chain.state.lock().unwrap() = (12, vec![log(11, 2), log(12, 3)]);
+        assert_eq!(
+            subscription.recv().await,
+            Some(LogEvent::Removed(log(11, 1)))
+        );
+        assert_eq!(subscription.recv().await, Some(LogEvent::Added(log(11, 2))));
+        assert_eq!(subscription.recv().await, Some(LogEvent::Added(log(12, 3))));
+
+        // Logs polled again are not repeated
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        *chain.state.lock().unwrap() = (13, vec![log(11, 2), log(12, 3), log(13, 4)]);
+        assert_eq!(subscription.recv().await, Some(LogEvent::Added(log(13, 4))));
+        assert!(subscription.liveness().is_alive());
+
+        // Reorganisations deeper than the last block seen are detected as well
+        *chain.state.lock().unwrap() = (14, vec![log(11, 2), log(12, 5), log(13, 6), log(14, 7)]);
+        for event in [
+            LogEvent::Removed(log(13, 4)),
+            LogEvent::Removed(log(12, 3)),
+            LogEvent::Added(log(12, 5)),
+            LogEvent::Added(log(13, 6)),
+            LogEvent::Added(log(14, 7)),
+        ] {
+            assert_eq!(subscription.recv().await, Some(event));
+        }
+
+        // Failed polls are reported until the node answers again
+        chain.down.store(true, Ordering::Relaxed);
+        for _ in 0..100 {
+            if !subscription.liveness().is_alive() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!subscription.liveness().is_alive());
+        chain.state.lock().unwrap().0 = 15;
+        chain.state.lock().unwrap().1.push(log(15, 8));
+        chain.down.store(false, Ordering::Relaxed);
+        assert_eq!(subscription.recv().await, Some(LogEvent::Added(log(15, 8))));
+        assert!(subscription.liveness().is_alive());
+    }
+}
@@ -0,0 +1,402 @@
+//! Log subscription reconnecting with backoff when its connection drops, and backfilling the
+//! logs missed in the meantime.
+use alloy_network::Ethereum;
+use alloy_primitives::B256;
+use alloy_provider::Provider;
+use alloy_pubsub::Subscription;
+use alloy_rpc_types::{Filter, Log};
+use alloy_transport::{Transport, TransportResult};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use super::{LogEvent, LogSubscription, EVENT_CHANNEL_CAPACITY};
+use crate::node_api::health::Liveness;
+
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+pub const DEFAULT_BACKFILL_BLOCK_RANGE: u64 = 10_000;
+pub const DEFAULT_REORG_DEPTH: u64 = 64;
+
+/// Subscribes to the logs matching a filter, reconnecting whenever the subscription closes.
+///
+/// After reconnecting, or when the subscription lagged behind, the logs from `reorg_depth`
+/// blocks before the last block seen to the latest block are fetched with `eth_getLogs`.
+/// Logs are only delivered once, and logs of the last `reorg_depth` blocks that disappear
+/// from the chain, either because the node marks them as removed or because a backfill no
+/// longer returns them, are delivered again as [`LogEvent::Removed`]. The removals found by
+/// a backfill are delivered newest first.
+#[derive(Debug, Clone)]
+pub struct ResilientLogSubscriber<P, T> {
+    provider: P,
+    filter: Filter,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    backfill_block_range: u64,
+    reorg_depth: u64,
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<P, T> ResilientLogSubscriber<P, T>
+where
+    P: Provider<T, Ethereum> + Clone + 'static,
+    T: Transport + Clone,
+{
+    pub fn new(provider: P, filter: Filter) -> Self {
+        Self {
+            provider,
+            filter,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            backfill_block_range: DEFAULT_BACKFILL_BLOCK_RANGE,
+            reorg_depth: DEFAULT_REORG_DEPTH,
+            _transport: PhantomData,
+        }
+    }
+
+    /// Waits `initial_backoff` before retrying a failed reconnection, doubling the wait after
+    /// every failure up to `max_backoff`.
+    pub fn with_backoff(mut self, initial_backoff: Duration, max_backoff: Duration) -> Self {
+        self.initial_backoff = initial_backoff;
+        self.max_backoff = max_backoff.max(initial_backoff);
+        self
+    }
+
+    /// Fetches missed logs in `eth_getLogs` requests spanning at most this many blocks.
+    pub fn with_backfill_block_range(mut self, backfill_block_range: u64) -> Self {
+        self.backfill_block_range = backfill_block_range.max(1);
+        self
+    }
+
+    /// Tracks the logs of this many blocks to de-duplicate them and detect their removal.
+    pub fn with_reorg_depth(mut self, reorg_depth: u64) -> Self {
+        self.reorg_depth = reorg_depth;
+        self
+    }
+
+    /// Subscribes to the logs, failing if the first subscription cannot be made. Later
+    /// reconnections are retried until the returned subscription is dropped, which stops
+    /// them.
+    pub async fn subscribe(self) -> TransportResult<LogSubscription> {
+        let subscription = self.provider.subscribe_logs(&self.filter).await?;
+        let from_block = self.provider.get_block_number().await?;
+        let (sender, receiver) = mpsc::channel(EVENT_CHANNEL_CAPACITY);
+        let connected = Liveness::new(true);
+        let task = tokio::spawn(self.run(subscription, from_block, sender, connected.clone()));
+        Ok(LogSubscription {
+            receiver,
+            task,
+            connected,
+        })
+    }
+
+    async fn run(
+        self,
+        mut subscription: Subscription<Log>,
+        from_block: u64,
+        sender: mpsc::Sender<LogEvent>,
+        connected: Liveness,
+    ) {
+        let mut tracker = LogTracker::new(from_block, self.reorg_depth);
+        loop {
+            let closed = loop {
+                let log = tokio::select! {
+                    log = subscription.recv() => log,
+                    _ = sender.closed() => return,
+                };
+                match log {
+                    Ok(log) => {
+                        if let Some(event) = tracker.observe(log) {
+                            if sender.send(event).await.is_err() {
+                                return;
+                            }
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        log::warn!("Log subscription lagged, skipped {} logs", skipped);
+                        break false;
+                    }
+                    Err(RecvError::Closed) => {
+                        log::warn!("Log subscription closed, reconnecting");
+                        connected.set_alive(false);
+                        break true;
+                    }
+                }
+            };
+
+            let mut backoff = self.initial_backoff;
+            let backfilled = loop {
+                let recovered = async {
+                    if closed {
+                        subscription = self.provider.subscribe_logs(&self.filter).await?;
+                    }
+                    get_logs_since(
+                        &self.provider,
+                        &self.filter,
+                        tracker.backfill_from(),
+                        self.backfill_block_range,
+                    )
+                    .await
+                };
+                match recovered.await {
+                    Ok(backfilled) => {
+                        connected.set_alive(true);
+                        break backfilled;
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to recover log subscription, retrying in {:?}: {}",
+                            backoff,
+                            e
+                        );
+                        tokio::select! {
+                            _ = tokio::time::sleep(backoff) => {}
+                            _ = sender.closed() => return,
+                        }
+                        backoff = (backoff * 2).min(self.max_backoff);
+                    }
+                }
+            };
+            let (from_block, to_block, logs) = backfilled;
+            log::debug!(
+                "Backfilled {} logs from blocks {} to {}",
+                logs.len(),
+                from_block,
+                to_block
+            );
+            for event in tracker.reconcile(from_block, to_block, logs) {
+                if sender.send(event).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Fetches the logs matching `filter` from `from_block` to the latest block, in requests
+/// spanning at most `block_range` blocks. Returns the range fetched with the logs.
+pub(super) async fn get_logs_since<P, T>(
+    provider: &P,
+    filter: &Filter,
+    from_block: u64,
+    block_range: u64,
+) -> TransportResult<(u64, u64, Vec<Log>)>
+where
+    P: Provider<T, Ethereum>,
+    T: Transport + Clone,
+{
+    let to_block = provider.get_block_number().await?;
+    let mut logs = Vec::new();
+    let mut start = from_block;
+    while start <= to_block {
+        let end = to_block.min(start.saturating_add(block_range - 1));
+        let filter = filter.clone().from_block(start).to_block(end);
+        logs.extend(provider.get_logs(&filter).await?);
+        start = end + 1;
+    }
+    Ok((from_block, to_block, logs))
+}
+
+type LogKey = (Option<B256>, Option<u64>);
+
+fn log_key(log: &Log) -> LogKey {
+    (log.block_hash, log.log_index)
+}
+
+/// Logs delivered in the last `reorg_depth` blocks.
+pub(super) struct LogTracker {
+    /// Block the subscription started at, before which no logs are delivered.
+    start: u64,
+    /// The latest block that was backfilled or a log was seen in.
+    cursor: u64,
+    reorg_depth: u64,
+    recent: BTreeMap<u64, Vec<Log>>,
+}
+
+impl LogTracker {
+    pub(super) fn new(from_block: u64, reorg_depth: u64) -> Self {
+        Self {
+            start: from_block,
+            cursor: from_block,
+            reorg_depth,
+            recent: BTreeMap::new(),
+        }
+    }
+
+    /// Block the next backfill starts at, so that it covers every tracked block and the
+    /// reorganisations of any of them are detected.
+    pub(super) fn backfill_from(&self) -> u64 {
+        self.cursor.saturating_sub(self.reorg_depth).max(self.start)
+    }
+
+    /// Returns the event to deliver for a log from the subscription, if it is not a
+    /// duplicate.
+    fn observe(&mut self, log: Log) -> Option<LogEvent> {
+        let Some(block_number) = log.block_number else {
+            // Pending logs cannot be reorganised out of the chain
+            return Some(LogEvent::Added(log));
+        };
+        let key = log_key(&log);
+        let block_logs = self.recent.entry(block_number).or_default();
+        let seen = block_logs.iter().position(|seen| log_key(seen) == key);
+        let event = match (log.removed, seen) {
+            (true, Some(index)) => Some(LogEvent::Removed(block_logs.remove(index))),
+            (false, None) => {
+                block_logs.push(log.clone());
+                Some(LogEvent::Added(log))
+            }
+            (true, None) | (false, Some(_)) => None,
+        };
+        if block_logs.is_empty() {
+            self.recent.remove(&block_number);
+        }
+        self.advance(block_number);
+        event
+    }
+
+    /// Returns the events turning the logs delivered for `from_block..=to_block` into the
+    /// backfilled `logs`: removals for the logs no longer in the chain, newest first so that
+    /// they undo the logs in reverse, then additions for the missed logs.
+    pub(super) fn reconcile(
+        &mut self,
+        from_block: u64,
+        to_block: u64,
+        mut logs: Vec<Log>,
+    ) -> Vec<LogEvent> {
+        // The node may be behind the blocks already seen
+        if to_block < from_block {
+            return Vec::new();
+        }
+        let backfilled: HashSet<LogKey> = logs.iter().map(log_key).collect();
+        let mut removed_logs = Vec::new();
+        for block_logs in self
+            .recent
+            .range_mut(from_block..=to_block)
+            .map(|(_, logs)| logs)
+        {
+            let (kept, removed) = block_logs
+                .drain(..)
+                .partition(|log| backfilled.contains(&log_key(log)));
+            *block_logs = kept;
+            removed_logs.extend(removed);
+        }
+        self.recent.retain(|_, block_logs| !block_logs.is_empty());
+        removed_logs.sort_by_key(|log| Reverse((log.block_number, log.log_index)));
+        let mut events: Vec<_> = removed_logs.into_iter().map(LogEvent::Removed).collect();
+
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+        events.extend(logs.into_iter().filter_map(|log| self.observe(log)));
+        self.advance(to_block);
+        events
+    }
+
+    fn advance(&mut self, block_number: u64) {
+        self.cursor = self.cursor.max(block_number);
+        let oldest = self.cursor.saturating_sub(self.reorg_depth);
+        self.recent = self.recent.split_off(&oldest);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(block_number: u64, block_hash: u8, log_index: u64) -> Log {
+        Log {
+            block_number: Some(block_number),
+            block_hash: Some(B256::repeat_byte(block_hash)),
+            log_index: Some(log_index),
+            ..Default::default()
+        }
+    }
+
+    fn removed(mut log: Log) -> Log {
+        log.removed = true;
+        log
+    }
+
+    #[test]
+    fn test_log_tracker_deduplicates_and_removes() {
+        let mut tracker = LogTracker::new(10, 4);
+
+        assert_eq!(
+            tracker.observe(log(10, 1, 0)),
+            Some(LogEvent::Added(log(10, 1, 0)))
+        );
+        assert_eq!(tracker.observe(log(10, 1, 0)), None);
+        assert_eq!(
+            tracker.observe(log(11, 2, 0)),
+            Some(LogEvent::Added(log(11, 2, 0)))
+        );
+        assert_eq!(tracker.cursor, 11);
+
+        assert_eq!(
+            tracker.observe(removed(log(11, 2, 0))),
+            Some(LogEvent::Removed(log(11, 2, 0)))
+        );
+        assert_eq!(tracker.observe(removed(log(11, 2, 0))), None);
+        assert_eq!(tracker.observe(removed(log(12, 3, 0))), None);
+
+        // Logs older than the reorg depth are forgotten
+        tracker.observe(log(20, 4, 0));
+        assert_eq!(tracker.recent.keys().copied().collect::<Vec<_>>(), vec![20]);
+    }
+
+    #[test]
+    fn test_log_tracker_reconciles_backfill() {
+        let mut tracker = LogTracker::new(10, 64);
+        tracker.observe(log(10, 1, 0));
+        tracker.observe(log(11, 2, 0));
+        tracker.observe(log(11, 2, 1));
+
+        // Block 11 was reorganised while disconnected, and block 12 was missed
+        let events = tracker.reconcile(11, 12, vec![log(12, 4, 0), log(11, 3, 0), log(11, 2, 1)]);
+        assert_eq!(
+            events,
+            vec![
+                LogEvent::Removed(log(11, 2, 0)),
+                LogEvent::Added(log(11, 3, 0)),
+                LogEvent::Added(log(12, 4, 0)),
+            ]
+        );
+        assert_eq!(tracker.cursor, 12);
+
+        // Logs backfilled again, or also delivered by the new subscription, are not repeated
+        assert!(tracker.reconcile(12, 12, vec![log(12, 4, 0)]).is_empty());
+        assert_eq!(tracker.observe(log(12, 4, 0)), None);
+        assert_eq!(tracker.reconcile(13, 13, vec![]), vec![]);
+        assert_eq!(tracker.cursor, 13);
+    }
+
+    #[test]
+    fn test_log_tracker_reconciles_reorg_below_cursor() {
+        let mut tracker = LogTracker::new(10, 4);
+        assert_eq!(tracker.backfill_from(), 10);
+        tracker.observe(log(10, 1, 0));
+        tracker.observe(log(12, 2, 0));
+        tracker.observe(log(16, 3, 0));
+        assert_eq!(tracker.backfill_from(), 12);
+
+        // Blocks 12 to 16 were reorganised while disconnected, and block 17 was missed
+        let from_block = tracker.backfill_from();
+        let events = tracker.reconcile(
+            from_block,
+            17,
+            vec![log(12, 4, 0), log(14, 5, 0), log(17, 6, 0)],
+        );
+        assert_eq!(
+            events,
+            vec![
+                LogEvent::Removed(log(16, 3, 0)),
+                LogEvent::Removed(log(12, 2, 0)),
+                LogEvent::Added(log(12, 4, 0)),
+                LogEvent::Added(log(14, 5, 0)),
+                LogEvent::Added(log(17, 6, 0)),
+            ]
+        );
+        assert_eq!(tracker.backfill_from(), 13);
+    }
+}
@@ -0,0 +1,362 @@
+//! TLS termination shared by the HTTP servers in this crate.
+//!
+//! Connections are served with hyper's auto builder, which speaks HTTP/1.1 and HTTP/2 (with
+//! prior knowledge in plaintext, negotiated through ALPN over TLS).
+use hyper::body::{Body, Incoming};
+use hyper::rt::{Read, Write};
+use hyper::service::Service;
+use hyper::{Request, Response};
+use hyper_util::server::conn::auto;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::client::danger::HandshakeSignatureValid;
+use tokio_rustls::rustls::crypto::{
+    ring, verify_tls12_signature, verify_tls13_signature, WebPkiSupportedAlgorithms,
+};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, UnixTime};
+use tokio_rustls::rustls::server::danger::{ClientCertVerified, ClientCertVerifier};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{
+    self, CertificateError, DigitallySignedStruct, DistinguishedName, RootCertStore, ServerConfig,
+    SignatureScheme,
+};
+use tokio_rustls::TlsAcceptor;
+use tokio_util::sync::CancellationToken;
+
+use crate::node_api::tokiort::{TokioExecutor, TokioIo};
+
+#[derive(Debug, Error)]
+pub enum TlsError {
+    #[error("Failed to read {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("No certificates found in {0}")]
+    NoCertificates(PathBuf),
+    #[error("No private key found in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("Invalid client certificate verifier: {0}")]
+    ClientVerifier(String),
+    #[error("{0}")]
+    Rustls(#[from] rustls::Error),
+}
+
+/// How a TLS server authenticates its clients.
+#[derive(Debug, Clone)]
+pub enum ClientAuth {
+    /// Require a client certificate issued by one of the CAs in this PEM file.
+    CaCertificates(PathBuf),
+    /// Require one of the exact certificates in these PEM files.
+    PinnedCertificates(Vec<PathBuf>),
+}
+
+/// Server TLS configuration loaded from PEM files.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Certificate chain, leaf first.
+    pub cert_path: PathBuf,
+    /// PKCS#8, PKCS#1 or SEC1 private key for the leaf certificate.
+    pub key_path: PathBuf,
+    /// Client authentication for mutual TLS, if any.
+    pub client_auth: Option<ClientAuth>,
+}
+
+impl TlsConfig {
+    pub fn new(cert_path: impl Into<PathBuf>, key_path: impl Into<PathBuf>) -> Self {
+        Self {
+            cert_path: cert_path.into(),
+            key_path: key_path.into(),
+            client_auth: None,
+        }
+    }
+
+    pub fn with_client_ca(mut self, ca_cert_path: impl Into<PathBuf>) -> Self {
+        self.client_auth = Some(ClientAuth::CaCertificates(ca_cert_path.into()));
+        self
+    }
+
+    pub fn with_pinned_client_certificates(mut self, cert_paths: Vec<PathBuf>) -> Self {
+        self.client_auth = Some(ClientAuth::PinnedCertificates(cert_paths));
+        self
+    }
+
+    /// Loads the certificates and key and builds an acceptor offering HTTP/2 and HTTP/1.1.
+    pub fn build_acceptor(&self) -> Result<TlsAcceptor, TlsError> {
+        let provider = Arc::new(ring::default_provider());
+        let certs = read_certs(&self.cert_path)?;
+        let key = read_private_key(&self.key_path)?;
+
+        let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+            .with_safe_default_protocol_versions()?;
+        let builder = match &self.client_auth {
+            None => builder.with_no_client_auth(),
+            Some(ClientAuth::CaCertificates(path)) => {
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(path)? {
+                    roots.add(cert)?;
+                }
+                let verifier = WebPkiClientVerifier::builder_with_provider(
+                    Arc::new(roots),
+                    Arc::clone(&provider),
+                )
+                .build()
+                .map_err(|e| TlsError::ClientVerifier(e.to_string()))?;
+                builder.with_client_cert_verifier(verifier)
+            }
+            Some(ClientAuth::PinnedCertificates(paths)) => {
+                let mut pinned = Vec::new();
+                for path in paths {
+                    pinned.extend(read_certs(path)?);
+                }
+                builder.with_client_cert_verifier(Arc::new(PinnedClientCertVerifier {
+                    pinned,
+                    algorithms: provider.signature_verification_algorithms,
+                }))
+            }
+        };
+
+        let mut config = builder.with_single_cert(certs, key)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(TlsAcceptor::from(Arc::new(config)))
+    }
+}
+
+/// Serves HTTP/1.1 and HTTP/2 on an accepted connection, after a TLS handshake if
+/// `tls_acceptor` is set. Once `shutdown` is cancelled the connection finishes its in-flight
+/// requests and closes. Errors are logged, as there is no one to report them to.
+pub(crate) async fn serve_connection<S, B>(
+    stream: TcpStream,
+    tls_acceptor: Option<TlsAcceptor>,
+    service: S,
+    shutdown: CancellationToken,
+) where
+    S: Service<Request<Incoming>, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>>,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    let result = match tls_acceptor {
+        Some(tls_acceptor) => match tls_acceptor.accept(stream).await {
+            Ok(stream) => serve_io(TokioIo::new(stream), service, shutdown).await,
+            Err(err) => {
+                log::info!("TLS handshake failed: {}", err);
+                return;
+            }
+        },
+        None => serve_io(TokioIo::new(stream), service, shutdown).await,
+    };
+    if let Err(err) = result {
+        log::info!("Error serving connection: {:?}", err);
+    }
+}
+
+async fn serve_io<I, S, B>(
+    io: I,
+    service: S,
+    shutdown: CancellationToken,
+) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    I: Read + Write + Unpin + Send + 'static,
+    S: Service<Request<Incoming>, Response = Response<B>> + Send + 'static,
+    S::Future: Send + 'static,
+    S::Error: Into<Box<dyn Error + Send + Sync>>,
+    B: Body + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<Box<dyn Error + Send + Sync>>,
+{
+    let builder = auto::Builder::new(TokioExecutor);
+    let connection = builder.serve_connection(io, service);
+    tokio::pin!(connection);
+    tokio::select! {
+        result = connection.as_mut() => result,
+        _ = shutdown.cancelled() => {
+            connection.as_mut().graceful_shutdown();
+            connection.await
+        }
+    }
+}
+
+fn read_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, TlsError> {
+    let io_error = |source| TlsError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    let certs = rustls_pemfile::certs(&mut reader)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_error)?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.to_path_buf()));
+    }
+    Ok(certs)
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, TlsError> {
+    let io_error = |source| TlsError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let mut reader = BufReader::new(File::open(path).map_err(io_error)?);
+    rustls_pemfile::private_key(&mut reader)
+        .map_err(io_error)?
+        .ok_or_else(|| TlsError::NoPrivateKey(path.to_path_buf()))
+}
+
+/// Accepts exactly the pinned client certificates, which may be self-signed.
+#[derive(Debug)]
+struct PinnedClientCertVerifier {
+    pinned: Vec<CertificateDer<'static>>,
+    algorithms: WebPkiSupportedAlgorithms,
+}
+
+impl ClientCertVerifier for PinnedClientCertVerifier {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> Result<ClientCertVerified, rustls::Error> {
+        if self.pinned.iter().any(|cert| cert == end_entity) {
+            Ok(ClientCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::ClientConfig;
+    use tokio_rustls::TlsConnector;
+
+    fn testdata(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("testdata/tls")
+            .join(name)
+    }
+
+    /// Runs a handshake against `tls_config`, presenting the named client certificate, and
+    /// returns whether the server accepted the connection.
+    async fn server_accepts(tls_config: &TlsConfig, client_cert: Option<&str>) -> bool {
+        let acceptor = tls_config.build_acceptor().unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            acceptor.accept(stream).await.is_ok()
+        });
+
+        let mut roots = RootCertStore::empty();
+        for cert in read_certs(&testdata("ca.crt")).unwrap() {
+            roots.add(cert).unwrap();
+        }
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let client_config = match client_cert {
+            Some(name) => builder
+                .with_client_auth_cert(
+                    read_certs(&testdata(&format!("{}.crt", name))).unwrap(),
+                    read_private_key(&testdata(&format!("{}.key", name))).unwrap(),
+                )
+                .unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        let stream = TcpStream::connect(addr).await.unwrap();
+        // The client may finish its side of the handshake before the server rejects it
+        let _ = TlsConnector::from(Arc::new(client_config))
+            .connect(ServerName::try_from("localhost").unwrap(), stream)
+            .await;
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_pinned_client_certificates() {
+        let tls_config = TlsConfig::new(testdata("server.crt"), testdata("server.key"))
+            .with_pinned_client_certificates(vec![testdata("client.crt")]);
+
+        assert!(server_accepts(&tls_config, Some("client")).await);
+        assert!(!server_accepts(&tls_config, Some("other")).await);
+        assert!(!server_accepts(&tls_config, None).await);
+    }
+
+    #[tokio::test]
+    async fn test_client_auth_is_optional() {
+        let tls_config = TlsConfig::new(testdata("server.crt"), testdata("server.key"));
+
+        assert!(server_accepts(&tls_config, None).await);
+    }
+
+    #[test]
+    fn test_pinned_client_cert_verifier() {
+        let client = read_certs(&testdata("client.crt")).unwrap().remove(0);
+        let other = read_certs(&testdata("other.crt")).unwrap().remove(0);
+        let verifier = PinnedClientCertVerifier {
+            pinned: vec![client.clone()],
+            algorithms: ring::default_provider().signature_verification_algorithms,
+        };
+
+        assert!(verifier
+            .verify_client_cert(&client, &[], UnixTime::now())
+            .is_ok());
+        assert!(matches!(
+            verifier.verify_client_cert(&other, &[], UnixTime::now()),
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure
+            ))
+        ));
+        // Pinning is by exact certificate, not by the presented chain
+        assert!(verifier
+            .verify_client_cert(&other, &[client], UnixTime::now())
+            .is_err());
+    }
+
+    #[test]
+    fn test_missing_files() {
+        assert!(matches!(
+            TlsConfig::new(testdata("missing.crt"), testdata("server.key")).build_acceptor(),
+            Err(TlsError::Io { .. })
+        ));
+        assert!(matches!(
+            TlsConfig::new(testdata("server.crt"), testdata("server.crt")).build_acceptor(),
+            Err(TlsError::NoPrivateKey(_))
+        ));
+    }
+}